@@ -0,0 +1,148 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Expr, Fields, Lit, LitStr, Meta, Token};
+
+/// One `key = value` of a `#[guc(...)]` attribute
+struct GucArg {
+    key: syn::Ident,
+    value: Expr,
+}
+
+impl Parse for GucArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(GucArg { key, value })
+    }
+}
+
+fn guc_args(attrs: &[Attribute]) -> syn::Result<Vec<GucArg>> {
+    let mut args = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("guc")) {
+        args.extend(attr.parse_args_with(Punctuated::<GucArg, Token![,]>::parse_terminated)?);
+    }
+    Ok(args)
+}
+
+fn string_arg(arg: &GucArg) -> syn::Result<LitStr> {
+    match &arg.value {
+        Expr::Lit(syn::ExprLit { lit: Lit::Str(s), .. }) => Ok(s.clone()),
+        other => Err(syn::Error::new(other.span(), format!("`{}` must be a string", arg.key))),
+    }
+}
+
+/// Splits doc comments into their first paragraph, used as the GUC's short description, and
+/// the rest as its long description
+fn descriptions(attrs: &[Attribute]) -> syn::Result<(String, String)> {
+    let mut lines = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("doc")) {
+        if let Meta::NameValue(syn::MetaNameValue { lit: Lit::Str(doc), .. }) = attr.parse_meta()? {
+            lines.push(doc.value().trim().to_string());
+        }
+    }
+
+    let mut paragraphs = lines
+        .split(|line| line.is_empty())
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| paragraph.join(" "));
+    let short_description = paragraphs.next().unwrap_or_default();
+    let long_description = paragraphs.collect::<Vec<_>>().join("\n\n");
+    Ok((short_description, long_description))
+}
+
+pub(crate) fn impl_postgres_guc(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match ast.data {
+        Data::Struct(syn::DataStruct { fields: Fields::Named(fields), .. }) => fields.named,
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(PostgresGuc)] can only be applied to structs with named fields",
+            ))
+        }
+    };
+
+    let mut prefix = None;
+    for arg in guc_args(&ast.attrs)? {
+        match arg.key.to_string().as_str() {
+            "prefix" => prefix = Some(string_arg(&arg)?.value()),
+            _ => return Err(syn::Error::new(arg.key.span(), "expected `prefix`")),
+        }
+    }
+    let prefix = prefix.ok_or_else(|| {
+        syn::Error::new(
+            ast.ident.span(),
+            "#[derive(PostgresGuc)] requires a `#[guc(prefix = \"...\")]` for its GUCs' names",
+        )
+    })?;
+
+    let mut definitions = TokenStream::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut name = ident.to_string();
+        let mut min = quote! { None };
+        let mut max = quote! { None };
+        let mut unit = quote! { None };
+        let mut context = quote! { ::pgrx::guc::GucContext::Userset };
+        let mut flags = quote! { ::pgrx::guc::GucFlags::default() };
+        for arg in guc_args(&field.attrs)? {
+            let value = &arg.value;
+            match arg.key.to_string().as_str() {
+                "name" => name = string_arg(&arg)?.value(),
+                "min" => min = quote! { Some(#value) },
+                "max" => max = quote! { Some(#value) },
+                "unit" => unit = quote! { Some(#value) },
+                "context" => context = quote! { #value },
+                "flags" => flags = quote! { #value },
+                _ => {
+                    return Err(syn::Error::new(
+                        arg.key.span(),
+                        "expected one of `name`, `min`, `max`, `unit`, `context` or `flags`",
+                    ))
+                }
+            }
+        }
+
+        let (short_description, long_description) = descriptions(&field.attrs)?;
+        if short_description.is_empty() {
+            return Err(syn::Error::new(
+                ident.span(),
+                "#[derive(PostgresGuc)] fields need a doc comment, which describes their GUC",
+            ));
+        }
+
+        let name = format!("{prefix}.{name}");
+        definitions.extend(quote! {
+            ::pgrx::guc::DefineGuc::define(
+                &self.#ident,
+                #name,
+                #short_description,
+                #long_description,
+                ::pgrx::guc::GucOptions { min: #min, max: #max, unit: #unit, context: #context, flags: #flags },
+            );
+        });
+    }
+
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Registers each of these settings as a GUC, which should be done from `_PG_init()`
+            pub fn register(&'static self) {
+                #definitions
+            }
+        }
+    })
+}
@@ -19,14 +19,17 @@ use syn::{parse_macro_input, Attribute, Data, DeriveInput, Item, ItemImpl};
 
 use operators::{impl_postgres_eq, impl_postgres_hash, impl_postgres_ord};
 use pgrx_sql_entity_graph::{
-    parse_extern_attributes, CodeEnrichment, ExtensionSql, ExtensionSqlFile, ExternArgs,
-    PgAggregate, PgExtern, PostgresEnum, PostgresType, Schema,
+    parse_extern_attributes, AccessMethodType, CodeEnrichment, ExtensionSql, ExtensionSqlFile,
+    ExternArgs, PgAccessMethod, PgAggregate, PgExtern, PgForeignDataWrapper, PgProcedure,
+    PostgresEnum, PostgresType, Schema,
 };
 
 use crate::rewriter::PgGuardRewriter;
 
+mod guc;
 mod operators;
 mod rewriter;
+mod spi;
 
 /// Declare a function as `#[pg_guard]` to indicate that it is called from a Postgres `extern "C"`
 /// function so that Rust `panic!()`s (and Postgres `elog(ERROR)`s) will be properly handled by `pgrx`
@@ -457,6 +460,15 @@ Optionally accepts the following attributes:
 * `parallel_safe`: Corresponds to [`PARALLEL SAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_unsafe`: Corresponds to [`PARALLEL UNSAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_restricted`: Corresponds to [`PARALLEL RESTRICTED`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+* `called_on_null_input`: Corresponds to [`CALLED ON NULL INPUT`](https://www.postgresql.org/docs/current/sql-createfunction.html), preventing `strict` from being inferred.  Every argument must then be an `Option<T>`.
+* `leakproof`: Corresponds to [`LEAKPROOF`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+* `cost`: Corresponds to [`COST`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+* `rows`: Corresponds to [`ROWS`](https://www.postgresql.org/docs/current/sql-createfunction.html), the estimated number of rows a set-returning function returns.
+* `set`: Corresponds to [`SET`](https://www.postgresql.org/docs/current/sql-createfunction.html), as in `set(work_mem = "64MB", "myext.setting" = 42)`.
+* `window`: Corresponds to [`WINDOW`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+  + The function may take a [`pgrx::window::WindowContext`](../pgrx/window/struct.WindowContext.html) argument to access other rows of the partition.
+* `materialize`: Return all of a `SetOfIterator` or `TableIterator`'s rows from a single call, in a tuplestore, instead of one row per call (see [materialize mode](https://www.postgresql.org/docs/current/xfunc-c.html#XFUNC-C-RETURN-SET)).
+* `support`: Corresponds to [`SUPPORT`](https://www.postgresql.org/docs/current/xfunc-optimization.html), naming another `#[pg_extern]` function (see [`pgrx::support::SupportRequest`](../pgrx/support/enum.SupportRequest.html)).
 * `no_guard`: Do not use `#[pg_guard]` with the function.
 * `sql`: Same arguments as [`#[pgrx(sql = ..)]`](macro@pgrx).
 * `name`: Specifies target function name. Defaults to Rust function name.
@@ -597,6 +609,56 @@ pub fn pg_extern(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Declare a function as a `CREATE PROCEDURE`, to be run with `CALL`.
+
+It accepts the same arguments as `#[pg_extern]`, except for those that only apply to functions
+(volatility, strictness, parallel safety, `cost`, `rows`, ...), and must return `()` or
+`Result<(), E>`.
+
+A procedure that takes a `pgrx::spi::CallContext` can commit and roll back transactions through
+`Spi::connect_nonatomic`, when Postgres runs it nonatomically. The `CallContext` isn't part of the
+SQL signature.
+
+```rust,ignore
+use pgrx::prelude::*;
+
+#[pg_procedure]
+fn insert_in_batches(call: CallContext, rows: i64, batch_size: i64) -> Result<(), spi::Error> {
+    Spi::connect_nonatomic(call, |mut spi| {
+        for i in 0..rows {
+            spi.client().update(
+                "INSERT INTO items VALUES ($1)",
+                None,
+                Some(vec![(PgBuiltInOids::INT8OID.oid(), i.into_datum())]),
+            )?;
+            if (i + 1) % batch_size == 0 {
+                spi.commit();
+            }
+        }
+        Ok(())
+    })
+}
+```
+*/
+#[proc_macro_attribute]
+pub fn pg_procedure(attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(attr: TokenStream, item: TokenStream) -> Result<TokenStream, syn::Error> {
+        let pg_procedure_item = PgProcedure::new(attr.into(), item.into())?;
+        Ok(pg_procedure_item.to_token_stream().into())
+    }
+
+    match wrapped(attr, item) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
 /**
 Generate necessary bindings for using the enum with PostgreSQL.
 
@@ -849,6 +911,85 @@ pub fn postgres_guc_enum(input: TokenStream) -> TokenStream {
     impl_guc_enum(ast).unwrap_or_else(|e| e.to_compile_error()).into()
 }
 
+/// Derives a `register()` function that defines each field of a struct of `GucSetting`s as a GUC,
+/// named `prefix.field_name`.
+///
+/// The first paragraph of a field's doc comment is its GUC's short description, and the rest is
+/// its long description.  Fields can be configured with `#[guc(...)]`:
+///
+/// * `name = "..."`: overrides the part of the GUC's name after the prefix
+/// * `min = ...` and `max = ...`: bounds for numeric, duration and memory-size settings
+/// * `unit = ...`: a `GucTimeUnit` or `GucMemoryUnit`, for duration and memory-size settings
+/// * `context = ...`: a `GucContext`, `GucContext::Userset` by default
+/// * `flags = ...`: `GucFlags`, none by default
+///
+/// ```rust,ignore
+/// use pgrx::guc::*;
+/// use std::time::Duration;
+///
+/// #[derive(PostgresGuc)]
+/// #[guc(prefix = "myext")]
+/// struct Settings {
+///     /// Whether to frobnicate
+///     enabled: GucSetting<bool>,
+///
+///     /// How long to wait for a frobnication
+///     ///
+///     /// Zero waits forever.
+///     #[guc(unit = GucTimeUnit::Seconds, max = Duration::from_secs(3600), context = GucContext::Suset)]
+///     timeout: GucSetting<Duration>,
+/// }
+///
+/// static SETTINGS: Settings = Settings {
+///     enabled: GucSetting::<bool>::new(true),
+///     timeout: GucSetting::<Duration>::new(Duration::from_secs(30)),
+/// };
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     SETTINGS.register();
+/// }
+/// ```
+#[proc_macro_derive(PostgresGuc, attributes(guc))]
+pub fn postgres_guc(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    guc::impl_postgres_guc(ast).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Derives `pgrx::spi::FromSpiRow`, to read a struct from a row returned by SPI.
+///
+/// Each field is read from the column of the same name, or the one given by
+/// `#[spi(rename = "...")]`.  `Option<T>` fields accept NULLs, and any other field raises
+/// `spi::Error::NullColumn` for them.
+///
+/// ```rust,ignore
+/// use pgrx::prelude::*;
+///
+/// #[derive(FromSpiRow)]
+/// struct Dog {
+///     name: String,
+///     #[spi(rename = "age_in_years")]
+///     age: i32,
+///     owner: Option<String>,
+/// }
+///
+/// fn dogs() -> Result<Vec<Dog>, spi::Error> {
+///     Spi::connect(|client| {
+///         client
+///             .select("SELECT name, age_in_years, owner FROM dogs", None, None)?
+///             .map(|row| row.into::<Dog>())
+///             .collect()
+///     })
+/// }
+/// ```
+#[proc_macro_derive(FromSpiRow, attributes(spi))]
+pub fn from_spi_row(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    spi::impl_from_spi_row(ast).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
 fn impl_guc_enum(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let mut stream = proc_macro2::TokenStream::new();
 
@@ -1058,6 +1199,112 @@ pub fn pg_aggregate(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Declare a `pgrx::fdw::ForeignDataWrapper` implementation on a type as a Postgres foreign data wrapper.
+
+Generates the `fdw_handler` and validator functions (named `{type}_handler` and `{type}_validator`,
+in snake case) and the `CREATE FOREIGN DATA WRAPPER {type}` statement that uses them.
+
+```rust,ignore
+use pgrx::prelude::*;
+
+struct NumbersFdw {
+    current: i64,
+}
+
+#[pg_fdw]
+impl ForeignDataWrapper for NumbersFdw {
+    type Row = (i64,);
+
+    fn begin_scan(_table: &PgForeignTable) -> Self {
+        NumbersFdw { current: 0 }
+    }
+
+    fn iterate_scan(&mut self) -> Option<Self::Row> {
+        self.current += 1;
+        (self.current <= 10).then(|| (self.current,))
+    }
+
+    fn re_scan(&mut self) {
+        self.current = 0;
+    }
+}
+```
+*/
+#[proc_macro_attribute]
+pub fn pg_fdw(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(item_impl: ItemImpl) -> Result<TokenStream, syn::Error> {
+        let sql_graph_entity_item = PgForeignDataWrapper::new(item_impl)?;
+
+        Ok(sql_graph_entity_item.to_token_stream().into())
+    }
+
+    let parsed_base = parse_macro_input!(item as syn::ItemImpl);
+    match wrapped(parsed_base) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a `pgrx::index_am::IndexAccessMethod` implementation on a type as a Postgres index access method.
+
+Generates the `index_am_handler` function (named `{type}_handler`, in snake case) and the
+`CREATE ACCESS METHOD {type} TYPE INDEX` statement that uses it.  Operator classes for the new
+access method can then be created with [`extension_sql!`](macro@extension_sql).
+*/
+#[proc_macro_attribute]
+pub fn pg_index_am(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(item_impl: ItemImpl) -> Result<TokenStream, syn::Error> {
+        let sql_graph_entity_item = PgAccessMethod::new(item_impl, AccessMethodType::Index)?;
+
+        Ok(sql_graph_entity_item.to_token_stream().into())
+    }
+
+    let parsed_base = parse_macro_input!(item as syn::ItemImpl);
+    match wrapped(parsed_base) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a `pgrx::table_am::TableAccessMethod` implementation on a type as a Postgres table access method.
+
+Generates the `table_am_handler` function (named `{type}_handler`, in snake case) and the
+`CREATE ACCESS METHOD {type} TYPE TABLE` statement that uses it.  Tables can then be created
+with `CREATE TABLE ... USING {type}`.  Requires Postgres 12 or later.
+*/
+#[proc_macro_attribute]
+pub fn pg_table_am(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    fn wrapped(item_impl: ItemImpl) -> Result<TokenStream, syn::Error> {
+        let sql_graph_entity_item = PgAccessMethod::new(item_impl, AccessMethodType::Table)?;
+
+        Ok(sql_graph_entity_item.to_token_stream().into())
+    }
+
+    let parsed_base = parse_macro_input!(item as syn::ItemImpl);
+    match wrapped(parsed_base) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
 /**
 A helper attribute for various contexts.
 
@@ -0,0 +1,95 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, LitStr, Token};
+
+/// One `key = "value"` of a `#[spi(...)]` attribute
+struct SpiArg {
+    key: syn::Ident,
+    value: LitStr,
+}
+
+impl Parse for SpiArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(SpiArg { key, value })
+    }
+}
+
+/// The `T` of an `Option<T>`, which reads a nullable column
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else { return None };
+    let last = path.path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub(crate) fn impl_from_spi_row(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match ast.data {
+        Data::Struct(syn::DataStruct { fields: Fields::Named(fields), .. }) => fields.named,
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(FromSpiRow)] can only be applied to structs with named fields",
+            ))
+        }
+    };
+
+    let mut field_values = TokenStream::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut column = ident.to_string();
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("spi")) {
+            for arg in attr.parse_args_with(Punctuated::<SpiArg, Token![,]>::parse_terminated)? {
+                match arg.key.to_string().as_str() {
+                    "rename" => column = arg.value.value(),
+                    _ => return Err(syn::Error::new(arg.key.span(), "expected `rename`")),
+                }
+            }
+        }
+
+        let value = match option_inner(&field.ty) {
+            Some(inner) => quote! { row.column::<#inner>(#column)? },
+            None => {
+                let ty = &field.ty;
+                quote! {
+                    row.column::<#ty>(#column)?.ok_or_else(|| {
+                        ::pgrx::spi::Error::NullColumn(::std::string::String::from(#column))
+                    })?
+                }
+            }
+        };
+        field_values.extend(quote! { #ident: #value, });
+    }
+
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pgrx::spi::FromSpiRow for #name #ty_generics #where_clause {
+            fn from_spi_row(row: &::pgrx::spi::SpiHeapTupleData<'_>) -> ::pgrx::spi::Result<Self> {
+                Ok(Self { #field_values })
+            }
+        }
+    })
+}
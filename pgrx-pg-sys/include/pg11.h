@@ -45,6 +45,7 @@
 #include "catalog/pg_tablespace.h"
 #include "catalog/pg_trigger.h"
 #include "catalog/pg_type.h"
+#include "commands/async.h"
 #include "commands/comment.h"
 #include "commands/dbcommands.h"
 #include "commands/defrem.h"
@@ -141,3 +142,6 @@
 #include "utils/syscache.h"
 #include "utils/typcache.h"
 #include "utils/rangetypes.h"
+#include "libpq/auth.h"
+#include "libpq/libpq.h"
+#include "windowapi.h"
@@ -299,6 +299,14 @@ mod all_versions {
     use std::str::FromStr;
 
     pub use crate::submodules::htup::*;
+    #[cfg(any(
+        feature = "pg12",
+        feature = "pg13",
+        feature = "pg14",
+        feature = "pg15",
+        feature = "pg16"
+    ))]
+    pub use crate::submodules::tuptable::*;
 
     /// this comes from `postgres_ext.h`
     pub const InvalidOid: crate::Oid = crate::Oid::INVALID;
@@ -315,6 +323,7 @@ mod all_versions {
     pub const FrozenTransactionId: super::TransactionId = 2 as super::TransactionId;
     pub const FirstNormalTransactionId: super::TransactionId = 3 as super::TransactionId;
     pub const MaxTransactionId: super::TransactionId = 0xFFFF_FFFF as super::TransactionId;
+    pub const InvalidMultiXactId: super::MultiXactId = 0 as super::MultiXactId;
 
     #[cfg(feature = "cshim")]
     #[pgrx_macros::pg_guard]
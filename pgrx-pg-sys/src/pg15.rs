@@ -64808,6 +64808,121 @@ extern "C" {
         output2: *mut *mut RangeType,
     ) -> bool;
 }
+pub const WINDOW_SEEK_CURRENT: u32 = 0;
+pub const WINDOW_SEEK_HEAD: u32 = 1;
+pub const WINDOW_SEEK_TAIL: u32 = 2;
+pub type WindowObject = *mut WindowObjectData;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetPartitionLocalMemory(
+        winobj: WindowObject,
+        sz: Size,
+    ) -> *mut ::std::os::raw::c_void;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetCurrentPosition(winobj: WindowObject) -> int64;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetPartitionRowCount(winobj: WindowObject) -> int64;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinSetMarkPosition(winobj: WindowObject, markpos: int64);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinRowsArePeers(winobj: WindowObject, pos1: int64, pos2: int64) -> bool;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetFuncArgInPartition(
+        winobj: WindowObject,
+        argno: ::std::os::raw::c_int,
+        relpos: ::std::os::raw::c_int,
+        seektype: ::std::os::raw::c_int,
+        set_mark: bool,
+        isnull: *mut bool,
+        isout: *mut bool,
+    ) -> Datum;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetFuncArgInFrame(
+        winobj: WindowObject,
+        argno: ::std::os::raw::c_int,
+        relpos: ::std::os::raw::c_int,
+        seektype: ::std::os::raw::c_int,
+        set_mark: bool,
+        isnull: *mut bool,
+        isout: *mut bool,
+    ) -> Datum;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetFuncArgCurrent(
+        winobj: WindowObject,
+        argno: ::std::os::raw::c_int,
+        isnull: *mut bool,
+    ) -> Datum;
+}
+extern "C" {
+    pub static mut notifyInterruptPending: sig_atomic_t;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn Async_Notify(
+        channel: *const ::std::os::raw::c_char,
+        payload: *const ::std::os::raw::c_char,
+    );
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn Async_Listen(channel: *const ::std::os::raw::c_char);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn Async_Unlisten(channel: *const ::std::os::raw::c_char);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn Async_UnlistenAll();
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn ProcessNotifyInterrupt(flush: bool);
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PQcommMethods {
+    pub comm_reset: ::std::option::Option<unsafe extern "C" fn()>,
+    pub flush: ::std::option::Option<unsafe extern "C" fn() -> ::std::os::raw::c_int>,
+    pub flush_if_writable: ::std::option::Option<unsafe extern "C" fn() -> ::std::os::raw::c_int>,
+    pub is_send_pending: ::std::option::Option<unsafe extern "C" fn() -> bool>,
+    pub putmessage: ::std::option::Option<
+        unsafe extern "C" fn(
+            msgtype: ::std::os::raw::c_char,
+            s: *const ::std::os::raw::c_char,
+            len: usize,
+        ) -> ::std::os::raw::c_int,
+    >,
+    pub putmessage_noblock: ::std::option::Option<
+        unsafe extern "C" fn(
+            msgtype: ::std::os::raw::c_char,
+            s: *const ::std::os::raw::c_char,
+            len: usize,
+        ),
+    >,
+}
+extern "C" {
+    pub static mut PqCommMethods: *const PQcommMethods;
+}
+pub type ClientAuthentication_hook_type =
+    ::std::option::Option<unsafe extern "C" fn(arg1: *mut Port, arg2: ::std::os::raw::c_int)>;
+extern "C" {
+    pub static mut ClientAuthentication_hook: ClientAuthentication_hook_type;
+}
 pub type __builtin_va_list = [__va_list_tag; 1usize];
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
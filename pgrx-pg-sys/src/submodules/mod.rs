@@ -20,6 +20,7 @@ pub mod pg_try;
 pub mod polyfill;
 pub(crate) mod thread_check;
 pub mod tupdesc;
+pub mod tuptable;
 
 pub mod utils;
 
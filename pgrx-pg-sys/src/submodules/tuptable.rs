@@ -0,0 +1,143 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Ports of the `static inline` `TupleTableSlot` functions from `executor/tuptable.h`.
+//!
+//! Postgres 11 exports these as regular functions, so we only need them for 12+, where slots
+//! became "abstract" and their behavior is dispatched through `slot->tts_ops`.
+#![cfg(any(
+    feature = "pg12",
+    feature = "pg13",
+    feature = "pg14",
+    feature = "pg15",
+    feature = "pg16"
+))]
+
+use crate::{slot_getsomeattrs_int, Datum, TupleTableSlot};
+
+/// Clear the contents of the specified [`TupleTableSlot`].
+///
+/// # Safety
+///
+/// Caller must ensure `slot` is a valid, non-null [`TupleTableSlot`] pointer
+#[inline(always)]
+pub unsafe fn ExecClearTuple(slot: *mut TupleTableSlot) -> *mut TupleTableSlot {
+    // static inline TupleTableSlot *
+    // ExecClearTuple(TupleTableSlot *slot)
+    // {
+    //     slot->tts_ops->clear(slot);
+    //
+    //     return slot;
+    // }
+
+    unsafe {
+        // SAFETY:  caller has asserted `slot` is a valid TupleTableSlot, and every slot has a
+        // `tts_ops` with a `clear` function
+        (*(*slot).tts_ops).clear.expect("TupleTableSlotOps has no `clear` function")(slot);
+    }
+    slot
+}
+
+/// Make the contents of the specified [`TupleTableSlot`] independent of any external resources,
+/// copying any by-reference values into the slot's own memory.
+///
+/// # Safety
+///
+/// Caller must ensure `slot` is a valid, non-null [`TupleTableSlot`] pointer
+#[inline(always)]
+pub unsafe fn ExecMaterializeSlot(slot: *mut TupleTableSlot) {
+    // static inline void
+    // ExecMaterializeSlot(TupleTableSlot *slot)
+    // {
+    //     slot->tts_ops->materialize(slot);
+    // }
+
+    unsafe {
+        // SAFETY:  caller has asserted `slot` is a valid TupleTableSlot, and every slot has a
+        // `tts_ops` with a `materialize` function
+        (*(*slot).tts_ops).materialize.expect("TupleTableSlotOps has no `materialize` function")(
+            slot,
+        );
+    }
+}
+
+/// Ensure the first `attnum` attributes of the specified [`TupleTableSlot`] are deformed into its
+/// `tts_values`/`tts_isnull` arrays.
+///
+/// # Safety
+///
+/// Caller must ensure `slot` is a valid, non-null [`TupleTableSlot`] pointer
+#[inline(always)]
+pub unsafe fn slot_getsomeattrs(slot: *mut TupleTableSlot, attnum: i32) {
+    // static inline void
+    // slot_getsomeattrs(TupleTableSlot *slot, int attnum)
+    // {
+    //     if (slot->tts_nvalid < attnum)
+    //         slot_getsomeattrs_int(slot, attnum);
+    // }
+
+    unsafe {
+        // SAFETY:  caller has asserted `slot` is a valid TupleTableSlot
+        if ((*slot).tts_nvalid as i32) < attnum {
+            slot_getsomeattrs_int(slot, attnum);
+        }
+    }
+}
+
+/// Ensure all of the attributes of the specified [`TupleTableSlot`] are deformed into its
+/// `tts_values`/`tts_isnull` arrays.
+///
+/// # Safety
+///
+/// Caller must ensure `slot` is a valid, non-null [`TupleTableSlot`] pointer
+#[inline(always)]
+pub unsafe fn slot_getallattrs(slot: *mut TupleTableSlot) {
+    // static inline void
+    // slot_getallattrs(TupleTableSlot *slot)
+    // {
+    //     slot_getsomeattrs(slot, slot->tts_tupleDescriptor->natts);
+    // }
+
+    unsafe {
+        // SAFETY:  caller has asserted `slot` is a valid TupleTableSlot, and it always has a tupdesc
+        slot_getsomeattrs(slot, (*(*slot).tts_tupleDescriptor).natts);
+    }
+}
+
+/// Fetch the value of the (1-based) attribute `attnum` from the specified [`TupleTableSlot`].
+///
+/// # Safety
+///
+/// Caller must ensure `slot` is a valid, non-null [`TupleTableSlot`] pointer, that `isnull` is a
+/// valid pointer, and that `attnum` is a user attribute number (greater than zero and no larger than
+/// the slot's tuple descriptor's `natts`)
+#[inline(always)]
+pub unsafe fn slot_getattr(slot: *mut TupleTableSlot, attnum: i32, isnull: *mut bool) -> Datum {
+    // static inline Datum
+    // slot_getattr(TupleTableSlot *slot, int attnum,
+    //              bool *isnull)
+    // {
+    //     AssertArg(attnum > 0);
+    //
+    //     if (attnum > slot->tts_nvalid)
+    //         slot_getsomeattrs(slot, attnum);
+    //
+    //     *isnull = slot->tts_isnull[attnum - 1];
+    //
+    //     return slot->tts_values[attnum - 1];
+    // }
+
+    debug_assert!(attnum > 0);
+    unsafe {
+        // SAFETY:  caller has asserted `slot` is valid and `attnum` is in bounds
+        slot_getsomeattrs(slot, attnum);
+        *isnull = *(*slot).tts_isnull.add(attnum as usize - 1);
+        *(*slot).tts_values.add(attnum as usize - 1)
+    }
+}
@@ -26,10 +26,18 @@ pub enum ExternArgs {
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
+    Leakproof,
+    CalledOnNullInput,
+    Materialize,
+    Procedure,
     Error(String),
     Schema(String),
     Name(String),
     Cost(String),
+    Rows(String),
+    Set(String, String),
+    Support(PositioningRef),
     Requires(Vec<PositioningRef>),
 }
 
@@ -47,11 +55,19 @@ impl core::fmt::Display for ExternArgs {
             ExternArgs::SecurityDefiner => write!(f, "SECURITY DEFINER"),
             ExternArgs::SecurityInvoker => write!(f, "SECURITY INVOKER"),
             ExternArgs::ParallelRestricted => write!(f, "PARALLEL RESTRICTED"),
+            ExternArgs::Window => write!(f, "WINDOW"),
+            ExternArgs::Leakproof => write!(f, "LEAKPROOF"),
+            ExternArgs::CalledOnNullInput => write!(f, "CALLED ON NULL INPUT"),
+            ExternArgs::Materialize => Ok(()),
+            ExternArgs::Procedure => Ok(()),
             ExternArgs::Error(_) => Ok(()),
             ExternArgs::NoGuard => Ok(()),
             ExternArgs::Schema(_) => Ok(()),
             ExternArgs::Name(_) => Ok(()),
             ExternArgs::Cost(cost) => write!(f, "COST {}", cost),
+            ExternArgs::Rows(rows) => write!(f, "ROWS {}", rows),
+            ExternArgs::Set(name, value) => write!(f, "SET {} TO {}", name, value),
+            ExternArgs::Support(_) => Ok(()),
             ExternArgs::Requires(_) => Ok(()),
         }
     }
@@ -72,6 +88,11 @@ impl ToTokens for ExternArgs {
             ExternArgs::ParallelSafe => tokens.append(format_ident!("ParallelSafe")),
             ExternArgs::ParallelUnsafe => tokens.append(format_ident!("ParallelUnsafe")),
             ExternArgs::ParallelRestricted => tokens.append(format_ident!("ParallelRestricted")),
+            ExternArgs::Window => tokens.append(format_ident!("Window")),
+            ExternArgs::Leakproof => tokens.append(format_ident!("Leakproof")),
+            ExternArgs::CalledOnNullInput => tokens.append(format_ident!("CalledOnNullInput")),
+            ExternArgs::Materialize => tokens.append(format_ident!("Materialize")),
+            ExternArgs::Procedure => tokens.append(format_ident!("Procedure")),
             ExternArgs::Error(_s) => {
                 tokens.append_all(
                     quote! {
@@ -104,6 +125,30 @@ impl ToTokens for ExternArgs {
                     .to_token_stream(),
                 );
             }
+            ExternArgs::Rows(rows) => {
+                tokens.append_all(
+                    quote! {
+                        Rows(String::from(#rows))
+                    }
+                    .to_token_stream(),
+                );
+            }
+            ExternArgs::Set(name, value) => {
+                tokens.append_all(
+                    quote! {
+                        Set(String::from(#name), String::from(#value))
+                    }
+                    .to_token_stream(),
+                );
+            }
+            ExternArgs::Support(item) => {
+                tokens.append_all(
+                    quote! {
+                        Support(#item)
+                    }
+                    .to_token_stream(),
+                );
+            }
             ExternArgs::Requires(items) => {
                 tokens.append_all(
                     quote! {
@@ -141,6 +186,10 @@ pub fn parse_extern_attributes(attr: TokenStream) -> HashSet<ExternArgs> {
                     "parallel_safe" => args.insert(ExternArgs::ParallelSafe),
                     "parallel_unsafe" => args.insert(ExternArgs::ParallelUnsafe),
                     "parallel_restricted" => args.insert(ExternArgs::ParallelRestricted),
+                    "window" => args.insert(ExternArgs::Window),
+                    "leakproof" => args.insert(ExternArgs::Leakproof),
+                    "called_on_null_input" => args.insert(ExternArgs::CalledOnNullInput),
+                    "materialize" => args.insert(ExternArgs::Materialize),
                     "error" => {
                         let _punc = itr.next().unwrap();
                         let literal = itr.next().unwrap();
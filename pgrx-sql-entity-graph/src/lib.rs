@@ -25,11 +25,16 @@ pub use extension_sql::entity::{ExtensionSqlEntity, SqlDeclaredEntity};
 pub use extension_sql::{ExtensionSql, ExtensionSqlFile, SqlDeclared};
 pub use extern_args::{parse_extern_attributes, ExternArgs};
 pub use mapping::RustSqlMapping;
+pub use pg_access_method::entity::{AccessMethodTypeEntity, PgAccessMethodEntity};
+pub use pg_access_method::{AccessMethodType, PgAccessMethod};
 pub use pg_extern::entity::{
     PgExternArgumentEntity, PgExternEntity, PgExternReturnEntity, PgExternReturnEntityIteratedItem,
     PgOperatorEntity,
 };
 pub use pg_extern::{NameMacro, PgExtern, PgExternArgument, PgOperator};
+pub use pg_fdw::entity::PgForeignDataWrapperEntity;
+pub use pg_fdw::PgForeignDataWrapper;
+pub use pg_procedure::PgProcedure;
 pub use pg_trigger::attribute::PgTriggerAttribute;
 pub use pg_trigger::entity::PgTriggerEntity;
 pub use pg_trigger::PgTrigger;
@@ -57,7 +62,10 @@ pub(crate) mod extern_args;
 pub mod lifetimes;
 pub(crate) mod mapping;
 pub mod metadata;
+pub(crate) mod pg_access_method;
 pub(crate) mod pg_extern;
+pub(crate) mod pg_fdw;
+pub(crate) mod pg_procedure;
 pub(crate) mod pg_trigger;
 pub(crate) mod pgrx_attribute;
 pub(crate) mod pgrx_sql;
@@ -104,6 +112,8 @@ pub enum SqlGraphEntity {
     Hash(PostgresHashEntity),
     Aggregate(PgAggregateEntity),
     Trigger(PgTriggerEntity),
+    ForeignDataWrapper(PgForeignDataWrapperEntity),
+    AccessMethod(PgAccessMethodEntity),
 }
 
 impl SqlGraphEntity {
@@ -137,6 +147,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.dot_identifier(),
             SqlGraphEntity::Aggregate(item) => item.dot_identifier(),
             SqlGraphEntity::Trigger(item) => item.dot_identifier(),
+            SqlGraphEntity::ForeignDataWrapper(item) => item.dot_identifier(),
+            SqlGraphEntity::AccessMethod(item) => item.dot_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.dot_identifier(),
         }
     }
@@ -153,6 +165,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.rust_identifier(),
             SqlGraphEntity::Aggregate(item) => item.rust_identifier(),
             SqlGraphEntity::Trigger(item) => item.rust_identifier(),
+            SqlGraphEntity::ForeignDataWrapper(item) => item.rust_identifier(),
+            SqlGraphEntity::AccessMethod(item) => item.rust_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.rust_identifier(),
         }
     }
@@ -169,6 +183,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.file(),
             SqlGraphEntity::Aggregate(item) => item.file(),
             SqlGraphEntity::Trigger(item) => item.file(),
+            SqlGraphEntity::ForeignDataWrapper(item) => item.file(),
+            SqlGraphEntity::AccessMethod(item) => item.file(),
             SqlGraphEntity::ExtensionRoot(item) => item.file(),
         }
     }
@@ -185,6 +201,8 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Hash(item) => item.line(),
             SqlGraphEntity::Aggregate(item) => item.line(),
             SqlGraphEntity::Trigger(item) => item.line(),
+            SqlGraphEntity::ForeignDataWrapper(item) => item.line(),
+            SqlGraphEntity::AccessMethod(item) => item.line(),
             SqlGraphEntity::ExtensionRoot(item) => item.line(),
         }
     }
@@ -252,6 +270,12 @@ impl ToSql for SqlGraphEntity {
             SqlGraphEntity::Trigger(item) => {
                 item.to_sql_config.to_sql(self, context).unwrap_or_else(|| item.to_sql(context))
             }
+            SqlGraphEntity::ForeignDataWrapper(item) => {
+                item.to_sql_config.to_sql(self, context).unwrap_or_else(|| item.to_sql(context))
+            }
+            SqlGraphEntity::AccessMethod(item) => {
+                item.to_sql_config.to_sql(self, context).unwrap_or_else(|| item.to_sql(context))
+            }
             SqlGraphEntity::ExtensionRoot(item) => item.to_sql(context),
         }
     }
@@ -0,0 +1,106 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+/*!
+
+`#[pg_index_am]` and `#[pg_table_am]` related entities for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgrx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgrx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+use crate::{PgrxSql, SqlGraphEntity, SqlGraphIdentifier, ToSql, ToSqlConfigEntity};
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AccessMethodTypeEntity {
+    Index,
+    Table,
+}
+
+impl AccessMethodTypeEntity {
+    fn sql_keyword(&self) -> &'static str {
+        match self {
+            AccessMethodTypeEntity::Index => "INDEX",
+            AccessMethodTypeEntity::Table => "TABLE",
+        }
+    }
+
+    fn handler_type(&self) -> &'static str {
+        match self {
+            AccessMethodTypeEntity::Index => "index_am_handler",
+            AccessMethodTypeEntity::Table => "table_am_handler",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PgAccessMethodEntity {
+    /// The name of the access method, as used by `CREATE INDEX ... USING` or `CREATE TABLE ... USING`
+    pub name: &'static str,
+    /// The name of the generated handler function
+    pub handler: &'static str,
+    pub am_type: AccessMethodTypeEntity,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl From<PgAccessMethodEntity> for SqlGraphEntity {
+    fn from(val: PgAccessMethodEntity) -> Self {
+        SqlGraphEntity::AccessMethod(val)
+    }
+}
+
+impl SqlGraphIdentifier for PgAccessMethodEntity {
+    fn dot_identifier(&self) -> String {
+        format!("access method {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for PgAccessMethodEntity {
+    fn to_sql(&self, context: &PgrxSql) -> eyre::Result<String> {
+        let self_index = context.access_methods[self];
+        let schema = context.schema_prefix_for(&self_index);
+
+        let sql = format!(
+            "\n\
+            -- {file}:{line}\n\
+            -- {full_path}\n\
+            CREATE FUNCTION {schema}\"{handler}\"(internal)\n\
+                \tRETURNS {handler_type}\n\
+                \tLANGUAGE c\n\
+                \tAS 'MODULE_PATHNAME', '{handler}_wrapper';\n\
+            CREATE ACCESS METHOD \"{name}\"\n\
+                \tTYPE {am_type}\n\
+                \tHANDLER {schema}\"{handler}\";",
+            file = self.file,
+            line = self.line,
+            full_path = self.full_path,
+            schema = schema,
+            handler = self.handler,
+            handler_type = self.am_type.handler_type(),
+            name = self.name,
+            am_type = self.am_type.sql_keyword(),
+        );
+        Ok(sql)
+    }
+}
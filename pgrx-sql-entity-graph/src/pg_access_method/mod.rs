@@ -0,0 +1,286 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+/*!
+
+`#[pg_index_am]` and `#[pg_table_am]` related macro expansion for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgrx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgrx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+pub mod entity;
+
+use crate::enrich::{CodeEnrichment, ToEntityGraphTokens, ToRustCodeTokens};
+use crate::ToSqlConfig;
+use convert_case::{Case, Casing};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{ItemFn, ItemImpl};
+
+/// The kind of access method being declared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMethodType {
+    /// An index access method, declared with `#[pg_index_am]`
+    Index,
+    /// A table access method, declared with `#[pg_table_am]`
+    Table,
+}
+
+impl AccessMethodType {
+    fn attribute(&self) -> &'static str {
+        match self {
+            AccessMethodType::Index => "#[pg_index_am]",
+            AccessMethodType::Table => "#[pg_table_am]",
+        }
+    }
+
+    fn trait_name(&self) -> &'static str {
+        match self {
+            AccessMethodType::Index => "IndexAccessMethod",
+            AccessMethodType::Table => "TableAccessMethod",
+        }
+    }
+
+    fn routine_tokens(&self, target_ident: &Ident) -> TokenStream2 {
+        match self {
+            // Postgres copies the `IndexAmRoutine` into the relcache
+            AccessMethodType::Index => {
+                quote! { ::pgrx::index_am::index_am_routine::<#target_ident>().into_pg() }
+            }
+            // ... but keeps a pointer to the `TableAmRoutine`, which must therefore live forever
+            AccessMethodType::Table => {
+                quote! {{
+                    static ROUTINE: ::pgrx::once_cell::sync::OnceCell<::pgrx::pg_sys::TableAmRoutine> =
+                        ::pgrx::once_cell::sync::OnceCell::new();
+                    ROUTINE.get_or_init(::pgrx::table_am::table_am_routine::<#target_ident>)
+                        as *const ::pgrx::pg_sys::TableAmRoutine
+                }}
+            }
+        }
+    }
+
+    fn entity_tokens(&self) -> TokenStream2 {
+        match self {
+            AccessMethodType::Index => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::AccessMethodTypeEntity::Index }
+            }
+            AccessMethodType::Table => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::AccessMethodTypeEntity::Table }
+            }
+        }
+    }
+}
+
+/** A parsed `#[pg_index_am]` or `#[pg_table_am]` item.
+
+Generates the handler function for an `impl IndexAccessMethod for T` (or `TableAccessMethod`),
+along with the SQL to create it and the `CREATE ACCESS METHOD` statement that uses it.
+*/
+#[derive(Debug, Clone)]
+pub struct PgAccessMethod {
+    item_impl: ItemImpl,
+    target_ident: Ident,
+    name: String,
+    am_type: AccessMethodType,
+    to_sql_config: ToSqlConfig,
+}
+
+impl PgAccessMethod {
+    pub fn new(
+        item_impl: ItemImpl,
+        am_type: AccessMethodType,
+    ) -> Result<CodeEnrichment<Self>, syn::Error> {
+        let to_sql_config =
+            ToSqlConfig::from_attributes(item_impl.attrs.as_slice())?.unwrap_or_default();
+
+        let wrong_trait = |span| {
+            syn::Error::new(
+                span,
+                format!(
+                    "`{}` only works with the `{}` trait.",
+                    am_type.attribute(),
+                    am_type.trait_name()
+                ),
+            )
+        };
+        match &item_impl.trait_ {
+            Some((_, path, _)) => match path.segments.last() {
+                Some(last) if last.ident == am_type.trait_name() => (),
+                _ => return Err(wrong_trait(path.span())),
+            },
+            None => return Err(wrong_trait(item_impl.span())),
+        }
+
+        let target_ident = match &*item_impl.self_ty {
+            syn::Type::Path(type_path) => match type_path.path.segments.last() {
+                Some(last) => last.ident.clone(),
+                None => {
+                    return Err(syn::Error::new(
+                        type_path.span(),
+                        format!(
+                            "`{}` only works with types whose path have a final segment.",
+                            am_type.attribute()
+                        ),
+                    ))
+                }
+            },
+            something_else => {
+                return Err(syn::Error::new(
+                    something_else.span(),
+                    format!("`{}` only works with types.", am_type.attribute()),
+                ))
+            }
+        };
+
+        let name = target_ident.to_string().to_case(Case::Snake);
+        crate::ident_is_acceptable_to_postgres(&Ident::new(
+            &format!("{}_handler_wrapper", name),
+            target_ident.span(),
+        ))?;
+
+        Ok(CodeEnrichment(Self { item_impl, target_ident, name, am_type, to_sql_config }))
+    }
+
+    fn handler_name(&self) -> String {
+        format!("{}_handler", self.name)
+    }
+
+    pub fn wrapper_tokens(&self) -> Result<ItemFn, syn::Error> {
+        let target_ident = &self.target_ident;
+        let wrapper_ident =
+            Ident::new(&format!("{}_wrapper", self.handler_name()), target_ident.span());
+        let routine = self.am_type.routine_tokens(target_ident);
+        let tokens = quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            #[::pgrx::pgrx_macros::pg_guard]
+            pub unsafe extern "C" fn #wrapper_ident(_fcinfo: ::pgrx::pg_sys::FunctionCallInfo) -> ::pgrx::pg_sys::Datum {
+                ::pgrx::pg_sys::Datum::from(#routine)
+            }
+        };
+        syn::parse2(tokens)
+    }
+
+    pub fn finfo_tokens(&self) -> Result<ItemFn, syn::Error> {
+        let finfo_name = Ident::new(
+            &format!("pg_finfo_{}_wrapper", self.handler_name()),
+            proc_macro2::Span::call_site(),
+        );
+        let tokens = quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #finfo_name() -> &'static ::pgrx::pg_sys::Pg_finfo_record {
+                const V1_API: ::pgrx::pg_sys::Pg_finfo_record = ::pgrx::pg_sys::Pg_finfo_record { api_version: 1 };
+                &V1_API
+            }
+        };
+        syn::parse2(tokens)
+    }
+}
+
+impl ToEntityGraphTokens for PgAccessMethod {
+    fn to_entity_graph_tokens(&self) -> TokenStream2 {
+        let target_ident = &self.target_ident;
+        let sql_graph_entity_fn_name =
+            Ident::new(&format!("__pgrx_internals_am_{}", self.name), target_ident.span());
+        let name = &self.name;
+        let handler = self.handler_name();
+        let am_type = self.am_type.entity_tokens();
+        let to_sql_config = &self.to_sql_config;
+
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            #[allow(unknown_lints, clippy::no_mangle_with_rust_abi)]
+            pub extern "Rust" fn #sql_graph_entity_fn_name() -> ::pgrx::pgrx_sql_entity_graph::SqlGraphEntity {
+                let submission = ::pgrx::pgrx_sql_entity_graph::PgAccessMethodEntity {
+                    name: #name,
+                    handler: #handler,
+                    am_type: #am_type,
+                    full_path: ::core::any::type_name::<#target_ident>(),
+                    module_path: module_path!(),
+                    file: file!(),
+                    line: line!(),
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgrx::pgrx_sql_entity_graph::SqlGraphEntity::AccessMethod(submission)
+            }
+        }
+    }
+}
+
+impl ToRustCodeTokens for PgAccessMethod {
+    fn to_rust_code_tokens(&self) -> TokenStream2 {
+        let item_impl = &self.item_impl;
+        let wrapper_func =
+            self.wrapper_tokens().expect("Generating wrapper function for access method");
+        let finfo_func = self.finfo_tokens().expect("Generating finfo function for access method");
+        quote! {
+            #item_impl
+            #wrapper_func
+            #finfo_func
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessMethodType, PgAccessMethod};
+    use eyre::Result;
+    use quote::ToTokens;
+    use syn::{parse_quote, ItemImpl};
+
+    #[test]
+    fn index_am_generates_handler() -> Result<()> {
+        let tokens: ItemImpl = parse_quote! {
+            impl IndexAccessMethod for DemoIndex {
+                fn begin_scan(index: &PgRelation, nkeys: i32, norderbys: i32) -> Self {
+                    todo!()
+                }
+            }
+        };
+        let am = PgAccessMethod::new(tokens, AccessMethodType::Index)?;
+        assert_eq!(am.0.name, "demo_index");
+        assert_eq!(am.0.wrapper_tokens()?.sig.ident.to_string(), "demo_index_handler_wrapper");
+        // It should be possible to generate entity tokens.
+        let _ = am.to_token_stream();
+        Ok(())
+    }
+
+    #[test]
+    fn table_am_generates_handler() -> Result<()> {
+        let tokens: ItemImpl = parse_quote! {
+            impl TableAccessMethod for DemoTable {
+                fn begin_scan(scan: &TableScan) -> Self {
+                    todo!()
+                }
+
+                fn get_next_slot(&mut self, direction: pg_sys::ScanDirection, slot: &mut TupleSlot) -> bool {
+                    todo!()
+                }
+            }
+        };
+        let am = PgAccessMethod::new(tokens, AccessMethodType::Table)?;
+        assert_eq!(am.0.name, "demo_table");
+        assert_eq!(am.0.wrapper_tokens()?.sig.ident.to_string(), "demo_table_handler_wrapper");
+        // It should be possible to generate entity tokens.
+        let _ = am.to_token_stream();
+        Ok(())
+    }
+
+    #[test]
+    fn index_am_requires_trait() {
+        let tokens: ItemImpl = parse_quote! {
+            impl ForeignDataWrapper for DemoIndex {}
+        };
+        assert!(PgAccessMethod::new(tokens, AccessMethodType::Index).is_err());
+    }
+}
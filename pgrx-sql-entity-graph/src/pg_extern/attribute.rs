@@ -37,10 +37,17 @@ pub enum Attribute {
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
+    Leakproof,
+    CalledOnNullInput,
+    Materialize,
     Error(syn::LitStr),
     Schema(syn::LitStr),
     Name(syn::LitStr),
     Cost(syn::Expr),
+    Rows(syn::Expr),
+    Set(Vec<(String, syn::Lit)>),
+    Support(PositioningRef),
     Requires(Punctuated<PositioningRef, Token![,]>),
     Sql(ToSqlConfig),
 }
@@ -76,6 +83,16 @@ impl Attribute {
             Attribute::ParallelRestricted => {
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::ParallelRestricted }
             }
+            Attribute::Window => quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Window },
+            Attribute::Leakproof => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Leakproof }
+            }
+            Attribute::CalledOnNullInput => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::CalledOnNullInput }
+            }
+            Attribute::Materialize => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Materialize }
+            }
             Attribute::Error(s) => {
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Error(String::from(#s)) }
             }
@@ -88,6 +105,19 @@ impl Attribute {
             Attribute::Cost(s) => {
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Cost(format!("{}", #s)) }
             }
+            Attribute::Rows(s) => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Rows(format!("{}", #s)) }
+            }
+            Attribute::Set(settings) => {
+                let settings = settings.iter().map(|(name, value)| {
+                    let value = set_value_sql(value);
+                    quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Set(String::from(#name), String::from(#value)) }
+                });
+                quote! { #(#settings),* }
+            }
+            Attribute::Support(item) => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Support(#item) }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items.iter().map(|x| x.to_token_stream()).collect::<Vec<_>>();
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Requires(vec![#(#items_iter),*],) }
@@ -125,6 +155,10 @@ impl ToTokens for Attribute {
             Attribute::ParallelRestricted => {
                 quote! { parallel_restricted }
             }
+            Attribute::Window => quote! { window },
+            Attribute::Leakproof => quote! { leakproof },
+            Attribute::CalledOnNullInput => quote! { called_on_null_input },
+            Attribute::Materialize => quote! { materialize },
             Attribute::Error(s) => {
                 quote! { error = #s }
             }
@@ -137,6 +171,16 @@ impl ToTokens for Attribute {
             Attribute::Cost(s) => {
                 quote! { cost = #s }
             }
+            Attribute::Rows(s) => {
+                quote! { rows = #s }
+            }
+            Attribute::Set(settings) => {
+                let settings = settings.iter().map(|(name, value)| quote! { #name = #value });
+                quote! { set(#(#settings),*) }
+            }
+            Attribute::Support(item) => {
+                quote! { support = #item }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items.iter().map(|x| x.to_token_stream()).collect::<Vec<_>>();
                 quote! { requires = [#(#items_iter),*] }
@@ -166,6 +210,10 @@ impl Parse for Attribute {
             "parallel_safe" => Self::ParallelSafe,
             "parallel_unsafe" => Self::ParallelUnsafe,
             "parallel_restricted" => Self::ParallelRestricted,
+            "window" => Self::Window,
+            "leakproof" => Self::Leakproof,
+            "called_on_null_input" => Self::CalledOnNullInput,
+            "materialize" => Self::Materialize,
             "error" => {
                 let _eq: Token![=] = input.parse()?;
                 let literal: syn::LitStr = input.parse()?;
@@ -186,6 +234,43 @@ impl Parse for Attribute {
                 let literal: syn::Expr = input.parse()?;
                 Self::Cost(literal)
             }
+            "rows" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::Expr = input.parse()?;
+                Self::Rows(literal)
+            }
+            "set" => {
+                let content;
+                let _paren = syn::parenthesized!(content in input);
+                let settings = content.parse_terminated::<_, Token![,]>(|input: ParseStream| {
+                    // configuration parameter names may be qualified, like `myext.setting`,
+                    // so they're also accepted as string literals
+                    let name = if input.peek(syn::LitStr) {
+                        input.parse::<syn::LitStr>()?.value()
+                    } else {
+                        input.parse::<syn::Ident>()?.to_string()
+                    };
+                    let _eq: Token![=] = input.parse()?;
+                    let value = match input.parse::<syn::Lit>()? {
+                        value @ (syn::Lit::Str(_)
+                        | syn::Lit::Int(_)
+                        | syn::Lit::Float(_)
+                        | syn::Lit::Bool(_)) => value,
+                        other => {
+                            return Err(syn::Error::new(
+                                other.span(),
+                                "expected string, number, or boolean literal",
+                            ))
+                        }
+                    };
+                    Ok((name, value))
+                })?;
+                Self::Set(settings.into_iter().collect())
+            }
+            "support" => {
+                let _eq: Token![=] = input.parse()?;
+                Self::Support(input.parse()?)
+            }
             "requires" => {
                 let _eq: syn::token::Eq = input.parse()?;
                 let content;
@@ -224,3 +309,14 @@ impl Parse for Attribute {
         Ok(found)
     }
 }
+
+/// Render the value of a `set(name = value)` option as SQL
+fn set_value_sql(value: &syn::Lit) -> String {
+    match value {
+        syn::Lit::Str(s) => format!("'{}'", s.value().replace('\'', "''")),
+        syn::Lit::Int(i) => i.base10_digits().to_string(),
+        syn::Lit::Float(f) => f.base10_digits().to_string(),
+        syn::Lit::Bool(b) => b.value.to_string(),
+        other => other.to_token_stream().to_string(),
+    }
+}
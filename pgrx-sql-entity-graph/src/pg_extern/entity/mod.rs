@@ -24,7 +24,7 @@ pub use operator::PgOperatorEntity;
 pub use returning::{PgExternReturnEntity, PgExternReturnEntityIteratedItem};
 
 use crate::metadata::{Returns, SqlMapping};
-use crate::pgrx_sql::PgrxSql;
+use crate::pgrx_sql::{find_positioning_ref_target, PgrxSql};
 use crate::to_sql::entity::ToSqlConfigEntity;
 use crate::to_sql::ToSql;
 use crate::ExternArgs;
@@ -78,9 +78,15 @@ impl ToSql for PgExternEntity {
     fn to_sql(&self, context: &PgrxSql) -> eyre::Result<String> {
         let self_index = context.externs[self];
         let mut extern_attrs = self.extern_attrs.clone();
-        // if we already have a STRICT marker we do not need to add it
+        // procedures have no return type, and can't be `STRICT`
+        let is_procedure = extern_attrs.contains(&ExternArgs::Procedure);
+        // if we already have a STRICT marker we do not need to add it, and if the user explicitly
+        // asked for CALLED ON NULL INPUT we must not
         // presume we can upgrade, then disprove it
-        let mut strict_upgrade = !extern_attrs.iter().any(|i| i == &ExternArgs::Strict);
+        let mut strict_upgrade = !is_procedure
+            && !extern_attrs
+                .iter()
+                .any(|i| i == &ExternArgs::Strict || i == &ExternArgs::CalledOnNullInput);
         if strict_upgrade {
             // It may be possible to infer a `STRICT` marker though.
             // But we can only do that if the user hasn't used `Option<T>` or `pgrx::Internal`
@@ -99,16 +105,50 @@ impl ToSql for PgExternEntity {
 
         let module_pathname = &context.get_module_pathname();
 
+        let support = match self.extern_attrs.iter().find_map(|attr| match attr {
+            ExternArgs::Support(support) => Some(support),
+            _ => None,
+        }) {
+            Some(support) => {
+                let support_index = find_positioning_ref_target(
+                    support,
+                    &context.types,
+                    &context.enums,
+                    &context.externs,
+                    &context.schemas,
+                    &context.extension_sqls,
+                    &context.triggers,
+                    &context.access_methods,
+                )
+                .ok_or_else(|| eyre!("Could not find `support` target: {:?}", support))?;
+                match &context.graph[*support_index] {
+                    SqlGraphEntity::Function(support_fn) => format!(
+                        "SUPPORT {schema}\"{name}\"\n",
+                        schema = support_fn
+                            .schema
+                            .map(|schema| format!("{}.", schema))
+                            .unwrap_or_else(|| context.schema_prefix_for(support_index)),
+                        name = support_fn.name,
+                    ),
+                    _ => return Err(eyre!("`support` target {} is not a function", support)),
+                }
+            }
+            None => String::default(),
+        };
+
         let fn_sql = format!(
             "\
-                CREATE {or_replace} FUNCTION {schema}\"{name}\"({arguments}) {returns}\n\
+                CREATE {or_replace} {kind} {schema}\"{name}\"({arguments}) {returns}\n\
                 {extern_attrs}\
                 {search_path}\
+                {set_config}\
+                {support}\
                 LANGUAGE c /* Rust */\n\
                 AS '{module_pathname}', '{unaliased_name}_wrapper';\
             ",
             or_replace =
                 if extern_attrs.contains(&ExternArgs::CreateOrReplace) { "OR REPLACE" } else { "" },
+            kind = if is_procedure { "PROCEDURE" } else { "FUNCTION" },
             schema = self
                 .schema
                 .map(|schema| format!("{}.", schema))
@@ -117,12 +157,6 @@ impl ToSql for PgExternEntity {
             module_pathname = module_pathname,
             arguments = if !self.fn_args.is_empty() {
                 let mut args = Vec::new();
-                let metadata_without_arg_skips = &self
-                    .metadata
-                    .arguments
-                    .iter()
-                    .filter(|v| v.argument_sql != Ok(SqlMapping::Skip))
-                    .collect::<Vec<_>>();
                 for (idx, arg) in self.fn_args.iter().enumerate() {
                     let graph_index = context
                         .graph
@@ -136,7 +170,10 @@ impl ToSql for PgExternEntity {
                             _ => false,
                         })
                         .ok_or_else(|| eyre!("Could not find arg type in graph. Got: {:?}", arg))?;
-                    let needs_comma = idx < (metadata_without_arg_skips.len().saturating_sub(1));
+                    // skipped arguments, like a `WindowContext`, may appear anywhere in the signature
+                    let needs_comma = self.metadata.arguments[idx + 1..]
+                        .iter()
+                        .any(|v| v.argument_sql != Ok(SqlMapping::Skip));
                     let metadata_argument = &self.metadata.arguments[idx];
                     match metadata_argument.argument_sql {
                         Ok(SqlMapping::As(ref argument_sql)) => {
@@ -243,6 +280,7 @@ impl ToSql for PgExternEntity {
                 Default::default()
             },
             returns = match &self.fn_return {
+                _ if is_procedure => String::default(),
                 PgExternReturnEntity::None => String::from("RETURNS void"),
                 PgExternReturnEntity::Type { ty } => {
                     let graph_index = context
@@ -394,12 +432,22 @@ impl ToSql for PgExternEntity {
             } else {
                 Default::default()
             },
+            set_config = extern_attrs
+                .iter()
+                .filter(|attr| matches!(attr, ExternArgs::Set(..)))
+                .map(|attr| format!("{}\n", attr))
+                .collect::<String>(),
+            support = support,
             extern_attrs = if extern_attrs.is_empty() {
                 String::default()
             } else {
                 let mut retval = extern_attrs
                     .iter()
-                    .filter(|attr| **attr != ExternArgs::CreateOrReplace)
+                    .filter(|attr| {
+                        **attr != ExternArgs::CreateOrReplace
+                            && **attr != ExternArgs::Procedure
+                            && !matches!(attr, ExternArgs::Set(..))
+                    })
                     .map(|attr| format!("{}", attr).to_uppercase())
                     .collect::<Vec<_>>()
                     .join(" ");
@@ -35,6 +35,7 @@ use crate::enrich::CodeEnrichment;
 use crate::enrich::ToEntityGraphTokens;
 use crate::enrich::ToRustCodeTokens;
 use crate::lifetimes::staticize_lifetimes;
+use crate::used_type::{is_pgrx_path, WINDOW_CONTEXT_PATH};
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
 use quote::{quote, quote_spanned, ToTokens};
 use syn::parse::{Parse, ParseStream, Parser};
@@ -78,6 +79,7 @@ pub struct PgExtern {
     inputs: Vec<PgExternArgument>,
     input_types: Vec<syn::Type>,
     returns: Returning,
+    procedure: bool,
 }
 
 impl PgExtern {
@@ -98,6 +100,13 @@ impl PgExtern {
             }
         }
 
+        if attrs.contains(&Attribute::Strict) && attrs.contains(&Attribute::CalledOnNullInput) {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`strict` and `called_on_null_input` cannot be used together",
+            ));
+        }
+
         let mut to_sql_config = to_sql_config.unwrap_or_default();
 
         let func = syn::parse2::<syn::ItemFn>(item)?;
@@ -116,8 +125,35 @@ impl PgExtern {
         let operator = Self::operator(&func)?;
         let search_path = Self::search_path(&func)?;
         let inputs = Self::inputs(&func)?;
+        if attrs.contains(&Attribute::CalledOnNullInput) && !attrs.contains(&Attribute::Raw) {
+            // without `strict`, Postgres will call us with NULLs, which only `Option` can receive
+            if let Some(arg) = inputs.iter().find(|arg| {
+                let ty = &arg.used_ty.resolved_ty;
+                arg.used_ty.optional.is_none()
+                    && !is_window_context(ty)
+                    && !is_call_context(ty)
+                    && !is_function_call_info(ty)
+                    && *ty != syn::parse_quote!(())
+            }) {
+                return Err(syn::Error::new(
+                    arg.pat.span(),
+                    format!(
+                        "`called_on_null_input` requires every argument to be an `Option`, but `{}` isn't",
+                        arg.pat
+                    ),
+                ));
+            }
+        }
         let input_types = Self::input_types(&func)?;
         let returns = Returning::try_from(&func.sig.output)?;
+        if attrs.contains(&Attribute::Materialize)
+            && !matches!(returns, Returning::SetOf { .. } | Returning::Iterated { .. })
+        {
+            return Err(syn::Error::new(
+                func.sig.output.span(),
+                "`materialize` requires returning a `SetOfIterator` or `TableIterator`",
+            ));
+        }
         Ok(CodeEnrichment(Self {
             attrs,
             func,
@@ -127,9 +163,69 @@ impl PgExtern {
             inputs,
             input_types,
             returns,
+            procedure: false,
         }))
     }
 
+    /// Turn this into a `CREATE PROCEDURE`, rejecting anything only a function can have
+    pub(crate) fn into_procedure(mut self) -> Result<Self, syn::Error> {
+        for attr in &self.attrs {
+            let allowed = matches!(
+                attr,
+                Attribute::NoGuard
+                    | Attribute::CreateOrReplace
+                    | Attribute::SecurityDefiner
+                    | Attribute::SecurityInvoker
+                    | Attribute::Error(_)
+                    | Attribute::Schema(_)
+                    | Attribute::Name(_)
+                    | Attribute::Requires(_)
+            );
+            // a `SET` clause makes Postgres refuse to let the procedure commit or roll back
+            if !allowed {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    format!("`{}` cannot be used with `#[pg_procedure]`", attr.to_token_stream()),
+                ));
+            }
+        }
+        if self.operator.is_some() {
+            return Err(syn::Error::new(
+                self.func.sig.span(),
+                "a `#[pg_procedure]` cannot be an operator",
+            ));
+        }
+        let is_unit =
+            |ty: &syn::Type| matches!(ty, syn::Type::Tuple(tuple) if tuple.elems.is_empty());
+        let returns_unit = match &self.returns {
+            Returning::None => true,
+            // `Result<(), E>`
+            Returning::Type(ty) if ty.result => match &ty.resolved_ty {
+                syn::Type::Path(path) => path
+                    .path
+                    .segments
+                    .last()
+                    .and_then(|segment| match &segment.arguments {
+                        syn::PathArguments::AngleBracketed(args) => args.args.first(),
+                        _ => None,
+                    })
+                    .map(|arg| matches!(arg, syn::GenericArgument::Type(ty) if is_unit(ty)))
+                    .unwrap_or(false),
+                _ => false,
+            },
+            Returning::Type(ty) => is_unit(&ty.resolved_ty),
+            _ => false,
+        };
+        if !returns_unit {
+            return Err(syn::Error::new(
+                self.func.sig.output.span(),
+                "a `#[pg_procedure]` must return `()` or `Result<(), E>`",
+            ));
+        }
+        self.procedure = true;
+        Ok(self)
+    }
+
     fn input_types(func: &syn::ItemFn) -> syn::Result<Vec<syn::Type>> {
         func.sig
             .inputs
@@ -271,11 +367,14 @@ impl PgExtern {
         let unsafety = &self.func.sig.unsafety;
         let schema = self.schema();
         let schema_iter = schema.iter();
-        let extern_attrs = self
+        let mut extern_attrs = self
             .attrs
             .iter()
             .map(|attr| attr.to_sql_entity_graph_tokens())
             .collect::<Punctuated<_, Token![,]>>();
+        if self.procedure {
+            extern_attrs.push(quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Procedure });
+        }
         let search_path = self.search_path.clone().into_iter();
         let inputs = &self.inputs;
         let inputs_iter = inputs.iter().map(|v| v.entity_tokens());
@@ -362,6 +461,14 @@ impl PgExtern {
         );
         let func_generics = &self.func.sig.generics;
         let is_raw = self.extern_attrs().contains(&Attribute::Raw);
+        let is_window = self.extern_attrs().contains(&Attribute::Window);
+        // Set-returning functions either produce a row per call, or all their rows in a single
+        // call when asked to `materialize` them into a tuplestore
+        let srf_fn = if self.extern_attrs().contains(&Attribute::Materialize) {
+            syn::Ident::new("srf_materialize", self.func.sig.span())
+        } else {
+            syn::Ident::new("srf_next", self.func.sig.span())
+        };
         // We use a `_` prefix to make functions with no args more satisfied during linting.
         let fcinfo_ident = syn::Ident::new("_fcinfo", self.func.sig.ident.span());
 
@@ -370,13 +477,38 @@ impl PgExtern {
             .iter()
             .map(|v| syn::Ident::new(&format!("{}_", &v.pat), self.func.sig.span()))
             .collect::<Vec<_>>();
+        // Window functions aren't passed their arguments in `fcinfo`, so they're fetched by their
+        // position in the SQL signature, which excludes the `WindowContext`
         let arg_fetches = args.iter().enumerate().map(|(idx, arg)| {
             let pat = &arg_pats[idx];
             let resolved_ty = &arg.used_ty.resolved_ty;
-            if arg.used_ty.resolved_ty.to_token_stream().to_string() == quote!(pgrx::pg_sys::FunctionCallInfo).to_token_stream().to_string()
-                || arg.used_ty.resolved_ty.to_token_stream().to_string() == quote!(pg_sys::FunctionCallInfo).to_token_stream().to_string()
-                || arg.used_ty.resolved_ty.to_token_stream().to_string() == quote!(::pgrx::pg_sys::FunctionCallInfo).to_token_stream().to_string()
-            {
+            if is_window_context(resolved_ty) {
+                quote_spanned! {pat.span()=>
+                    let #pat = unsafe { ::pgrx::window::WindowContext::from_fcinfo(#fcinfo_ident) };
+                }
+            } else if is_call_context(resolved_ty) {
+                quote_spanned! {pat.span()=>
+                    let #pat = unsafe { ::pgrx::spi::CallContext::from_fcinfo(#fcinfo_ident) };
+                }
+            } else if is_window && !is_raw {
+                let window_idx = args[..idx]
+                    .iter()
+                    .filter(|arg| !is_window_context(&arg.used_ty.resolved_ty))
+                    .count();
+                match &arg.used_ty.optional {
+                    // Postgres doesn't apply STRICT to window functions, so do it here: a NULL
+                    // argument makes the result NULL without calling the function
+                    None => quote_spanned! { pat.span() =>
+                        let #pat = match unsafe { ::pgrx::window::pg_getarg_current::<#resolved_ty>(#fcinfo_ident, #window_idx) } {
+                            Some(value) => value,
+                            None => return unsafe { ::pgrx::fcinfo::pg_return_null(#fcinfo_ident) },
+                        };
+                    },
+                    Some(inner) => quote_spanned! { pat.span() =>
+                        let #pat = unsafe { ::pgrx::window::pg_getarg_current::<#inner>(#fcinfo_ident, #window_idx) };
+                    },
+                }
+            } else if is_function_call_info(resolved_ty) {
                 quote_spanned! {pat.span()=>
                     let #pat = #fcinfo_ident;
                 }
@@ -386,6 +518,11 @@ impl PgExtern {
                     let #pat = ();
                 }
             } else {
+                // a `CallContext` isn't part of the SQL signature, so it doesn't take up a position
+                let idx = args[..idx]
+                    .iter()
+                    .filter(|arg| !is_call_context(&arg.used_ty.resolved_ty))
+                    .count();
                 match (is_raw, &arg.used_ty.optional) {
                     (true, None) | (true, Some(_)) => quote_spanned! { pat.span() =>
                         let #pat = unsafe { ::pgrx::fcinfo::pg_getarg_datum_raw(#fcinfo_ident, #idx) as #resolved_ty };
@@ -509,7 +646,7 @@ impl PgExtern {
                             // SAFETY: the caller has asserted that `fcinfo` is a valid FunctionCallInfo pointer, allocated by Postgres
                             // with all its fields properly setup.  Unless the user is calling this wrapper function directly, this
                             // will always be the case
-                            ::pgrx::iter::SetOfIterator::srf_next(#fcinfo_ident, || {
+                            ::pgrx::iter::SetOfIterator::#srf_fn(#fcinfo_ident, || {
                                 #( #arg_fetches )*
                                 #result_handler
                             })
@@ -557,7 +694,7 @@ impl PgExtern {
                                 // SAFETY: the caller has asserted that `fcinfo` is a valid FunctionCallInfo pointer, allocated by Postgres
                                 // with all its fields properly setup.  Unless the user is calling this wrapper function directly, this
                                 // will always be the case
-                                ::pgrx::iter::SetOfIterator::srf_next(#fcinfo_ident, || {
+                                ::pgrx::iter::SetOfIterator::#srf_fn(#fcinfo_ident, || {
                                     #( #arg_fetches )*
                                     let table_iterator = { #result_handler };
 
@@ -580,7 +717,7 @@ impl PgExtern {
                                 // SAFETY: the caller has asserted that `fcinfo` is a valid FunctionCallInfo pointer, allocated by Postgres
                                 // with all its fields properly setup.  Unless the user is calling this wrapper function directly, this
                                 // will always be the case
-                                ::pgrx::iter::TableIterator::srf_next(#fcinfo_ident, || {
+                                ::pgrx::iter::TableIterator::#srf_fn(#fcinfo_ident, || {
                                     #( #arg_fetches )*
                                     #result_handler
                                 })
@@ -625,3 +762,82 @@ impl Parse for CodeEnrichment<PgExtern> {
         PgExtern::new(quote! {#(#attrs)*}, input.parse()?)
     }
 }
+
+/// The full paths of the `CallContext` that procedures can receive
+const CALL_CONTEXT_PATHS: &[&[&str]] =
+    &[&["pgrx", "spi", "CallContext"], &["pgrx", "prelude", "CallContext"]];
+
+/// Is `ty` a `pgrx::spi::CallContext`, which procedures receive instead of a SQL argument?
+///
+/// Postgres' own `pg_sys::CallContext` isn't one.
+fn is_call_context(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => {
+            CALL_CONTEXT_PATHS.iter().any(|full| is_pgrx_path(&path.path, full))
+        }
+        _ => false,
+    }
+}
+
+/// Is `ty` a `pg_sys::FunctionCallInfo`, which receives the function's `fcinfo` as-is?
+fn is_function_call_info(ty: &syn::Type) -> bool {
+    let ty = ty.to_token_stream().to_string();
+    ty == quote!(pgrx::pg_sys::FunctionCallInfo).to_string()
+        || ty == quote!(pg_sys::FunctionCallInfo).to_string()
+        || ty == quote!(::pgrx::pg_sys::FunctionCallInfo).to_string()
+}
+
+/// Is `ty` a `pgrx::window::WindowContext`, which window functions receive instead of a SQL argument?
+fn is_window_context(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => is_pgrx_path(&path.path, WINDOW_CONTEXT_PATH),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_call_context, PgExtern};
+    use quote::quote;
+
+    #[test]
+    fn called_on_null_input_requires_options() {
+        let err = PgExtern::new(
+            quote! { called_on_null_input },
+            quote! { fn demo(a: Option<i32>, b: i32) -> i32 { b } },
+        )
+        .err()
+        .expect("a non-`Option` argument should be rejected");
+        assert!(err.to_string().contains("`b` isn't"), "{err}");
+
+        assert!(PgExtern::new(
+            quote! { called_on_null_input },
+            quote! { fn demo(a: Option<i32>) -> Option<i32> { a } },
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn procedures_reject_set() {
+        let err = PgExtern::new(
+            quote! { set(search_path = "public") },
+            quote! { fn demo(call: CallContext) {} },
+        )
+        .and_then(|pg_extern| pg_extern.0.into_procedure())
+        .expect_err("a `SET` clause should be rejected");
+        assert!(err.to_string().contains("cannot be used with `#[pg_procedure]`"), "{err}");
+    }
+
+    #[test]
+    fn call_context_is_pgrx_only() {
+        for ty in [
+            quote! { CallContext },
+            quote! { spi::CallContext },
+            quote! { pgrx::spi::CallContext },
+            quote! { ::pgrx::prelude::CallContext },
+        ] {
+            assert!(is_call_context(&syn::parse2(ty.clone()).unwrap()), "{ty}");
+        }
+        assert!(!is_call_context(&syn::parse2(quote! { pg_sys::CallContext }).unwrap()));
+    }
+}
@@ -0,0 +1,80 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+/*!
+
+`#[pg_fdw]` related entities for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgrx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgrx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+use crate::{PgrxSql, SqlGraphEntity, SqlGraphIdentifier, ToSql, ToSqlConfigEntity};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PgForeignDataWrapperEntity {
+    /// The name of the foreign data wrapper, as used by `CREATE SERVER ... FOREIGN DATA WRAPPER`
+    pub name: &'static str,
+    /// The name of the generated `fdw_handler` function
+    pub handler: &'static str,
+    /// The name of the generated validator function
+    pub validator: &'static str,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl From<PgForeignDataWrapperEntity> for SqlGraphEntity {
+    fn from(val: PgForeignDataWrapperEntity) -> Self {
+        SqlGraphEntity::ForeignDataWrapper(val)
+    }
+}
+
+impl SqlGraphIdentifier for PgForeignDataWrapperEntity {
+    fn dot_identifier(&self) -> String {
+        format!("foreign data wrapper {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for PgForeignDataWrapperEntity {
+    fn to_sql(&self, context: &PgrxSql) -> eyre::Result<String> {
+        let self_index = context.foreign_data_wrappers[self];
+        let schema = context.schema_prefix_for(&self_index);
+
+        let sql = format!(
+            "\n\
+            -- {file}:{line}\n\
+            -- {full_path}\n\
+            CREATE FOREIGN DATA WRAPPER \"{name}\"\n\
+                \tHANDLER {schema}\"{handler}\"\n\
+                \tVALIDATOR {schema}\"{validator}\";",
+            file = self.file,
+            line = self.line,
+            full_path = self.full_path,
+            name = self.name,
+            schema = schema,
+            handler = self.handler,
+            validator = self.validator,
+        );
+        Ok(sql)
+    }
+}
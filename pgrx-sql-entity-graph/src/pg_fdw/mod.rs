@@ -0,0 +1,205 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+/*!
+
+`#[pg_fdw]` related macro expansion for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgrx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgrx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+pub mod entity;
+
+use crate::enrich::{CodeEnrichment, ToEntityGraphTokens, ToRustCodeTokens};
+use crate::ToSqlConfig;
+use convert_case::{Case, Casing};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{parse_quote, ItemFn, ItemImpl};
+
+/** A parsed `#[pg_fdw]` item.
+
+Generates the `fdw_handler` and validator functions for an `impl ForeignDataWrapper for T`, along
+with the `CREATE FOREIGN DATA WRAPPER` statement that uses them.
+*/
+#[derive(Debug, Clone)]
+pub struct PgForeignDataWrapper {
+    item_impl: ItemImpl,
+    target_ident: Ident,
+    name: String,
+    pg_externs: Vec<ItemFn>,
+    to_sql_config: ToSqlConfig,
+}
+
+impl PgForeignDataWrapper {
+    pub fn new(item_impl: ItemImpl) -> Result<CodeEnrichment<Self>, syn::Error> {
+        let to_sql_config =
+            ToSqlConfig::from_attributes(item_impl.attrs.as_slice())?.unwrap_or_default();
+
+        match &item_impl.trait_ {
+            Some((_, path, _)) => match path.segments.last() {
+                Some(last) if last.ident == "ForeignDataWrapper" => (),
+                _ => {
+                    return Err(syn::Error::new(
+                        path.span(),
+                        "`#[pg_fdw]` only works with the `ForeignDataWrapper` trait.",
+                    ))
+                }
+            },
+            None => {
+                return Err(syn::Error::new(
+                    item_impl.span(),
+                    "`#[pg_fdw]` only works with the `ForeignDataWrapper` trait.",
+                ))
+            }
+        }
+
+        let target_ident = match &*item_impl.self_ty {
+            syn::Type::Path(type_path) => match type_path.path.segments.last() {
+                Some(last) => last.ident.clone(),
+                None => {
+                    return Err(syn::Error::new(
+                        type_path.span(),
+                        "`#[pg_fdw]` only works with types whose path have a final segment.",
+                    ))
+                }
+            },
+            something_else => {
+                return Err(syn::Error::new(
+                    something_else.span(),
+                    "`#[pg_fdw]` only works with types.",
+                ))
+            }
+        };
+
+        let name = target_ident.to_string().to_case(Case::Snake);
+        let handler_ident = Ident::new(&format!("{}_handler", name), target_ident.span());
+        let validator_ident = Ident::new(&format!("{}_validator", name), target_ident.span());
+        crate::ident_is_acceptable_to_postgres(&handler_ident)?;
+        crate::ident_is_acceptable_to_postgres(&validator_ident)?;
+
+        let pg_externs = vec![
+            parse_quote! {
+                #[::pgrx::pg_extern]
+                fn #handler_ident() -> ::pgrx::PgBox<::pgrx::pg_sys::FdwRoutine, ::pgrx::AllocatedByRust> {
+                    ::pgrx::fdw::fdw_routine::<#target_ident>()
+                }
+            },
+            parse_quote! {
+                #[::pgrx::pg_extern]
+                fn #validator_ident(options: Vec<String>, catalog: ::pgrx::pg_sys::Oid) {
+                    ::pgrx::fdw::validate_options::<#target_ident>(options, catalog)
+                }
+            },
+        ];
+
+        Ok(CodeEnrichment(Self { item_impl, target_ident, name, pg_externs, to_sql_config }))
+    }
+
+    fn handler_name(&self) -> String {
+        format!("{}_handler", self.name)
+    }
+
+    fn validator_name(&self) -> String {
+        format!("{}_validator", self.name)
+    }
+}
+
+impl ToEntityGraphTokens for PgForeignDataWrapper {
+    fn to_entity_graph_tokens(&self) -> TokenStream2 {
+        let target_ident = &self.target_ident;
+        let sql_graph_entity_fn_name =
+            Ident::new(&format!("__pgrx_internals_fdw_{}", self.name), target_ident.span());
+        let name = &self.name;
+        let handler = self.handler_name();
+        let validator = self.validator_name();
+        let to_sql_config = &self.to_sql_config;
+
+        quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            #[allow(unknown_lints, clippy::no_mangle_with_rust_abi)]
+            pub extern "Rust" fn #sql_graph_entity_fn_name() -> ::pgrx::pgrx_sql_entity_graph::SqlGraphEntity {
+                let submission = ::pgrx::pgrx_sql_entity_graph::PgForeignDataWrapperEntity {
+                    name: #name,
+                    handler: #handler,
+                    validator: #validator,
+                    full_path: ::core::any::type_name::<#target_ident>(),
+                    module_path: module_path!(),
+                    file: file!(),
+                    line: line!(),
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgrx::pgrx_sql_entity_graph::SqlGraphEntity::ForeignDataWrapper(submission)
+            }
+        }
+    }
+}
+
+impl ToRustCodeTokens for PgForeignDataWrapper {
+    fn to_rust_code_tokens(&self) -> TokenStream2 {
+        let item_impl = &self.item_impl;
+        let pg_externs = self.pg_externs.iter();
+        quote! {
+            #item_impl
+            #(#pg_externs)*
+        }
+    }
+}
+
+impl Parse for CodeEnrichment<PgForeignDataWrapper> {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        PgForeignDataWrapper::new(input.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PgForeignDataWrapper;
+    use eyre::Result;
+    use quote::ToTokens;
+    use syn::{parse_quote, ItemImpl};
+
+    #[test]
+    fn fdw_generates_handler_and_validator() -> Result<()> {
+        let tokens: ItemImpl = parse_quote! {
+            impl ForeignDataWrapper for DemoFdw {
+                type Row = (i32,);
+
+                fn begin_scan(table: &PgForeignTable) -> Self {
+                    todo!()
+                }
+
+                fn iterate_scan(&mut self) -> Option<Self::Row> {
+                    todo!()
+                }
+
+                fn re_scan(&mut self) {}
+            }
+        };
+        let fdw = PgForeignDataWrapper::new(tokens)?;
+        assert_eq!(fdw.0.name, "demo_fdw");
+        let names = fdw.0.pg_externs.iter().map(|f| f.sig.ident.to_string()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["demo_fdw_handler", "demo_fdw_validator"]);
+        // It should be possible to generate entity tokens.
+        let _ = fdw.to_token_stream();
+        Ok(())
+    }
+
+    #[test]
+    fn fdw_requires_trait() {
+        let tokens: ItemImpl = parse_quote! {
+            impl Aggregate for DemoFdw {}
+        };
+        assert!(PgForeignDataWrapper::new(tokens).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+/*!
+
+`#[pg_procedure]` related macro expansion for Rust to SQL translation
+
+> Like all of the [`sql_entity_graph`][crate::pgrx_sql_entity_graph] APIs, this is considered **internal**
+to the `pgrx` framework and very subject to change between versions. While you may use this, please do it with caution.
+
+*/
+use crate::enrich::{CodeEnrichment, ToEntityGraphTokens, ToRustCodeTokens};
+use crate::PgExtern;
+use proc_macro2::TokenStream as TokenStream2;
+
+/** A parsed `#[pg_procedure]` item.
+
+Procedures are called like a `#[pg_extern]` function, so they share its wrapper, but they're
+declared with `CREATE PROCEDURE` and produce a [`PgExternEntity`][crate::PgExternEntity] that
+carries [`ExternArgs::Procedure`][crate::ExternArgs::Procedure].
+*/
+#[derive(Debug, Clone)]
+pub struct PgProcedure {
+    pg_extern: PgExtern,
+}
+
+impl PgProcedure {
+    pub fn new(attr: TokenStream2, item: TokenStream2) -> Result<CodeEnrichment<Self>, syn::Error> {
+        let CodeEnrichment(pg_extern) = PgExtern::new(attr, item)?;
+        Ok(CodeEnrichment(Self { pg_extern: pg_extern.into_procedure()? }))
+    }
+}
+
+impl ToEntityGraphTokens for PgProcedure {
+    fn to_entity_graph_tokens(&self) -> TokenStream2 {
+        self.pg_extern.to_entity_graph_tokens()
+    }
+}
+
+impl ToRustCodeTokens for PgProcedure {
+    fn to_rust_code_tokens(&self) -> TokenStream2 {
+        self.pg_extern.to_rust_code_tokens()
+    }
+}
@@ -29,7 +29,9 @@ use crate::aggregate::entity::PgAggregateEntity;
 use crate::control_file::ControlFile;
 use crate::extension_sql::entity::{ExtensionSqlEntity, SqlDeclaredEntity};
 use crate::extension_sql::SqlDeclared;
+use crate::pg_access_method::entity::PgAccessMethodEntity;
 use crate::pg_extern::entity::PgExternEntity;
+use crate::pg_fdw::entity::PgForeignDataWrapperEntity;
 use crate::pg_trigger::entity::PgTriggerEntity;
 use crate::positioning_ref::PositioningRef;
 use crate::postgres_enum::entity::PostgresEnumEntity;
@@ -77,6 +79,8 @@ pub struct PgrxSql {
     pub hashes: HashMap<PostgresHashEntity, NodeIndex>,
     pub aggregates: HashMap<PgAggregateEntity, NodeIndex>,
     pub triggers: HashMap<PgTriggerEntity, NodeIndex>,
+    pub foreign_data_wrappers: HashMap<PgForeignDataWrapperEntity, NodeIndex>,
+    pub access_methods: HashMap<PgAccessMethodEntity, NodeIndex>,
     pub extension_name: String,
     pub versioned_so: bool,
 }
@@ -102,6 +106,8 @@ impl PgrxSql {
         let mut hashes: Vec<PostgresHashEntity> = Vec::default();
         let mut aggregates: Vec<PgAggregateEntity> = Vec::default();
         let mut triggers: Vec<PgTriggerEntity> = Vec::default();
+        let mut foreign_data_wrappers: Vec<PgForeignDataWrapperEntity> = Vec::default();
+        let mut access_methods: Vec<PgAccessMethodEntity> = Vec::default();
         for entity in entities {
             match entity {
                 SqlGraphEntity::ExtensionRoot(input_control) => {
@@ -135,6 +141,12 @@ impl PgrxSql {
                 SqlGraphEntity::Trigger(input_trigger) => {
                     triggers.push(input_trigger);
                 }
+                SqlGraphEntity::ForeignDataWrapper(input_fdw) => {
+                    foreign_data_wrappers.push(input_fdw);
+                }
+                SqlGraphEntity::AccessMethod(input_am) => {
+                    access_methods.push(input_am);
+                }
             }
         }
 
@@ -176,6 +188,15 @@ impl PgrxSql {
             &mapped_types,
         )?;
         let mapped_triggers = initialize_triggers(&mut graph, root, bootstrap, finalize, triggers)?;
+        let mapped_foreign_data_wrappers = initialize_foreign_data_wrappers(
+            &mut graph,
+            root,
+            bootstrap,
+            finalize,
+            foreign_data_wrappers,
+        )?;
+        let mapped_access_methods =
+            initialize_access_methods(&mut graph, root, bootstrap, finalize, access_methods)?;
 
         // Now we can circle back and build up the edge sets.
         connect_schemas(&mut graph, &mapped_schemas, root);
@@ -187,6 +208,7 @@ impl PgrxSql {
             &mapped_enums,
             &mapped_externs,
             &mapped_triggers,
+            &mapped_access_methods,
         )?;
         connect_enums(&mut graph, &mapped_enums, &mapped_schemas);
         connect_types(&mut graph, &mapped_types, &mapped_schemas);
@@ -200,6 +222,7 @@ impl PgrxSql {
             &mapped_builtin_types,
             &mapped_extension_sqls,
             &mapped_triggers,
+            &mapped_access_methods,
         )?;
         connect_ords(
             &mut graph,
@@ -227,6 +250,13 @@ impl PgrxSql {
             &mapped_externs,
         )?;
         connect_triggers(&mut graph, &mapped_triggers, &mapped_schemas);
+        connect_foreign_data_wrappers(
+            &mut graph,
+            &mapped_foreign_data_wrappers,
+            &mapped_schemas,
+            &mapped_externs,
+        )?;
+        connect_access_methods(&mut graph, &mapped_access_methods, &mapped_schemas);
 
         let this = Self {
             control: control,
@@ -240,6 +270,8 @@ impl PgrxSql {
             hashes: mapped_hashes,
             aggregates: mapped_aggregates,
             triggers: mapped_triggers,
+            foreign_data_wrappers: mapped_foreign_data_wrappers,
+            access_methods: mapped_access_methods,
             graph: graph,
             graph_root: root,
             graph_bootstrap: bootstrap,
@@ -371,6 +403,14 @@ impl PgrxSql {
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::ForeignDataWrapper(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
+                        node.dot_identifier()
+                    ),
+                    SqlGraphEntity::AccessMethod(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFE4E0\", weight = 5, shape = \"diamond\"",
+                        node.dot_identifier()
+                    ),
                     SqlGraphEntity::CustomSql(_item) => format!(
                         "label = \"{}\", weight = 3, shape = \"signature\"",
                         node.dot_identifier()
@@ -541,6 +581,7 @@ pub fn find_positioning_ref_target<'a>(
     schemas: &'a HashMap<SchemaEntity, NodeIndex>,
     extension_sqls: &'a HashMap<ExtensionSqlEntity, NodeIndex>,
     triggers: &'a HashMap<PgTriggerEntity, NodeIndex>,
+    access_methods: &'a HashMap<PgAccessMethodEntity, NodeIndex>,
 ) -> Option<&'a NodeIndex> {
     match positioning_ref {
         PositioningRef::FullPath(path) => {
@@ -579,6 +620,14 @@ pub fn find_positioning_ref_target<'a>(
                     return Some(&other_index);
                 }
             }
+
+            for (other, other_index) in access_methods {
+                if other.full_path.ends_with(&format!("::{}", last_segment))
+                    && other.module_path.ends_with(&module_path)
+                {
+                    return Some(&other_index);
+                }
+            }
         }
         PositioningRef::Name(name) => {
             for (other, other_index) in extension_sqls {
@@ -599,6 +648,7 @@ fn connect_extension_sqls(
     enums: &HashMap<PostgresEnumEntity, NodeIndex>,
     externs: &HashMap<PgExternEntity, NodeIndex>,
     triggers: &HashMap<PgTriggerEntity, NodeIndex>,
+    access_methods: &HashMap<PgAccessMethodEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in extension_sqls {
         make_schema_connection(
@@ -619,6 +669,7 @@ fn connect_extension_sqls(
                 schemas,
                 extension_sqls,
                 triggers,
+                access_methods,
             ) {
                 graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
             } else {
@@ -847,6 +898,7 @@ fn connect_externs(
     builtin_types: &HashMap<String, NodeIndex>,
     extension_sqls: &HashMap<ExtensionSqlEntity, NodeIndex>,
     triggers: &HashMap<PgTriggerEntity, NodeIndex>,
+    access_methods: &HashMap<PgAccessMethodEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in externs {
         let mut found_schema_declaration = false;
@@ -863,6 +915,7 @@ fn connect_externs(
                             schemas,
                             extension_sqls,
                             triggers,
+                            access_methods,
                         ) {
                             graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
                             has_explicit_requires = true;
@@ -871,6 +924,23 @@ fn connect_externs(
                         }
                     }
                 }
+                crate::ExternArgs::Support(support) => {
+                    // the support function must exist before the function which names it
+                    if let Some(target) = find_positioning_ref_target(
+                        support,
+                        types,
+                        enums,
+                        externs,
+                        schemas,
+                        extension_sqls,
+                        triggers,
+                        access_methods,
+                    ) {
+                        graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
+                    } else {
+                        return Err(eyre!("Could not find `support` target: {:?}", support));
+                    }
+                }
                 crate::ExternArgs::Schema(declared_schema_name) => {
                     for (schema, schema_index) in schemas {
                         if schema.name == declared_schema_name {
@@ -1453,6 +1523,89 @@ fn connect_triggers(
     }
 }
 
+fn initialize_foreign_data_wrappers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    foreign_data_wrappers: Vec<PgForeignDataWrapperEntity>,
+) -> eyre::Result<HashMap<PgForeignDataWrapperEntity, NodeIndex>> {
+    let mut mapped_foreign_data_wrappers = HashMap::default();
+    for item in foreign_data_wrappers {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+
+        mapped_foreign_data_wrappers.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_foreign_data_wrappers)
+}
+
+fn connect_foreign_data_wrappers(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    foreign_data_wrappers: &HashMap<PgForeignDataWrapperEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+    externs: &HashMap<PgExternEntity, NodeIndex>,
+) -> eyre::Result<()> {
+    for (item, &index) in foreign_data_wrappers {
+        make_schema_connection(
+            graph,
+            "ForeignDataWrapper",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+
+        for func in [item.handler, item.validator] {
+            make_extern_connection(
+                graph,
+                "ForeignDataWrapper",
+                index,
+                &item.rust_identifier(),
+                &(item.module_path.to_string() + "::" + func),
+                externs,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn initialize_access_methods(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    access_methods: Vec<PgAccessMethodEntity>,
+) -> eyre::Result<HashMap<PgAccessMethodEntity, NodeIndex>> {
+    let mut mapped_access_methods = HashMap::default();
+    for item in access_methods {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+
+        mapped_access_methods.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_access_methods)
+}
+
+fn connect_access_methods(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    access_methods: &HashMap<PgAccessMethodEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+) {
+    for (item, &index) in access_methods {
+        make_schema_connection(
+            graph,
+            "AccessMethod",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            schemas,
+        );
+    }
+}
+
 fn make_schema_connection(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
     _kind: &str,
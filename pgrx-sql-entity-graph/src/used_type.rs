@@ -136,6 +136,10 @@ impl UsedType {
                     // Array<composite_type!(..)>
                     // Array<Option<composite_type!(..)>>
                     "Array" => resolve_array_inner(path)?,
+                    // WindowContext
+                    "WindowContext" if is_pgrx_path(&path.path, WINDOW_CONTEXT_PATH) => {
+                        (resolve_window_context(path), None)
+                    }
                     _ => (syn::Type::Path(path), None),
                 }
             }
@@ -389,6 +393,33 @@ fn resolve_vec_inner(
     }
 }
 
+/// The full path of the `WindowContext` that window functions receive
+pub(crate) const WINDOW_CONTEXT_PATH: &[&str] = &["pgrx", "window", "WindowContext"];
+
+/// Does `path` name the pgrx type whose full path is `full`?
+///
+/// Macros can't see `use` statements, so any suffix of the full path is accepted, such as
+/// `WindowContext` or `window::WindowContext`, but a type of the same name elsewhere isn't.
+pub(crate) fn is_pgrx_path(path: &syn::Path, full: &[&str]) -> bool {
+    let segments = &path.segments;
+    !segments.is_empty()
+        && segments.len() <= full.len()
+        && segments
+            .iter()
+            .zip(&full[full.len() - segments.len()..])
+            .all(|(segment, name)| segment.ident == name)
+}
+
+fn resolve_window_context(mut original: syn::TypePath) -> syn::Type {
+    // The `WindowContext` borrows from the function call, so give it a nameable lifetime
+    if let Some(last) = original.path.segments.last_mut() {
+        if let syn::PathArguments::None = last.arguments {
+            last.arguments = syn::PathArguments::AngleBracketed(syn::parse_quote!(<'static>));
+        }
+    }
+    syn::Type::Path(original)
+}
+
 fn resolve_variadic_array_inner(
     mut original: syn::TypePath,
 ) -> syn::Result<(syn::Type, Option<CompositeTypeMacro>)> {
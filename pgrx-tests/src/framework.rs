@@ -190,6 +190,19 @@ fn format_loglines(session_id: &str, loglines: &LogLines) -> String {
     result
 }
 
+/// Run `f` with a client connected to the test database, outside of any transaction.
+///
+/// A `#[pg_test]` runs inside a transaction that's rolled back afterwards, so this is for the few
+/// tests that need to commit, like `CALL`ing a procedure that commits or decoding committed changes.
+pub fn run_client_test(
+    postgresql_conf: Vec<&'static str>,
+    f: impl FnOnce(&mut postgres::Client) -> eyre::Result<()>,
+) -> eyre::Result<()> {
+    initialize_test_framework(postgresql_conf)?;
+    let (mut client, _) = client()?;
+    f(&mut client)
+}
+
 fn initialize_test_framework(
     postgresql_conf: Vec<&'static str>,
 ) -> eyre::Result<(LogLines, String)> {
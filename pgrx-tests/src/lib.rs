@@ -23,6 +23,6 @@ pub mod pg_test {
     }
 
     pub fn postgresql_conf_options() -> Vec<&'static str> {
-        vec!["shared_preload_libraries='pgrx_tests'"]
+        vec!["shared_preload_libraries='pgrx_tests'", "wal_level=logical"]
     }
 }
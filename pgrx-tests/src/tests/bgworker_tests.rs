@@ -86,6 +86,84 @@ pub extern "C" fn bgworker_return_value(arg: pg_sys::Datum) {
     .expect("bgworker transaction failed");
 }
 
+#[pg_guard]
+#[no_mangle]
+/// Sends the words of its `bgw_extra` back through the message queue in the segment it's given
+pub extern "C" fn bgworker_shm_mq(arg: pg_sys::Datum) {
+    use pgrx::bgworkers::*;
+    use pgrx::dsm::{DsmSegment, ShmMq};
+
+    let segment = DsmSegment::attach(arg.value() as pg_sys::dsm_handle)
+        .expect("the dynamic shared memory segment no longer exists");
+    let queue = ShmMq::<(usize, String)>::open(&segment).expect("no message queue in the segment");
+    let mut sender = queue.sender().expect("the queue already has a sender");
+    for (i, word) in BackgroundWorker::get_extra().split_whitespace().enumerate() {
+        sender.send(&(i, word.to_string())).expect("the receiver detached");
+    }
+}
+
+#[pg_guard]
+#[no_mangle]
+/// Listens for a notification from the `bgworker_notifier` it starts, and records it in a table
+pub extern "C" fn bgworker_listener(_arg: pg_sys::Datum) {
+    use pgrx::bgworkers::*;
+    use std::time::Duration;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    BackgroundWorker::listen("pgrx_tests_channel");
+    BackgroundWorkerBuilder::new("dynamic_bgworker_notifier")
+        .set_library("pgrx_tests")
+        .set_function("bgworker_notifier")
+        .enable_spi_access()
+        .load_dynamic();
+
+    let mut received = None;
+    for _ in 0..100 {
+        if !BackgroundWorker::wait_latch(Some(Duration::from_millis(100))) {
+            break;
+        }
+        received = BackgroundWorker::notifications().next();
+        if received.is_some() {
+            break;
+        }
+    }
+
+    let notification = received.expect("no notification was received");
+    BackgroundWorker::transaction(|| {
+        Spi::run("CREATE TABLE tests.bgworker_notify_test (channel TEXT, payload TEXT);")?;
+        Spi::connect(|mut client| {
+            client
+                .update(
+                    "INSERT INTO tests.bgworker_notify_test VALUES ($1, $2);",
+                    None,
+                    Some(vec![
+                        (PgOid::BuiltIn(PgBuiltInOids::TEXTOID), notification.channel.into_datum()),
+                        (PgOid::BuiltIn(PgBuiltInOids::TEXTOID), notification.payload.into_datum()),
+                    ]),
+                )
+                .map(|_| ())
+        })
+    })
+    .expect("bgworker transaction failed");
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn bgworker_notifier(_arg: pg_sys::Datum) {
+    use pgrx::bgworkers::*;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    BackgroundWorker::transaction(|| pgrx::notify("pgrx_tests_channel", "hello"));
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
 mod tests {
@@ -159,4 +237,65 @@ mod tests {
 
         assert_eq!(Ok(Some(123)), Spi::get_one::<i32>("SELECT v FROM tests.bgworker_test_return;"));
     }
+
+    #[pg_test]
+    fn test_dynamic_bgworker_shm_mq() {
+        use pgrx::dsm::{DsmSegment, ShmMq};
+
+        let segment = DsmSegment::create(16 * 1024);
+        let queue = ShmMq::<(usize, String)>::create(&segment);
+        let worker = BackgroundWorkerBuilder::new("dynamic_bgworker_shm_mq")
+            .set_library("pgrx_tests")
+            .set_function("bgworker_shm_mq")
+            .set_argument(Some(segment.handle().into()))
+            .set_extra("the quick brown fox")
+            .enable_shmem_access(None)
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .load_dynamic();
+
+        let received = queue.receiver_for(&worker).unwrap().collect::<Vec<_>>();
+        assert_eq!(
+            received,
+            vec![
+                (0, "the".to_string()),
+                (1, "quick".to_string()),
+                (2, "brown".to_string()),
+                (3, "fox".to_string())
+            ]
+        );
+        worker.wait_for_shutdown().expect("aborted shutdown");
+    }
+
+    #[pg_test]
+    fn test_shm_mq_open_and_attach() {
+        use pgrx::dsm::{DsmSegment, ShmMq, ShmMqError};
+
+        let plain = DsmSegment::create(16 * 1024);
+        assert!(matches!(ShmMq::<i32>::open(&plain), Err(ShmMqError::NotAQueue)));
+
+        let segment = DsmSegment::create(16 * 1024);
+        let _receiver = ShmMq::<i32>::create(&segment).receiver().unwrap();
+        let again = ShmMq::<i32>::open(&segment).unwrap();
+        assert!(matches!(again.receiver(), Err(ShmMqError::AlreadyAttached)));
+        assert!(ShmMq::<i32>::open(&segment).unwrap().sender().is_ok());
+    }
+
+    #[pg_test]
+    fn test_dynamic_bgworker_listen() {
+        let worker = BackgroundWorkerBuilder::new("dynamic_bgworker_listener")
+            .set_library("pgrx_tests")
+            .set_function("bgworker_listener")
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .load_dynamic();
+        worker.wait_for_startup().expect("no PID from the worker");
+        worker.wait_for_shutdown().expect("aborted shutdown");
+
+        assert_eq!(
+            Ok((Some("pgrx_tests_channel".to_string()), Some("hello".to_string()))),
+            Spi::get_two::<String, String>(
+                "SELECT channel, payload FROM tests.bgworker_notify_test;"
+            )
+        );
+    }
 }
@@ -0,0 +1,100 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::custom_scan::create_custom_path;
+    use pgrx::hooks::*;
+    use pgrx::prelude::*;
+    use std::ffi::CStr;
+
+    /// Pretends the table `custom_scan_test` contains the numbers 1 through 5
+    struct NumbersScan {
+        current: i32,
+    }
+
+    impl CustomScan for NumbersScan {
+        const NAME: &'static str = "NumbersScan";
+        type Row = (i32,);
+
+        fn begin_scan(_info: &CustomScanInfo) -> Self {
+            NumbersScan { current: 0 }
+        }
+
+        fn exec(&mut self) -> Option<Self::Row> {
+            if self.current >= 5 {
+                return None;
+            }
+            self.current += 1;
+            Some((self.current,))
+        }
+
+        fn rescan(&mut self) {
+            self.current = 0;
+        }
+
+        fn explain(&self) -> Vec<(String, String)> {
+            vec![("Numbers".into(), "1 to 5".into())]
+        }
+    }
+
+    struct CustomScanHook;
+
+    impl PgHooks for CustomScanHook {
+        fn set_rel_pathlist(
+            &mut self,
+            root: PgBox<pg_sys::PlannerInfo>,
+            rel: PgBox<pg_sys::RelOptInfo>,
+            rti: pg_sys::Index,
+            rte: PgBox<pg_sys::RangeTblEntry>,
+            prev_hook: fn(
+                PgBox<pg_sys::PlannerInfo>,
+                PgBox<pg_sys::RelOptInfo>,
+                pg_sys::Index,
+                PgBox<pg_sys::RangeTblEntry>,
+            ) -> HookResult<()>,
+        ) -> HookResult<()> {
+            unsafe {
+                if rte.rtekind == pg_sys::RTEKind_RTE_RELATION {
+                    let name = pg_sys::get_rel_name(rte.relid);
+                    if !name.is_null() && CStr::from_ptr(name).to_bytes() == b"custom_scan_test" {
+                        let mut path = create_custom_path::<NumbersScan>(rel.as_ptr());
+                        path.path.rows = 5.0;
+                        pg_sys::add_path(rel.as_ptr(), path.into_pg().cast());
+                    }
+                }
+            }
+            prev_hook(root, rel, rti, rte)
+        }
+    }
+
+    #[pg_test]
+    unsafe fn test_custom_scan() -> Result<(), spi::Error> {
+        static mut HOOK: CustomScanHook = CustomScanHook;
+        pgrx::hooks::register_hook(&mut HOOK);
+
+        Spi::run("CREATE TABLE custom_scan_test (id int4)")?;
+
+        let plan = Spi::explain("SELECT * FROM custom_scan_test")?;
+        let plan = plan.0.to_string();
+        assert!(plan.contains("NumbersScan"));
+        assert!(plan.contains("1 to 5"));
+
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM custom_scan_test")?, Some(5));
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT sum(id) FROM custom_scan_test WHERE id > 2")?,
+            Some(12)
+        );
+        Ok(())
+    }
+}
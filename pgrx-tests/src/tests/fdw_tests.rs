@@ -0,0 +1,158 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+struct NumbersFdw {
+    current: i64,
+    max: i64,
+}
+
+#[pg_fdw]
+impl ForeignDataWrapper for NumbersFdw {
+    type Row = (i64, String);
+
+    fn validate(options: HashMap<String, String>, _catalog: FdwOptionCatalog) {
+        if let Some(max) = options.get("max") {
+            if max.parse::<i64>().is_err() {
+                error!("`max` must be an integer, not `{}`", max)
+            }
+        }
+    }
+
+    fn begin_scan(table: &PgForeignTable) -> Self {
+        let max = table.options().get("max").map(|max| max.parse().unwrap()).unwrap_or(10);
+        NumbersFdw { current: 0, max }
+    }
+
+    fn iterate_scan(&mut self) -> Option<Self::Row> {
+        if self.current >= self.max {
+            return None;
+        }
+        self.current += 1;
+        Some((self.current, format!("number {}", self.current)))
+    }
+
+    fn re_scan(&mut self) {
+        self.current = 0;
+    }
+}
+
+/// Rows of every `kv_fdw` table in this backend, keyed by the table's oid so tests don't see
+/// each other's data
+static KV_STORE: Mutex<BTreeMap<u32, BTreeMap<i64, String>>> = Mutex::new(BTreeMap::new());
+
+struct KvFdw {
+    table: pg_sys::Oid,
+    rows: Vec<(i64, String)>,
+    pos: usize,
+}
+
+impl KvFdw {
+    fn with_store<R>(&self, f: impl FnOnce(&mut BTreeMap<i64, String>) -> R) -> R {
+        f(KV_STORE.lock().unwrap().entry(self.table.as_u32()).or_default())
+    }
+}
+
+#[pg_fdw]
+impl ForeignDataWrapper for KvFdw {
+    type Row = (i64, String);
+    const ROWID_COLUMN: Option<&'static str> = Some("key");
+
+    fn begin_scan(table: &PgForeignTable) -> Self {
+        let mut fdw = KvFdw { table: table.relation().oid(), rows: Vec::new(), pos: 0 };
+        fdw.rows = fdw.with_store(|store| store.clone().into_iter().collect());
+        fdw
+    }
+
+    fn iterate_scan(&mut self) -> Option<Self::Row> {
+        let row = self.rows.get(self.pos).cloned();
+        self.pos += 1;
+        row
+    }
+
+    fn re_scan(&mut self) {
+        self.pos = 0;
+    }
+
+    fn begin_modify(table: &PgForeignTable) -> Self {
+        KvFdw { table: table.relation().oid(), rows: Vec::new(), pos: 0 }
+    }
+
+    fn insert(&mut self, new_row: PgHeapTuple<'_, AllocatedByPostgres>) {
+        let key = new_row.get_by_name::<i64>("key").unwrap().unwrap();
+        let value = new_row.get_by_name::<String>("value").unwrap().unwrap_or_default();
+        self.with_store(|store| store.insert(key, value));
+    }
+
+    fn update(&mut self, rowid: FdwRowId, new_row: PgHeapTuple<'_, AllocatedByPostgres>) {
+        let key = rowid.value::<i64>().unwrap().unwrap();
+        let value = new_row.get_by_name::<String>("value").unwrap().unwrap_or_default();
+        self.with_store(|store| store.insert(key, value));
+    }
+
+    fn delete(&mut self, rowid: FdwRowId) {
+        let key = rowid.value::<i64>().unwrap().unwrap();
+        self.with_store(|store| store.remove(&key));
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_fdw_scan() -> Result<(), spi::Error> {
+        Spi::run("CREATE SERVER numbers_server FOREIGN DATA WRAPPER numbers_fdw")?;
+        Spi::run(
+            "CREATE FOREIGN TABLE numbers (id bigint, label text) SERVER numbers_server OPTIONS (max '5')",
+        )?;
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM numbers")?;
+        assert_eq!(count, Some(5));
+
+        let sum = Spi::get_one::<i64>("SELECT sum(id)::bigint FROM numbers WHERE id > 2")?;
+        assert_eq!(sum, Some(12));
+
+        let label = Spi::get_one::<String>("SELECT label FROM numbers WHERE id = 3")?;
+        assert_eq!(label.as_deref(), Some("number 3"));
+        Ok(())
+    }
+
+    #[pg_test(error = "`max` must be an integer, not `lots`")]
+    fn test_fdw_validator() -> Result<(), spi::Error> {
+        Spi::run(
+            "CREATE SERVER numbers_server FOREIGN DATA WRAPPER numbers_fdw OPTIONS (max 'lots')",
+        )
+    }
+
+    #[pg_test]
+    fn test_fdw_modify() -> Result<(), spi::Error> {
+        Spi::run("CREATE SERVER kv_server FOREIGN DATA WRAPPER kv_fdw")?;
+        Spi::run("CREATE FOREIGN TABLE kv (key bigint, value text) SERVER kv_server")?;
+
+        Spi::run("INSERT INTO kv VALUES (1, 'one'), (2, 'two'), (3, 'three')")?;
+        Spi::run("UPDATE kv SET value = 'TWO' WHERE key = 2")?;
+        Spi::run("DELETE FROM kv WHERE key = 3")?;
+
+        let values = Spi::get_one::<String>("SELECT string_agg(value, ',' ORDER BY key) FROM kv")?;
+        assert_eq!(values.as_deref(), Some("one,TWO"));
+
+        // another table on the same server has its own rows
+        Spi::run("CREATE FOREIGN TABLE kv_other (key bigint, value text) SERVER kv_server")?;
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM kv_other")?, Some(0));
+        Ok(())
+    }
+}
@@ -13,6 +13,8 @@ mod tests {
     #[allow(unused_imports)]
     use crate as pgrx_tests;
     use std::ffi::CStr;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::time::Duration;
 
     use pgrx::guc::*;
     use pgrx::prelude::*;
@@ -78,6 +80,81 @@ mod tests {
         assert_eq!(GUC.get(), 1024);
     }
 
+    #[pg_test]
+    fn test_duration_guc() {
+        static GUC: GucSetting<Duration> = GucSetting::<Duration>::new(Duration::from_secs(30));
+        GucRegistry::define_duration_guc(
+            "test.duration",
+            "test duration guc",
+            "test duration guc",
+            &GUC,
+            Duration::ZERO,
+            Duration::from_secs(60 * 60),
+            GucTimeUnit::Seconds,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+        assert_eq!(GUC.get(), Duration::from_secs(30));
+
+        Spi::run("SET test.duration = '5min'").expect("SPI failed");
+        assert_eq!(GUC.get(), Duration::from_secs(5 * 60));
+        assert_eq!(Spi::get_one::<String>("SHOW test.duration"), Ok(Some(String::from("5min"))));
+
+        // without a unit, the value is in the registered unit
+        Spi::run("SET test.duration = 90").expect("SPI failed");
+        assert_eq!(GUC.get(), Duration::from_secs(90));
+    }
+
+    #[pg_test(error = "test.duration_checked must be at most 10min")]
+    fn test_duration_guc_check_hook() {
+        static GUC: GucSetting<Duration> = GucSetting::<Duration>::new(Duration::from_secs(30));
+        GucRegistry::define_duration_guc_with_hooks(
+            "test.duration_checked",
+            "test checked duration guc",
+            "test checked duration guc",
+            &GUC,
+            Duration::ZERO,
+            Duration::from_secs(60 * 60),
+            GucTimeUnit::Seconds,
+            GucContext::Userset,
+            GucFlags::default(),
+            // the hook sees the value in seconds
+            GucHooks::<i32>::new().check(|seconds| match seconds {
+                0..=600 => Ok(()),
+                _ => Err(GucCheckError::new()
+                    .with_message("test.duration_checked must be at most 10min")),
+            }),
+        );
+
+        Spi::run("SET test.duration_checked = '5min'").expect("SPI failed");
+        assert_eq!(GUC.get(), Duration::from_secs(5 * 60));
+        Spi::run("SET test.duration_checked = '1h'").expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_memory_guc() {
+        static GUC: GucSetting<usize> = GucSetting::<usize>::new(8 * 1024 * 1024);
+        GucRegistry::define_memory_guc(
+            "test.memory",
+            "test memory guc",
+            "test memory guc",
+            &GUC,
+            0,
+            1024 * 1024 * 1024,
+            GucMemoryUnit::Kilobytes,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+        assert_eq!(GUC.get(), 8 * 1024 * 1024);
+
+        Spi::run("SET test.memory = '64MB'").expect("SPI failed");
+        assert_eq!(GUC.get(), 64 * 1024 * 1024);
+        assert_eq!(Spi::get_one::<String>("SHOW test.memory"), Ok(Some(String::from("64MB"))));
+
+        Spi::run("SET test.memory = 100").expect("SPI failed");
+        assert_eq!(GUC.get(), 100 * 1024);
+    }
+
     #[pg_test]
     fn test_float_guc() {
         static GUC: GucSetting<f64> = GucSetting::<f64>::new(42.42);
@@ -173,4 +250,189 @@ mod tests {
         Spi::run("SET test.enum = 'three'").expect("SPI failed");
         assert_eq!(GUC.get(), TestEnum::Three);
     }
+
+    #[pg_test]
+    fn test_guc_check_hook() {
+        static GUC: GucSetting<Option<&'static CStr>> =
+            GucSetting::<Option<&'static CStr>>::new(None);
+        GucRegistry::define_string_guc_with_hooks(
+            "test.checked",
+            "test checked guc",
+            "test checked guc",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+            GucHooks::<Option<&'static CStr>>::new().check(|url| match url {
+                Some(url) if !url.to_bytes().starts_with(b"https://") => {
+                    Err(GucCheckError::new().with_detail("The URL must use https."))
+                }
+                _ => Ok(()),
+            }),
+        );
+
+        Spi::run("SET test.checked = 'https://example.com'").expect("SPI failed");
+        assert_eq!(GUC.get().unwrap().to_str().unwrap(), "https://example.com");
+    }
+
+    #[pg_test(error = "test.rejected must be even")]
+    fn test_guc_check_hook_rejects() {
+        static GUC: GucSetting<i32> = GucSetting::<i32>::new(0);
+        GucRegistry::define_int_guc_with_hooks(
+            "test.rejected",
+            "test rejected guc",
+            "test rejected guc",
+            &GUC,
+            0,
+            100,
+            GucContext::Userset,
+            GucFlags::default(),
+            GucHooks::<i32>::new().check(|value| match value % 2 {
+                0 => Ok(()),
+                _ => Err(GucCheckError::new()
+                    .with_message("test.rejected must be even")
+                    .with_hint("Try 42.")),
+            }),
+        );
+
+        Spi::run("SET test.rejected = 3").expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_guc_assign_and_show_hooks() {
+        #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+        enum TestEnum {
+            One,
+            Two,
+        }
+        static GUC: GucSetting<TestEnum> = GucSetting::<TestEnum>::new(TestEnum::One);
+        static ASSIGNED: AtomicI32 = AtomicI32::new(-1);
+        GucRegistry::define_enum_guc_with_hooks(
+            "test.hooked",
+            "test hooked guc",
+            "test hooked guc",
+            &GUC,
+            GucContext::Userset,
+            GucFlags::default(),
+            GucHooks::<TestEnum>::new()
+                .assign(|value| ASSIGNED.store(value.to_ordinal(), Ordering::SeqCst))
+                .show(|| format!("{:?}!", GUC.get())),
+        );
+
+        Spi::run("SET test.hooked = 'two'").expect("SPI failed");
+        assert_eq!(GUC.get(), TestEnum::Two);
+        assert_eq!(ASSIGNED.load(Ordering::SeqCst), TestEnum::Two.to_ordinal());
+        assert_eq!(Spi::get_one::<String>("SHOW test.hooked"), Ok(Some(String::from("Two!"))));
+    }
+
+    #[pg_test]
+    fn test_guc_get() {
+        Spi::run("SET work_mem = '8MB'").expect("SPI failed");
+        assert_eq!(Guc::get::<i32>("work_mem"), Some(8 * 1024));
+        assert_eq!(Guc::get::<usize>("work_mem"), Some(8 * 1024 * 1024));
+        assert_eq!(Guc::get::<String>("work_mem"), Some(String::from("8MB")));
+        assert_eq!(Guc::get::<Duration>("work_mem"), None);
+
+        Spi::run("SET statement_timeout = '2s'").expect("SPI failed");
+        assert_eq!(Guc::get::<Duration>("statement_timeout"), Some(Duration::from_secs(2)));
+
+        Spi::run("SET enable_seqscan = off").expect("SPI failed");
+        assert_eq!(Guc::get::<bool>("enable_seqscan"), Some(false));
+
+        assert_eq!(Guc::get::<String>("test.does_not_exist"), None);
+    }
+
+    #[pg_test]
+    fn test_guc_with_setting() {
+        Spi::run("SET work_mem = '4MB'").expect("SPI failed");
+        let inner = Guc::with_setting("work_mem", "16MB", || Guc::get::<String>("work_mem"));
+        assert_eq!(inner, Some(String::from("16MB")));
+        assert_eq!(Guc::get::<String>("work_mem"), Some(String::from("4MB")));
+    }
+
+    #[pg_test]
+    fn test_guc_with_setting_restores_after_error() {
+        Spi::run("SET work_mem = '4MB'").expect("SPI failed");
+        PgTryBuilder::new(|| {
+            Guc::with_setting("work_mem", "16MB", || {
+                error!("oops");
+            })
+        })
+        .catch_others(|_| ())
+        .execute();
+        assert_eq!(Guc::get::<String>("work_mem"), Some(String::from("4MB")));
+    }
+
+    #[pg_test]
+    fn test_guc_with_setting_keeps_inner_set() {
+        Spi::run("SET work_mem = '4MB'").expect("SPI failed");
+        Guc::with_setting("work_mem", "16MB", || {
+            Spi::run("SET maintenance_work_mem = '32MB'").expect("SPI failed")
+        });
+        assert_eq!(Guc::get::<String>("work_mem"), Some(String::from("4MB")));
+        assert_eq!(Guc::get::<String>("maintenance_work_mem"), Some(String::from("32MB")));
+    }
+
+    #[pg_test]
+    fn test_derive_postgres_guc() {
+        #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+        enum Mode {
+            Fast,
+            Slow,
+        }
+
+        #[derive(PostgresGuc)]
+        #[guc(prefix = "test_derive")]
+        struct Settings {
+            /// Whether things are enabled
+            enabled: GucSetting<bool>,
+
+            /// How many things there are
+            ///
+            /// There can't be more than ten.
+            #[guc(min = 0, max = 10)]
+            count: GucSetting<i32>,
+
+            /// How long things take
+            #[guc(unit = GucTimeUnit::Seconds, context = GucContext::Suset)]
+            timeout: GucSetting<Duration>,
+
+            /// How things are done
+            #[guc(name = "how")]
+            mode: GucSetting<Mode>,
+        }
+
+        static SETTINGS: Settings = Settings {
+            enabled: GucSetting::<bool>::new(true),
+            count: GucSetting::<i32>::new(3),
+            timeout: GucSetting::<Duration>::new(Duration::from_secs(30)),
+            mode: GucSetting::<Mode>::new(Mode::Fast),
+        };
+        SETTINGS.register();
+
+        Spi::run("SET test_derive.enabled = off").expect("SPI failed");
+        Spi::run("SET test_derive.count = 7").expect("SPI failed");
+        Spi::run("SET test_derive.timeout = '1min'").expect("SPI failed");
+        Spi::run("SET test_derive.how = 'slow'").expect("SPI failed");
+        assert_eq!(SETTINGS.enabled.get(), false);
+        assert_eq!(SETTINGS.count.get(), 7);
+        assert_eq!(SETTINGS.timeout.get(), Duration::from_secs(60));
+        assert_eq!(SETTINGS.mode.get(), Mode::Slow);
+
+        let descriptions = Spi::get_two::<String, String>(
+            "SELECT short_desc, extra_desc FROM pg_settings WHERE name = 'test_derive.count'",
+        );
+        assert_eq!(
+            descriptions,
+            Ok((
+                Some(String::from("How many things there are")),
+                Some(String::from("There can't be more than ten."))
+            ))
+        );
+        assert_eq!(
+            Spi::get_one::<i64>(
+                "SELECT max_val::bigint FROM pg_settings WHERE name = 'test_derive.count'"
+            ),
+            Ok(Some(10))
+        );
+    }
 }
@@ -133,4 +133,207 @@ mod tests {
         // TODO:  it'd be nice to also test that .commit() and .abort() also get called
         //    but I don't see how to do that since we're running *inside* a transaction here
     }
+
+    #[pg_test]
+    unsafe fn test_more_hooks() -> Result<(), spi::Error> {
+        struct TestHook {
+            hooked_function: pg_sys::Oid,
+            object_accesses: u32,
+            explains: u32,
+            fmgr_starts: u32,
+            fmgr_ends: u32,
+            passwords: u32,
+        }
+        impl PgHooks for TestHook {
+            fn optional_hooks(&self) -> OptionalHooks {
+                OptionalHooks::OBJECT_ACCESS
+                    | OptionalHooks::EXPLAIN_ONE_QUERY
+                    | OptionalHooks::FMGR
+                    | OptionalHooks::CHECK_PASSWORD
+            }
+
+            fn object_access(
+                &mut self,
+                access: pg_sys::ObjectAccessType,
+                class_id: pg_sys::Oid,
+                object_id: pg_sys::Oid,
+                sub_id: i32,
+                arg: *mut std::os::raw::c_void,
+                prev_hook: fn(
+                    pg_sys::ObjectAccessType,
+                    pg_sys::Oid,
+                    pg_sys::Oid,
+                    i32,
+                    *mut std::os::raw::c_void,
+                ) -> HookResult<()>,
+            ) -> HookResult<()> {
+                self.object_accesses += 1;
+                prev_hook(access, class_id, object_id, sub_id, arg)
+            }
+
+            fn explain_one_query(
+                &mut self,
+                query: PgBox<pg_sys::Query>,
+                cursor_options: i32,
+                into: PgBox<pg_sys::IntoClause>,
+                es: PgBox<pg_sys::ExplainState>,
+                query_string: &core::ffi::CStr,
+                params: PgBox<pg_sys::ParamListInfoData>,
+                query_env: PgBox<pg_sys::QueryEnvironment>,
+                prev_hook: fn(
+                    PgBox<pg_sys::Query>,
+                    i32,
+                    PgBox<pg_sys::IntoClause>,
+                    PgBox<pg_sys::ExplainState>,
+                    &core::ffi::CStr,
+                    PgBox<pg_sys::ParamListInfoData>,
+                    PgBox<pg_sys::QueryEnvironment>,
+                ) -> HookResult<()>,
+            ) -> HookResult<()> {
+                self.explains += 1;
+                prev_hook(query, cursor_options, into, es, query_string, params, query_env)
+            }
+
+            fn needs_fmgr(
+                &mut self,
+                fn_oid: pg_sys::Oid,
+                prev_hook: fn(pg_sys::Oid) -> HookResult<bool>,
+            ) -> HookResult<bool> {
+                if fn_oid == self.hooked_function {
+                    return HookResult::new(true);
+                }
+                prev_hook(fn_oid)
+            }
+
+            fn fmgr(
+                &mut self,
+                event: pg_sys::FmgrHookEventType,
+                flinfo: PgBox<pg_sys::FmgrInfo>,
+                arg: *mut pg_sys::Datum,
+                prev_hook: fn(
+                    pg_sys::FmgrHookEventType,
+                    PgBox<pg_sys::FmgrInfo>,
+                    *mut pg_sys::Datum,
+                ) -> HookResult<()>,
+            ) -> HookResult<()> {
+                match event {
+                    pg_sys::FmgrHookEventType_FHET_START => self.fmgr_starts += 1,
+                    pg_sys::FmgrHookEventType_FHET_END => self.fmgr_ends += 1,
+                    _ => {}
+                }
+                prev_hook(event, flinfo, arg)
+            }
+
+            fn check_password(
+                &mut self,
+                username: &core::ffi::CStr,
+                shadow_pass: &core::ffi::CStr,
+                password_type: pg_sys::PasswordType,
+                valid_until: Option<TimestampWithTimeZone>,
+                prev_hook: fn(
+                    &core::ffi::CStr,
+                    &core::ffi::CStr,
+                    pg_sys::PasswordType,
+                    Option<TimestampWithTimeZone>,
+                ) -> HookResult<()>,
+            ) -> HookResult<()> {
+                assert_eq!(username.to_str(), Ok("hooked_role"));
+                assert_eq!(valid_until, None);
+                self.passwords += 1;
+                prev_hook(username, shadow_pass, password_type, valid_until)
+            }
+        }
+
+        static mut HOOK: TestHook = TestHook {
+            hooked_function: pg_sys::InvalidOid,
+            object_accesses: 0,
+            explains: 0,
+            fmgr_starts: 0,
+            fmgr_ends: 0,
+            passwords: 0,
+        };
+        Spi::run("CREATE FUNCTION tests.hooked_function() RETURNS int LANGUAGE sql AS 'SELECT 1'")?;
+        HOOK.hooked_function =
+            Spi::get_one::<pg_sys::Oid>("SELECT 'tests.hooked_function'::regproc::oid")?.unwrap();
+        pgrx::hooks::register_hook(&mut HOOK);
+
+        Spi::run("CREATE TABLE tests.hooked_table (id int)")?;
+        assert!(HOOK.object_accesses > 0);
+
+        // our hook falls back to what Postgres does without one
+        let plan = Spi::get_one::<String>("EXPLAIN SELECT 1")?.unwrap();
+        assert!(plan.starts_with("Result"));
+        assert_eq!(HOOK.explains, 1);
+
+        assert_eq!(Spi::get_one::<i32>("SELECT tests.hooked_function()")?, Some(1));
+        assert!(HOOK.fmgr_starts > 0);
+        assert_eq!(HOOK.fmgr_starts, HOOK.fmgr_ends);
+
+        Spi::run("CREATE ROLE hooked_role PASSWORD 'secret'")?;
+        assert_eq!(HOOK.passwords, 1);
+        Ok(())
+    }
+
+    #[pg_test]
+    unsafe fn test_multiple_hooks() {
+        static CALLS: std::sync::Mutex<Vec<&str>> = std::sync::Mutex::new(Vec::new());
+
+        struct FirstHook;
+        impl PgHooks for FirstHook {
+            fn executor_start(
+                &mut self,
+                query_desc: PgBox<pg_sys::QueryDesc>,
+                eflags: i32,
+                prev_hook: fn(PgBox<pg_sys::QueryDesc>, i32) -> HookResult<()>,
+            ) -> HookResult<()> {
+                CALLS.lock().unwrap().push("first executor_start");
+                prev_hook(query_desc, eflags)
+            }
+
+            fn emit_log(
+                &mut self,
+                _error_data: PgBox<pg_sys::ErrorData>,
+                _prev_hook: fn(PgBox<pg_sys::ErrorData>) -> HookResult<()>,
+            ) -> HookResult<()> {
+                // the chain stops here
+                CALLS.lock().unwrap().push("first emit_log");
+                HookResult::new(())
+            }
+        }
+
+        struct SecondHook;
+        impl PgHooks for SecondHook {
+            fn executor_start(
+                &mut self,
+                query_desc: PgBox<pg_sys::QueryDesc>,
+                eflags: i32,
+                prev_hook: fn(PgBox<pg_sys::QueryDesc>, i32) -> HookResult<()>,
+            ) -> HookResult<()> {
+                CALLS.lock().unwrap().push("second executor_start");
+                prev_hook(query_desc, eflags)
+            }
+
+            fn emit_log(
+                &mut self,
+                error_data: PgBox<pg_sys::ErrorData>,
+                prev_hook: fn(PgBox<pg_sys::ErrorData>) -> HookResult<()>,
+            ) -> HookResult<()> {
+                CALLS.lock().unwrap().push("second emit_log");
+                prev_hook(error_data)
+            }
+        }
+
+        static mut FIRST: FirstHook = FirstHook;
+        static mut SECOND: SecondHook = SecondHook;
+        pgrx::hooks::register_hook_with_order(&mut SECOND, 1);
+        pgrx::hooks::register_hook(&mut FIRST);
+
+        Spi::run("SET local log_statement to 'all'; SELECT 1").expect("SPI failed");
+        let calls = CALLS.lock().unwrap();
+        assert_eq!(calls.iter().filter(|call| **call == "first emit_log").count(), 1);
+        assert!(!calls.contains(&"second emit_log"));
+        let executor_starts =
+            calls.iter().filter(|call| call.ends_with("executor_start")).collect::<Vec<_>>();
+        assert_eq!(executor_starts, [&"first executor_start", &"second executor_start"]);
+    }
 }
@@ -0,0 +1,149 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::index_am::{index_build_heap_scan, BulkDeleteCallback};
+use pgrx::itemptr::{item_pointer_to_u64, u64_to_item_pointer};
+use pgrx::prelude::*;
+use pgrx::PgRelation;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A toy index over `int4` columns which keeps its entries in backend-local memory
+struct MemIndex {
+    index: pg_sys::Oid,
+    matches: Vec<u64>,
+}
+
+static MEM_INDEXES: Mutex<Option<HashMap<u32, Vec<(i32, u64)>>>> = Mutex::new(None);
+
+fn with_entries<R>(index: pg_sys::Oid, f: impl FnOnce(&mut Vec<(i32, u64)>) -> R) -> R {
+    let mut indexes = MEM_INDEXES.lock().unwrap();
+    f(indexes.get_or_insert_with(HashMap::new).entry(index.as_u32()).or_default())
+}
+
+#[pg_index_am]
+impl IndexAccessMethod for MemIndex {
+    const STRATEGIES: u16 = 1;
+
+    fn build(
+        heap: &PgRelation,
+        index: &PgRelation,
+        index_info: *mut pg_sys::IndexInfo,
+    ) -> IndexBuildStats {
+        let mut entries = Vec::new();
+        let heap_tuples =
+            index_build_heap_scan(heap, index, index_info, |tid, values, isnull, _| {
+                if !isnull[0] {
+                    let value = unsafe { i32::from_datum(values[0], false) }.unwrap();
+                    entries.push((value, item_pointer_to_u64(tid)));
+                }
+            });
+        let index_tuples = entries.len() as f64;
+        with_entries(index.oid(), |existing| *existing = entries);
+        IndexBuildStats { heap_tuples, index_tuples }
+    }
+
+    fn insert(
+        index: &PgRelation,
+        values: &[pg_sys::Datum],
+        isnull: &[bool],
+        heap_tid: pg_sys::ItemPointerData,
+        _heap: &PgRelation,
+        _check_unique: pg_sys::IndexUniqueCheck,
+    ) -> bool {
+        if !isnull[0] {
+            let value = unsafe { i32::from_datum(values[0], false) }.unwrap();
+            with_entries(index.oid(), |entries| {
+                entries.push((value, item_pointer_to_u64(heap_tid)))
+            });
+        }
+        false
+    }
+
+    fn bulk_delete(
+        info: &pg_sys::IndexVacuumInfo,
+        stats: &mut pg_sys::IndexBulkDeleteResult,
+        callback: &BulkDeleteCallback,
+    ) {
+        let index = unsafe { PgRelation::from_pg(info.index) };
+        with_entries(index.oid(), |entries| {
+            entries.retain(|(_, tid)| {
+                let mut heap_tid = pg_sys::ItemPointerData::default();
+                u64_to_item_pointer(*tid, &mut heap_tid);
+                let dead = callback.is_dead(heap_tid);
+                if dead {
+                    stats.tuples_removed += 1.0;
+                }
+                !dead
+            });
+            stats.num_index_tuples = entries.len() as f64;
+        });
+    }
+
+    fn begin_scan(index: &PgRelation, _nkeys: i32, _norderbys: i32) -> Self {
+        MemIndex { index: index.oid(), matches: Vec::new() }
+    }
+
+    fn rescan(&mut self, keys: &[pg_sys::ScanKeyData], _orderbys: &[pg_sys::ScanKeyData]) {
+        let wanted = keys
+            .iter()
+            .map(|key| unsafe { i32::from_datum(key.sk_argument, false) }.unwrap())
+            .collect::<Vec<_>>();
+        self.matches = with_entries(self.index, |entries| {
+            entries
+                .iter()
+                .filter(|(value, _)| wanted.iter().all(|wanted| wanted == value))
+                .map(|(_, tid)| *tid)
+                .rev()
+                .collect()
+        });
+    }
+
+    fn get_tuple(&mut self, _direction: pg_sys::ScanDirection) -> Option<IndexScanItem> {
+        let tid = self.matches.pop()?;
+        let mut heap_tid = pg_sys::ItemPointerData::default();
+        u64_to_item_pointer(tid, &mut heap_tid);
+        Some(IndexScanItem { heap_tid, recheck: false })
+    }
+}
+
+extension_sql!(
+    r#"
+CREATE OPERATOR CLASS mem_index_int4_ops DEFAULT FOR TYPE int4 USING mem_index AS
+    OPERATOR 1 = (int4, int4);
+"#,
+    name = "mem_index_int4_ops",
+    requires = [MemIndex]
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_index_am_scan() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE mem_test (id int4)")?;
+        Spi::run("INSERT INTO mem_test SELECT generate_series(1, 100)")?;
+        Spi::run("CREATE INDEX idx_mem_test ON mem_test USING mem_index (id)")?;
+        Spi::run("INSERT INTO mem_test VALUES (42), (1000)")?;
+        Spi::run("SET LOCAL enable_seqscan TO off")?;
+
+        let plan = Spi::explain("SELECT * FROM mem_test WHERE id = 42")?;
+        assert!(plan.0.to_string().contains("idx_mem_test"));
+
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM mem_test WHERE id = 42")?, Some(2));
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM mem_test WHERE id = 1000")?, Some(1));
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM mem_test WHERE id = 101")?, Some(0));
+        Ok(())
+    }
+}
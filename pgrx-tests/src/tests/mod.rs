@@ -14,17 +14,22 @@ mod attributes_tests;
 mod bgworker_tests;
 mod bytea_tests;
 mod cfg_tests;
+#[cfg(feature = "cshim")]
+mod custom_scan_tests;
 mod datetime_tests;
 mod default_arg_value_tests;
 mod derive_pgtype_lifetimes;
 mod enum_type_tests;
 mod fcinfo_tests;
+#[cfg(feature = "cshim")]
+mod fdw_tests;
 mod from_into_datum_tests;
 mod geo_tests;
 mod guc_tests;
 mod heap_tuple;
 #[cfg(feature = "cshim")]
 mod hooks_tests;
+mod index_am_tests;
 mod inet_tests;
 mod internal_tests;
 mod issue1134;
@@ -34,12 +39,16 @@ mod log_tests;
 mod memcxt_tests;
 mod name_tests;
 mod numeric_tests;
+#[cfg(feature = "cshim")]
+mod output_plugin_tests;
+mod parallel_tests;
 mod pg_extern_tests;
 mod pg_guard_tests;
 mod pg_try_tests;
 mod pgbox_tests;
 mod pgrx_module_qualification;
 mod postgres_type_tests;
+mod procedure_tests;
 mod range_tests;
 mod result_tests;
 mod roundtrip_tests;
@@ -48,9 +57,23 @@ mod shmem_tests;
 mod spi_tests;
 mod srf_tests;
 mod struct_type_tests;
+#[cfg(all(
+    feature = "cshim",
+    any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16")
+))]
+mod support_tests;
+#[cfg(any(
+    feature = "pg12",
+    feature = "pg13",
+    feature = "pg14",
+    feature = "pg15",
+    feature = "pg16"
+))]
+mod table_am_tests;
 mod trigger_tests;
 mod uuid_tests;
 mod variadic_tests;
+mod window_tests;
 mod xact_callback_tests;
 mod xid64_tests;
 mod zero_datum_edge_cases;
@@ -0,0 +1,210 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::output_plugin::{Change, DecodingContext, ReorderBufferTxn};
+use pgrx::prelude::*;
+use pgrx::PgRelation;
+use std::num::NonZeroUsize;
+
+/// Describes each decoded change on its own line, in the spirit of `test_decoding`
+///
+/// Transactions without any (matching) changes are skipped, and the `table` option limits the
+/// changes to those of a single table.
+struct TestDecoding {
+    greeting: String,
+    table: Option<String>,
+    in_txn: bool,
+}
+
+impl TestDecoding {
+    fn wants(&self, relation: &PgRelation) -> bool {
+        self.table.as_deref().map(|table| table == relation.name()).unwrap_or(true)
+    }
+
+    /// Write the `BEGIN` of the current transaction, once it turns out to have changes
+    fn write_begin(&mut self, ctx: &mut DecodingContext) {
+        if !self.in_txn {
+            ctx.write("BEGIN");
+            self.in_txn = true;
+        }
+    }
+}
+
+impl OutputPlugin for TestDecoding {
+    fn startup(
+        _ctx: &mut DecodingContext,
+        options: &[(String, Option<String>)],
+        _is_init: bool,
+    ) -> Self {
+        let option = |option: &str| {
+            options.iter().find(|(name, _)| name == option).and_then(|(_, value)| value.clone())
+        };
+        TestDecoding {
+            greeting: option("greeting").unwrap_or_else(|| String::from("hello")),
+            table: option("table"),
+            in_txn: false,
+        }
+    }
+
+    fn begin(&mut self, _ctx: &mut DecodingContext, _txn: &ReorderBufferTxn) {
+        self.in_txn = false;
+    }
+
+    fn change(
+        &mut self,
+        ctx: &mut DecodingContext,
+        _txn: &ReorderBufferTxn,
+        relation: &PgRelation,
+        change: Change,
+    ) {
+        if !self.wants(relation) {
+            return;
+        }
+        self.write_begin(ctx);
+        let (action, tuple) = match change {
+            Change::Insert { new } => ("INSERT", new),
+            Change::Update { new, .. } => ("UPDATE", new),
+            Change::Delete { old } => ("DELETE", old),
+        };
+        let first_column = tuple
+            .and_then(|tuple| tuple.get_by_index::<i32>(NonZeroUsize::new(1).unwrap()).ok())
+            .flatten();
+        ctx.write(format!("{action} {}: {first_column:?}", relation.name()));
+    }
+
+    fn truncate(
+        &mut self,
+        ctx: &mut DecodingContext,
+        _txn: &ReorderBufferTxn,
+        relations: &[PgRelation],
+        _cascade: bool,
+        _restart_seqs: bool,
+    ) {
+        let names = relations
+            .iter()
+            .filter(|relation| self.wants(relation))
+            .map(|relation| relation.name())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return;
+        }
+        self.write_begin(ctx);
+        ctx.write(format!("TRUNCATE {}", names.join(", ")));
+    }
+
+    fn message(
+        &mut self,
+        ctx: &mut DecodingContext,
+        _txn: Option<&ReorderBufferTxn>,
+        _message_lsn: pg_sys::XLogRecPtr,
+        _transactional: bool,
+        prefix: &str,
+        message: &[u8],
+    ) {
+        ctx.write(format!("{} {prefix}: {}", self.greeting, String::from_utf8_lossy(message)));
+    }
+
+    fn commit(
+        &mut self,
+        ctx: &mut DecodingContext,
+        _txn: &ReorderBufferTxn,
+        _commit_lsn: pg_sys::XLogRecPtr,
+    ) {
+        if self.in_txn {
+            ctx.write("COMMIT");
+            self.in_txn = false;
+        }
+    }
+}
+
+pgrx::pg_output_plugin!(TestDecoding);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_output_plugin_message() -> Result<(), spi::Error> {
+        Spi::run(
+            "SELECT pg_create_logical_replication_slot('pgrx_tests_decoding', 'pgrx_tests', true)",
+        )?;
+
+        // non-transactional messages are decoded right away, without waiting for this
+        // transaction to commit
+        Spi::run("SELECT pg_logical_emit_message(false, 'pgrx', 'hi')")?;
+
+        let data = Spi::get_one::<String>(
+            "SELECT string_agg(data, '|') FROM pg_logical_slot_get_changes('pgrx_tests_decoding', NULL, NULL, 'greeting', 'howdy')",
+        )?;
+        assert_eq!(data, Some(String::from("howdy pgrx: hi")));
+
+        // everything was consumed
+        let data = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_logical_slot_get_changes('pgrx_tests_decoding', NULL, NULL)",
+        )?;
+        assert_eq!(data, Some(0));
+        Ok(())
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn test_output_plugin_changes() -> eyre::Result<()> {
+        // the changes have to be committed before they can be decoded, which a `#[pg_test]` can't
+        pgrx_tests::run_client_test(crate::pg_test::postgresql_conf_options(), |client| {
+            client.batch_execute("DROP TABLE IF EXISTS tests.decoded")?;
+            client.batch_execute("CREATE TABLE tests.decoded (id int PRIMARY KEY)")?;
+            // each of these runs in its own transaction, as the slot can't be created in one that
+            // has already written something
+            for statement in [
+                "SELECT pg_create_logical_replication_slot('pgrx_tests_changes', 'pgrx_tests', true)",
+                "INSERT INTO tests.decoded VALUES (1), (2)",
+                "UPDATE tests.decoded SET id = 3 WHERE id = 2",
+                "DELETE FROM tests.decoded WHERE id = 1",
+                "TRUNCATE tests.decoded",
+            ] {
+                client.batch_execute(statement)?;
+            }
+
+            let changes = client
+                .query(
+                    "SELECT data FROM pg_logical_slot_get_changes('pgrx_tests_changes', NULL, NULL, 'table', 'decoded')",
+                    &[],
+                )?
+                .iter()
+                .map(|row| row.get::<_, String>(0))
+                .collect::<Vec<_>>();
+            client.batch_execute("SELECT pg_drop_replication_slot('pgrx_tests_changes')")?;
+            client.batch_execute("DROP TABLE tests.decoded")?;
+
+            assert_eq!(
+                changes,
+                [
+                    "BEGIN",
+                    "INSERT decoded: Some(1)",
+                    "INSERT decoded: Some(2)",
+                    "COMMIT",
+                    "BEGIN",
+                    "UPDATE decoded: Some(3)",
+                    "COMMIT",
+                    "BEGIN",
+                    "DELETE decoded: Some(1)",
+                    "COMMIT",
+                    "BEGIN",
+                    "TRUNCATE decoded",
+                    "COMMIT",
+                ]
+            );
+            Ok(())
+        })
+    }
+}
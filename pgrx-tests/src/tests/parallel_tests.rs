@@ -0,0 +1,79 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::parallel::{ParallelKey, ParallelWorker};
+use pgrx::prelude::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const TOTAL: ParallelKey<AtomicU64> = ParallelKey::new(1);
+const UPTO: ParallelKey<u64> = ParallelKey::new(2);
+
+#[pg_guard]
+#[no_mangle]
+/// Adds up the numbers to `UPTO` into `TOTAL`
+pub extern "C" fn parallel_sum_worker(_seg: *mut pg_sys::dsm_segment, toc: *mut pg_sys::shm_toc) {
+    let worker = unsafe { ParallelWorker::from_toc(toc) };
+    let sum = (1..=*worker.shared(UPTO)).sum();
+    worker.shared(TOTAL).fetch_add(sum, Ordering::SeqCst);
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn parallel_error_worker(
+    _seg: *mut pg_sys::dsm_segment,
+    _toc: *mut pg_sys::shm_toc,
+) {
+    error!("parallel worker failed");
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use super::{TOTAL, UPTO};
+    use pgrx::parallel::ParallelContextBuilder;
+    use pgrx::prelude::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[pg_test]
+    fn test_parallel_context() {
+        let mut pcxt = ParallelContextBuilder::new("pgrx_tests", "parallel_sum_worker", 2)
+            .shared(TOTAL, AtomicU64::new(0))
+            .shared(UPTO, 100)
+            .build();
+        let launched = pcxt.launch() as u64;
+        pcxt.wait();
+        assert_eq!(pcxt.shared(UPTO), &100);
+        assert_eq!(pcxt.shared(TOTAL).load(Ordering::SeqCst), launched * 5050);
+    }
+
+    #[pg_test(error = "parallel key 2 holds a value of another type")]
+    fn test_parallel_key_type_mismatch() {
+        use pgrx::parallel::ParallelKey;
+
+        const UPTO_SIGNED: ParallelKey<i64> = ParallelKey::new(2);
+        let pcxt = ParallelContextBuilder::new("pgrx_tests", "parallel_sum_worker", 0)
+            .shared(UPTO, 100)
+            .build();
+        pcxt.shared(UPTO_SIGNED);
+    }
+
+    #[pg_test(error = "parallel worker failed")]
+    fn test_parallel_context_worker_error() {
+        let mut pcxt =
+            ParallelContextBuilder::new("pgrx_tests", "parallel_error_worker", 1).build();
+        if pcxt.launch() == 0 {
+            // nothing to propagate an error from
+            error!("parallel worker failed");
+        }
+        pcxt.wait();
+    }
+}
@@ -146,4 +146,63 @@ mod tests {
         let result = Spi::get_one::<bool>(r#"SELECT tests."custom_name"()"#);
         assert_eq!(result, Ok(Some(true)));
     }
+
+    #[pg_extern(rows = 42)]
+    fn has_rows_estimate() -> SetOfIterator<'static, i32> {
+        SetOfIterator::new(0..1)
+    }
+
+    #[pg_test]
+    fn test_rows() {
+        let result =
+            Spi::get_one::<f32>("SELECT prorows FROM pg_proc WHERE proname = 'has_rows_estimate'");
+        assert_eq!(result, Ok(Some(42.0)));
+    }
+
+    #[pg_extern(immutable, leakproof)]
+    fn is_leakproof(x: i32) -> i32 {
+        x
+    }
+
+    #[pg_test]
+    fn test_leakproof() {
+        let result =
+            Spi::get_one::<bool>("SELECT proleakproof FROM pg_proc WHERE proname = 'is_leakproof'");
+        assert_eq!(result, Ok(Some(true)));
+    }
+
+    #[pg_extern(called_on_null_input)]
+    fn is_called_on_null_input(x: Option<i32>) -> Option<i32> {
+        x
+    }
+
+    #[pg_test]
+    fn test_called_on_null_input() {
+        let result = Spi::get_one::<bool>(
+            "SELECT proisstrict FROM pg_proc WHERE proname = 'is_called_on_null_input'",
+        );
+        assert_eq!(result, Ok(Some(false)));
+
+        let result = Spi::get_one::<i32>("SELECT is_called_on_null_input(NULL)");
+        assert_eq!(result, Ok(None));
+    }
+
+    #[pg_extern(set(work_mem = "1234kB", "pgrx_tests.setting" = "x", enable_seqscan = false))]
+    fn has_settings() -> Option<String> {
+        Spi::get_one("SELECT current_setting('work_mem')").unwrap()
+    }
+
+    #[pg_test]
+    fn test_set() {
+        let result = Spi::get_one::<String>(
+            "SELECT array_to_string(proconfig, ',') FROM pg_proc WHERE proname = 'has_settings'",
+        );
+        assert_eq!(
+            result,
+            Ok(Some("enable_seqscan=false,pgrx_tests.setting=x,work_mem=1234kB".to_string()))
+        );
+
+        let result = Spi::get_one::<String>(r#"SELECT tests."has_settings"()"#);
+        assert_eq!(result, Ok(Some("1234kB".to_string())));
+    }
 }
@@ -0,0 +1,92 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::prelude::*;
+
+#[pg_procedure]
+fn procedure_insert_numbers(
+    call: CallContext,
+    table_name: &str,
+    count: i64,
+    commit_every: i64,
+) -> Result<(), spi::Error> {
+    let query = format!("INSERT INTO {} VALUES ($1)", spi::quote_identifier(table_name));
+    Spi::connect_nonatomic(call, |mut spi| {
+        for i in 1..=count {
+            spi.client().update(
+                &query,
+                None,
+                Some(vec![(PgBuiltInOids::INT8OID.oid(), i.into_datum())]),
+            )?;
+            if commit_every > 0 && i % commit_every == 0 {
+                spi.commit();
+            }
+        }
+        Ok(())
+    })
+}
+
+#[pg_procedure]
+fn procedure_is_atomic(call: CallContext) {
+    assert!(call.is_atomic(), "expected an atomic CALL");
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_call_procedure() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE procedure_numbers (n bigint)")?;
+        Spi::run("CALL procedure_insert_numbers('procedure_numbers', 5, 0)")?;
+        assert_eq!(Spi::get_one("SELECT sum(n) FROM procedure_numbers")?, Some(15i64));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_call_is_atomic_in_transaction() -> Result<(), spi::Error> {
+        // tests run inside a transaction, so their `CALL`s are never nonatomic
+        Spi::run("CALL procedure_is_atomic()")
+    }
+
+    #[pg_test(error = "invalid transaction termination")]
+    fn test_commit_in_atomic_call() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE procedure_commit (n bigint)")?;
+        Spi::run("CALL procedure_insert_numbers('procedure_commit', 2, 1)")
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn test_commit_survives_rollback() -> eyre::Result<()> {
+        // only a `CALL` outside of a transaction block can commit, which a `#[pg_test]` can't make
+        pgrx_tests::run_client_test(crate::pg_test::postgresql_conf_options(), |client| {
+            client.batch_execute("DROP TABLE IF EXISTS procedure_committed")?;
+            client.batch_execute("CREATE TABLE procedure_committed (n bigint CHECK (n < 3))")?;
+
+            // 1 and 2 are committed before inserting 3 fails, which rolls back only what's after
+            let error = client
+                .batch_execute("CALL procedure_insert_numbers('procedure_committed', 3, 2)")
+                .expect_err("inserting 3 should violate the check constraint");
+            assert!(error.to_string().contains("procedure_committed_n_check"), "{error}");
+
+            let committed = client
+                .query("SELECT n FROM procedure_committed ORDER BY n", &[])?
+                .iter()
+                .map(|row| row.get::<_, i64>(0))
+                .collect::<Vec<_>>();
+            client.batch_execute("DROP TABLE procedure_committed")?;
+            assert_eq!(committed, [1, 2]);
+            Ok(())
+        })
+    }
+}
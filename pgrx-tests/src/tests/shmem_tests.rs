@@ -8,17 +8,25 @@
 //LICENSE
 //LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 use pgrx::prelude::*;
-use pgrx::{pg_shmem_init, PgAtomic, PgLwLock, PgSharedMemoryInitialization};
+use pgrx::stats::PgStats;
+use pgrx::{pg_shmem_init, pg_stats, PgAtomic, PgLwLock, PgSharedMemoryInitialization};
 use std::sync::atomic::AtomicBool;
 
 static ATOMIC: PgAtomic<AtomicBool> = PgAtomic::new();
 static LWLOCK: PgLwLock<bool> = PgLwLock::new();
 
+pg_stats! {
+    static STATS: PgStats<i64, 16, 2> = PgStats::with_histogram([1.0, 10.0]);
+    view = test_stats;
+    reset = test_stats_reset;
+}
+
 #[pg_guard]
 pub extern "C" fn _PG_init() {
     // This ensures that this functionality works across PostgreSQL versions
     pg_shmem_init!(ATOMIC);
     pg_shmem_init!(LWLOCK);
+    pg_shmem_init!(STATS);
 }
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
@@ -26,7 +34,7 @@ mod tests {
     #[allow(unused_imports)]
     use crate as pgrx_tests;
 
-    use crate::tests::shmem_tests::LWLOCK;
+    use crate::tests::shmem_tests::{LWLOCK, STATS};
     use pgrx::prelude::*;
 
     #[pg_test]
@@ -53,4 +61,80 @@ mod tests {
         });
         let _lock = LWLOCK.exclusive();
     }
+
+    #[pg_test]
+    pub fn test_stats() -> Result<(), spi::Error> {
+        STATS.reset();
+        STATS.increment(1);
+        STATS.add(1, 2);
+        for value in [0.5, 5.0, 50.0] {
+            STATS.observe(2, value);
+        }
+
+        let entry = STATS.get(&1).unwrap();
+        assert_eq!((entry.count, entry.total, entry.histogram), (3, 0.0, [0, 0]));
+        let entry = STATS.get(&2).unwrap();
+        assert_eq!((entry.count, entry.total, entry.histogram), (3, 55.5, [1, 1]));
+        assert_eq!(STATS.get(&3), None);
+
+        assert_eq!(
+            Spi::get_one::<Vec<i64>>("SELECT histogram FROM test_stats() WHERE key = 2")?,
+            Some(vec![1, 1])
+        );
+        Spi::run("SELECT test_stats_reset()")?;
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM test_stats()")?, Some(0));
+        Ok(())
+    }
+
+    #[pg_test]
+    pub fn test_stats_save_and_load() -> Result<(), Box<dyn std::error::Error>> {
+        // relative to the data directory
+        const PATH: &str = "pgrx_tests_stats.stat";
+
+        STATS.reset();
+        STATS.add(1, 3);
+        for value in [0.5, 5.0] {
+            STATS.observe(2, value);
+        }
+        let mut saved = STATS.entries();
+        saved.sort_by_key(|(key, _)| *key);
+
+        STATS.save(PATH)?;
+        STATS.reset();
+        STATS.increment(3);
+        STATS.load(PATH)?;
+        let mut loaded = STATS.entries();
+        loaded.sort_by_key(|(key, _)| *key);
+        assert_eq!(loaded[..2], saved[..]);
+        assert_eq!(STATS.get(&3).map(|entry| entry.count), Some(1));
+
+        std::fs::write(PATH, b"not statistics")?;
+        let error = STATS.load(PATH).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(PATH)?;
+        STATS.reset();
+        Ok(())
+    }
+
+    #[pg_test]
+    pub fn test_stats_reset_is_revoked_from_public() -> Result<(), spi::Error> {
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT has_function_privilege('public', 'test_stats_reset()', 'EXECUTE')"
+            )?,
+            Some(false)
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    pub fn test_stats_ignore_new_keys_when_full() {
+        STATS.reset();
+        for key in 0..32 {
+            STATS.increment(key);
+        }
+        assert_eq!(STATS.entries().len(), 16);
+        STATS.reset();
+    }
 }
@@ -522,4 +522,245 @@ mod tests {
         assert_eq!(Some("hello".to_string()), value);
         Ok(())
     }
+
+    #[derive(Debug, PartialEq, FromSpiRow)]
+    struct Dog {
+        name: String,
+        #[spi(rename = "age_in_years")]
+        age: i32,
+        owner: Option<String>,
+    }
+
+    #[pg_test]
+    fn test_from_spi_row() -> Result<(), spi::Error> {
+        let dogs = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT * FROM (VALUES ('Nami', 3, 'Eric'), ('Brandy', 9, NULL)) \
+                     AS dogs(name, age_in_years, owner)",
+                    None,
+                    None,
+                )?
+                .map(|row| row.into::<Dog>())
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        assert_eq!(
+            dogs,
+            vec![
+                Dog { name: "Nami".into(), age: 3, owner: Some("Eric".into()) },
+                Dog { name: "Brandy".into(), age: 9, owner: None },
+            ]
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_from_spi_row_errors() -> Result<(), spi::Error> {
+        Spi::connect(|client| {
+            let row = |query: &str| client.select(query, None, None)?.next().unwrap().into::<Dog>();
+            assert_eq!(
+                row("SELECT 'Nami' AS name, 3 AS age, NULL::text AS owner"),
+                Err(spi::Error::ColumnNotFound("age_in_years".into()))
+            );
+            assert_eq!(
+                row("SELECT 'Nami' AS name, 3::bigint AS age_in_years, NULL::text AS owner"),
+                Err(spi::Error::ColumnTypeMismatch {
+                    column: "age_in_years".into(),
+                    column_type: "bigint".into(),
+                    rust_type: std::any::type_name::<i32>(),
+                })
+            );
+            assert_eq!(
+                row("SELECT NULL::text AS name, 3 AS age_in_years, NULL::text AS owner"),
+                Err(spi::Error::NullColumn("name".into()))
+            );
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_tuple_args() -> Result<(), spi::Error> {
+        let (sum, text) =
+            Spi::get_two_with_args::<i64, String>("SELECT $1 + $2, $3", (40_i64, 2_i64, "pgrx"))?;
+        assert_eq!(Some(42), sum);
+        assert_eq!(Some("pgrx".to_string()), text);
+
+        let null = Spi::get_one_with_args::<i32>("SELECT $1", (None::<i32>,))?;
+        assert_eq!(None, null);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_dyn_args() -> Result<(), spi::Error> {
+        let name = String::from("pgrx");
+        let args: Vec<&dyn IntoDatumDyn> = vec![&name, &1_i32];
+        let rc = Spi::connect(|client| {
+            client.select("SELECT $1 || $2::text", None, args.as_slice())?.first().get::<String>(1)
+        })?;
+        assert_eq!(Some("pgrx1".to_string()), rc);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_typed_prepared_statement() -> Result<(), spi::Error> {
+        let rc = Spi::connect(|client| {
+            let prepared =
+                client.prepare("SELECT $1 * $2", std::marker::PhantomData::<(i64, i64)>)?;
+            assert!(matches!(
+                client.select(&prepared, None, (6_i64,)),
+                Err(spi::Error::PreparedStatementArgumentMismatch { expected: 2, got: 1 })
+            ));
+            client.select(&prepared, None, (6_i64, 7_i64))?.first().get::<i64>(1)
+        })?;
+        assert_eq!(Some(42), rc);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_prepared_statement_tuple_args() -> Result<(), spi::Error> {
+        let rc = Spi::connect(|client| {
+            let prepared =
+                client.prepare("SELECT $1", Some(vec![PgOid::BuiltIn(PgBuiltInOids::INT4OID)]))?;
+            client.select(&prepared, None, (42,))?.first().get::<i32>(1)
+        })?;
+        assert_eq!(Some(42), rc);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_owned_prepared_statement_tuple_args() -> Result<(), spi::Error> {
+        let prepared = Spi::connect(|client| {
+            Ok::<_, spi::Error>(
+                client.prepare("SELECT $1", std::marker::PhantomData::<(i32,)>)?.keep(),
+            )
+        })?;
+        let rc =
+            Spi::connect(|client| client.select(&prepared, None, (42,))?.first().get::<i32>(1))?;
+        assert_eq!(Some(42), rc);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_prepared_statement_argument_type_mismatch() -> Result<(), spi::Error> {
+        Spi::connect(|client| {
+            let prepared = client.prepare("SELECT $1", std::marker::PhantomData::<(i64,)>)?;
+            assert_eq!(
+                Err(spi::Error::PreparedStatementArgumentTypeMismatch {
+                    position: 1,
+                    expected: pg_sys::INT8OID,
+                    got: pg_sys::INT4OID,
+                }),
+                client.select(&prepared, None, (6_i32,)).map(|_| ())
+            );
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_prepared_cursor_argument_mismatch() -> Result<(), spi::Error> {
+        Spi::connect(|client| {
+            let prepared = client.prepare("SELECT $1", std::marker::PhantomData::<(i64,)>)?;
+            assert_eq!(
+                Err(spi::Error::PreparedStatementArgumentMismatch { expected: 1, got: 0 }),
+                client.try_open_cursor(&prepared, None).map(|_| ())
+            );
+            assert_eq!(
+                Err(spi::Error::PreparedStatementArgumentTypeMismatch {
+                    position: 1,
+                    expected: pg_sys::INT8OID,
+                    got: pg_sys::TEXTOID,
+                }),
+                client.try_open_cursor(&prepared, ("six",)).map(|_| ())
+            );
+            let mut cursor = client.try_open_cursor(&prepared, (6_i64,))?;
+            assert_eq!(Some(6), cursor.fetch(1)?.first().get::<i64>(1)?);
+            Ok(())
+        })
+    }
+
+    #[derive(FromSpiRow)]
+    struct Number {
+        n: i64,
+    }
+
+    #[pg_test]
+    fn test_cursor_into_iter() -> Result<(), spi::Error> {
+        Spi::connect(|client| {
+            let cursor = client.open_cursor("SELECT n FROM generate_series(1, 1000) n", None);
+            let numbers = cursor.into_iter::<Number>(64).collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(1000, numbers.len());
+            assert_eq!(500500, numbers.iter().map(|number| number.n).sum::<i64>());
+
+            // an exact multiple of the batch size needs one more, empty, fetch to finish
+            let cursor = client.open_cursor("SELECT n FROM generate_series(1, 128) n", None);
+            assert_eq!(128, cursor.into_iter::<Number>(64).count());
+
+            let cursor = client.open_cursor("SELECT n FROM generate_series(1, 0) n", None);
+            assert_eq!(0, cursor.into_iter::<Number>(64).count());
+
+            // values from earlier batches survive those batches being freed
+            let cursor = client.open_cursor(
+                "SELECT repeat('x', n), n::float8 / 2 FROM generate_series(1, 100) n",
+                None,
+            );
+            let rows = cursor
+                .into_iter::<(Option<String>, Option<f64>)>(16)
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(100, rows.len());
+            for (i, (text, half)) in rows.into_iter().enumerate() {
+                assert_eq!(Some("x".repeat(i + 1)), text);
+                assert_eq!(Some((i + 1) as f64 / 2.0), half);
+            }
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_spi_get_row() -> Result<(), spi::Error> {
+        let (a, b, c, d, e) =
+            Spi::get_row::<(Option<i32>, Option<String>, Option<bool>, Option<f64>, Option<i64>)>(
+                "SELECT 1, 'two', true, 4.0::float8, NULL::bigint",
+            )?;
+        assert_eq!(
+            (Some(1), Some("two".to_string()), Some(true), Some(4.0), None),
+            (a, b, c, d, e)
+        );
+
+        let (sum,): (Option<i32>,) = Spi::get_row_with_args("SELECT $1 + $2", (40, 2))?;
+        assert_eq!(Some(42), sum);
+
+        assert_eq!(
+            Err(spi::Error::InvalidPosition),
+            Spi::get_row::<(Option<i32>, Option<i32>)>("SELECT 1, 2 LIMIT 0")
+        );
+        assert_eq!(
+            Err(spi::Error::SpiError(spi::SpiErrorCodes::NoAttribute)),
+            Spi::get_row::<(Option<i32>, Option<i32>)>("SELECT 1")
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_get_all() -> Result<(), spi::Error> {
+        let rows = Spi::get_all::<(Option<i32>, Option<String>)>(
+            "SELECT n, n::text FROM generate_series(1, 3) n",
+        )?;
+        assert_eq!(
+            vec![
+                (Some(1), Some("1".to_string())),
+                (Some(2), Some("2".to_string())),
+                (Some(3), Some("3".to_string())),
+            ],
+            rows
+        );
+
+        let dogs = Spi::get_all_with_args::<Dog>(
+            "SELECT $1 AS name, 3 AS age_in_years, NULL::text AS owner",
+            ("Nami",),
+        )?;
+        assert_eq!(vec![Dog { name: "Nami".into(), age: 3, owner: None }], dogs);
+
+        assert!(Spi::get_all::<(Option<i32>,)>("SELECT 1 LIMIT 0")?.is_empty());
+        Ok(())
+    }
 }
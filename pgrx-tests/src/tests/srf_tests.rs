@@ -154,6 +154,28 @@ fn one_col_result_option<'a>(
     Ok(Some(TableIterator::new(std::iter::once((42,)))))
 }
 
+#[pg_extern(materialize)]
+fn materialized_generate_series(start: i32, end: i32) -> SetOfIterator<'static, Option<i32>> {
+    SetOfIterator::new((start..=end).map(|i| if i % 2 == 0 { None } else { Some(i) }))
+}
+
+#[pg_extern(materialize)]
+fn materialized_composite_set<'a>(
+    input: &'a str,
+) -> TableIterator<'a, (name!(idx, i32), name!(value, &'a str))> {
+    TableIterator::new(input.split_whitespace().enumerate().map(|(i, s)| ((i + 1) as i32, s)))
+}
+
+#[pg_extern(materialize)]
+fn materialized_none() -> Option<TableIterator<'static, (name!(a, i32), name!(b, i32))>> {
+    None
+}
+
+#[pg_extern(materialize)]
+fn materialized_one_col<'a>() -> TableIterator<'a, (name!(a, i32),)> {
+    TableIterator::new(std::iter::once((42,)))
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
 mod tests {
@@ -345,4 +367,30 @@ mod tests {
         assert_eq!(Spi::get_one::<i32>("SELECT * from one_col_result()"), Ok(Some(42)));
         assert_eq!(Spi::get_one::<i32>("SELECT * from one_col_result_option()"), Ok(Some(42)));
     }
+
+    #[pg_test]
+    pub fn test_materialized_setof() {
+        let result = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT array_agg(x) FROM materialized_generate_series(1, 5) x",
+        );
+        assert_eq!(result, Ok(Some(vec![Some(1), None, Some(3), None, Some(5)])));
+
+        // also in a target list, where Postgres evaluates SRFs differently
+        let result = Spi::get_one::<i64>("SELECT count(materialized_generate_series(1, 100))");
+        assert_eq!(result, Ok(Some(50)));
+    }
+
+    #[pg_test]
+    pub fn test_materialized_table() {
+        let result = Spi::get_two::<i32, String>(
+            "SELECT idx, value FROM materialized_composite_set('a b c') ORDER BY idx DESC LIMIT 1",
+        );
+        assert_eq!(result, Ok((Some(3), Some("c".to_string()))));
+
+        let result = Spi::get_one::<i64>("SELECT count(*) FROM materialized_none()");
+        assert_eq!(result, Ok(Some(0)));
+
+        let result = Spi::get_one::<i32>("SELECT * FROM materialized_one_col()");
+        assert_eq!(result, Ok(Some(42)));
+    }
 }
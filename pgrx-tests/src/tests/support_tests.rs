@@ -0,0 +1,98 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::prelude::*;
+use pgrx::Internal;
+
+#[pg_extern]
+fn support_numbers_support(request: SupportRequest) -> Internal {
+    match request {
+        SupportRequest::Rows(rows) => match rows.const_arg::<i32>(0) {
+            Some(n) => rows.set_rows(n as f64),
+            None => Internal::default(),
+        },
+        SupportRequest::Cost(cost) => cost.set_cost(0.0, 0.01),
+        _ => Internal::default(),
+    }
+}
+
+#[pg_extern(support = support_numbers_support)]
+fn support_numbers(n: i32) -> SetOfIterator<'static, i32> {
+    SetOfIterator::new(1..=n)
+}
+
+#[pg_extern]
+fn support_folded_support(request: SupportRequest) -> Internal {
+    match request {
+        SupportRequest::Simplify(simplify) => match simplify.const_arg::<i32>(0) {
+            Some(value) => simplify.simplify_to_const(Some(value + 1)).unwrap_or_default(),
+            None => Internal::default(),
+        },
+        _ => Internal::default(),
+    }
+}
+
+#[pg_extern(support = support_folded_support)]
+fn support_folded(_value: i32) -> i32 {
+    error!("support_folded() should have been simplified away")
+}
+
+#[pg_extern]
+fn support_mistyped_support(request: SupportRequest) -> Internal {
+    match request {
+        // not the function's `int4` result type, so the call is left alone
+        SupportRequest::Simplify(simplify) => {
+            // nor can its `int4` argument be read as text
+            assert_eq!(simplify.const_arg::<String>(0), None);
+            simplify.simplify_to_const(Some(String::from("oops"))).unwrap_or_default()
+        }
+        _ => Internal::default(),
+    }
+}
+
+#[pg_extern(support = support_mistyped_support)]
+fn support_mistyped(value: i32) -> i32 {
+    value
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_support_rows() -> Result<(), spi::Error> {
+        let plan = Spi::explain("SELECT * FROM support_numbers(42)")?;
+        assert_eq!(plan.0[0]["Plan"]["Plan Rows"], 42);
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM support_numbers(42)")?;
+        assert_eq!(count, Some(42));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_support_simplify() -> Result<(), spi::Error> {
+        assert_eq!(Spi::get_one::<i32>("SELECT support_folded(41)")?, Some(42));
+        Ok(())
+    }
+
+    #[pg_test(error = "support_folded() should have been simplified away")]
+    fn test_support_simplify_non_const() -> Result<(), spi::Error> {
+        Spi::run("SELECT support_folded(g) FROM generate_series(1, 3) g")
+    }
+
+    #[pg_test]
+    fn test_support_simplify_wrong_type() -> Result<(), spi::Error> {
+        assert_eq!(Spi::get_one::<i32>("SELECT support_mistyped(41)")?, Some(41));
+        Ok(())
+    }
+}
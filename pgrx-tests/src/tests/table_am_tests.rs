@@ -0,0 +1,102 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::prelude::*;
+use pgrx::PgRelation;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// A toy table of `int4` columns which keeps its rows in backend-local memory
+struct MemTable {
+    rows: Vec<Vec<Option<i32>>>,
+    pos: usize,
+}
+
+static MEM_TABLES: Mutex<Option<HashMap<u32, Vec<Vec<Option<i32>>>>>> = Mutex::new(None);
+
+fn with_rows<R>(rel: &PgRelation, f: impl FnOnce(&mut Vec<Vec<Option<i32>>>) -> R) -> R {
+    let mut tables = MEM_TABLES.lock().unwrap();
+    f(tables.get_or_insert_with(HashMap::new).entry(rel.oid().as_u32()).or_default())
+}
+
+#[pg_table_am]
+impl TableAccessMethod for MemTable {
+    fn begin_scan(scan: &TableScan) -> Self {
+        MemTable { rows: with_rows(&scan.relation(), |rows| rows.clone()), pos: 0 }
+    }
+
+    fn rescan(&mut self, _scan: &TableScan) {
+        self.pos = 0;
+    }
+
+    fn get_next_slot(&mut self, _direction: pg_sys::ScanDirection, slot: &mut TupleSlot) -> bool {
+        let Some(row) = self.rows.get(self.pos) else {
+            return false;
+        };
+        self.pos += 1;
+        unsafe {
+            slot.store_datums(row.iter().map(|value| value.into_datum())).unwrap();
+        }
+        true
+    }
+
+    fn insert(rel: &PgRelation, slot: &mut TupleSlot) {
+        let natts = slot.tuple_desc().len();
+        let row = (1..=natts)
+            .map(|attno| slot.get_by_index::<i32>(NonZeroUsize::new(attno).unwrap()).unwrap())
+            .collect();
+        with_rows(rel, |rows| rows.push(row));
+    }
+
+    fn create_storage(rel: &PgRelation, _persistence: std::os::raw::c_char) {
+        with_rows(rel, |rows| rows.clear());
+    }
+
+    fn truncate(rel: &PgRelation) {
+        with_rows(rel, |rows| rows.clear());
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_table_am_scan() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE mem_table_test (id int4, value int4) USING mem_table")?;
+        Spi::run("INSERT INTO mem_table_test SELECT x, x * 10 FROM generate_series(1, 10) x")?;
+        Spi::run("INSERT INTO mem_table_test VALUES (11, NULL)")?;
+
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM mem_table_test")?, Some(11));
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT sum(value) FROM mem_table_test WHERE id > 5")?,
+            Some(400)
+        );
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM mem_table_test WHERE value IS NULL")?,
+            Some(1)
+        );
+
+        Spi::run("TRUNCATE mem_table_test")?;
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM mem_table_test")?, Some(0));
+        Ok(())
+    }
+
+    #[pg_test(error = "this table access method does not support DELETE")]
+    fn test_table_am_unsupported() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE mem_table_test (id int4) USING mem_table")?;
+        Spi::run("INSERT INTO mem_table_test VALUES (1)")?;
+        Spi::run("DELETE FROM mem_table_test")
+    }
+}
@@ -0,0 +1,117 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+use pgrx::prelude::*;
+use pgrx::TryFromDatumError;
+
+#[pg_extern(window)]
+fn window_row_number(window: WindowContext) -> i64 {
+    window.current_position() + 1
+}
+
+#[pg_extern(window)]
+fn window_delta(mut window: WindowContext, value: i32) -> Option<i32> {
+    let previous = window.arg_in_partition::<i32>(0, -1, WindowSeek::Current, false).unwrap()??;
+    Some(value - previous)
+}
+
+#[pg_extern(window)]
+fn window_until_last(mut window: WindowContext, value: &str) -> Option<String> {
+    let last = window.arg_in_partition::<String>(0, 0, WindowSeek::Tail, false).unwrap()??;
+    Some(format!("{value}..{last}"))
+}
+
+#[pg_extern(window)]
+fn window_arg_errors(window: WindowContext, _value: i32) -> String {
+    let wrong_type = window.arg_current::<String>(0).unwrap_err();
+    let no_such_arg = window.arg_current::<i32>(1).unwrap_err();
+    format!("{}/{}", matches!(wrong_type, TryFromDatumError::IncompatibleTypes { .. }), no_such_arg)
+}
+
+#[pg_extern(window)]
+fn window_running_sum(value: i32, mut window: WindowContext) -> i64 {
+    let sum = window.partition_state::<i64>();
+    *sum += value as i64;
+    *sum
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_window_position() -> Result<(), spi::Error> {
+        let numbers = Spi::get_one::<String>(
+            "SELECT string_agg(n::text, ',' ORDER BY g) FROM (
+                SELECT g, window_row_number() OVER (PARTITION BY g % 2 ORDER BY g) AS n
+                  FROM generate_series(1, 5) g
+            ) x",
+        )?;
+        assert_eq!(numbers.as_deref(), Some("1,1,2,2,3"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_window_partition_access() -> Result<(), spi::Error> {
+        let deltas = Spi::get_one::<String>(
+            "SELECT string_agg(coalesce(d::text, '-'), ',' ORDER BY g) FROM (
+                SELECT g, window_delta(g * g) OVER (ORDER BY g) AS d FROM generate_series(1, 4) g
+            ) x",
+        )?;
+        assert_eq!(deltas.as_deref(), Some("-,3,5,7"));
+
+        let ranges = Spi::get_one::<String>(
+            "SELECT string_agg(r, ',' ORDER BY v) FROM (
+                SELECT v, window_until_last(v) OVER (ORDER BY v) AS r
+                  FROM (VALUES ('a'), ('b'), ('c')) t(v)
+            ) x",
+        )?;
+        assert_eq!(ranges.as_deref(), Some("a..c,b..c,c..c"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_window_arg_errors() -> Result<(), spi::Error> {
+        let errors = Spi::get_one::<String>("SELECT window_arg_errors(1) OVER ()")?;
+        assert_eq!(
+            errors.as_deref(),
+            Some("true/The specified attribute number `2` is not present")
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_window_partition_state() -> Result<(), spi::Error> {
+        let sums = Spi::get_one::<String>(
+            "SELECT string_agg(s::text, ',' ORDER BY g) FROM (
+                SELECT g, window_running_sum(g) OVER (PARTITION BY g > 3 ORDER BY g) AS s
+                  FROM generate_series(1, 6) g
+            ) x",
+        )?;
+        assert_eq!(sums.as_deref(), Some("1,3,6,4,9,15"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_window_null_argument() -> Result<(), spi::Error> {
+        // like a STRICT function, a NULL argument gives a NULL result without calling the function
+        let sums = Spi::get_one::<String>(
+            "SELECT string_agg(coalesce(s::text, 'null'), ',' ORDER BY g) FROM (
+                SELECT g, window_running_sum(v) OVER (ORDER BY g) AS s
+                  FROM (VALUES (1, 1), (2, NULL), (3, 2)) t(g, v)
+            ) x",
+        )?;
+        assert_eq!(sums.as_deref(), Some("1,null,3"));
+        Ok(())
+    }
+}
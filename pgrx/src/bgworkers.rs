@@ -10,6 +10,7 @@
 //! Safely create Postgres Background Workers, including with full SPI support
 //!
 //! See: [https://www.postgresql.org/docs/current/bgworker.html](https://www.postgresql.org/docs/current/bgworker.html)
+use crate::notify::{self, Notification};
 use crate::pg_sys;
 use pgrx_pg_sys::PgTryBuilder;
 use std::convert::TryInto;
@@ -193,6 +194,46 @@ impl BackgroundWorker {
         }
     }
 
+    /// Listen for notifications sent on `channel`, which [`BackgroundWorker::notifications`]
+    /// returns.  This takes effect when the current transaction commits, or right away if we
+    /// aren't in one.
+    pub fn listen(channel: &str) {
+        unsafe {
+            assert!(!pg_sys::MyBgworkerEntry.is_null(), "BackgroundWorker associated functions can only be called from a registered background worker");
+            if pg_sys::IsTransactionState() {
+                notify::listen(channel);
+            } else {
+                BackgroundWorker::transaction(|| notify::listen(channel));
+            }
+        }
+    }
+
+    /// Stop listening for notifications sent on `channel`
+    pub fn unlisten(channel: &str) {
+        unsafe {
+            assert!(!pg_sys::MyBgworkerEntry.is_null(), "BackgroundWorker associated functions can only be called from a registered background worker");
+            if pg_sys::IsTransactionState() {
+                notify::unlisten(channel);
+            } else {
+                BackgroundWorker::transaction(|| notify::unlisten(channel));
+            }
+        }
+    }
+
+    /// The notifications received since the last call, on the channels we [`listen`] on.
+    ///
+    /// Postgres sets our latch when a notification arrives, so this is best called each time
+    /// [`BackgroundWorker::wait_latch`] returns.  Notifications can't be received in a
+    /// transaction, and are left for a later call.
+    ///
+    /// [`listen`]: BackgroundWorker::listen
+    pub fn notifications() -> impl Iterator<Item = Notification> {
+        unsafe {
+            assert!(!pg_sys::MyBgworkerEntry.is_null(), "BackgroundWorker associated functions can only be called from a registered background worker");
+        }
+        notify::receive().into_iter()
+    }
+
     /// Once connected to SPI via `connect_worker_to_spi()`, begin a transaction to
     /// use the `pgrx::Spi` interface. Returns the return value of the `F` function.
     pub fn transaction<F: FnOnce() -> R + std::panic::UnwindSafe + std::panic::RefUnwindSafe, R>(
@@ -262,6 +303,11 @@ impl From<pg_sys::BgwHandleStatus> for BackgroundWorkerStatus {
 }
 
 impl DynamicBackgroundWorker {
+    /// The underlying [`pg_sys::BackgroundWorkerHandle`]
+    pub fn as_ptr(&self) -> *mut pg_sys::BackgroundWorkerHandle {
+        self.handle
+    }
+
     /// Return dynamic background worker's PID if the worker is successfully registered,
     /// otherwise it return worker's status as an error.
     pub fn pid(&self) -> Result<Pid, BackgroundWorkerStatus> {
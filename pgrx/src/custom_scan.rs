@@ -0,0 +1,316 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Safe support for writing Postgres Custom Scan Providers
+//!
+//! A custom scan is injected into a query plan in two steps:
+//!
+//! 1. During planning, a [`PgHooks::set_rel_pathlist`](crate::hooks::PgHooks::set_rel_pathlist)
+//!    (or [`PgHooks::set_join_pathlist`](crate::hooks::PgHooks::set_join_pathlist)) hook creates a
+//!    path with [`create_custom_path`], adjusts its costs, and offers it to the planner with
+//!    `pg_sys::add_path()`.
+//! 2. If the planner picks that path, Postgres calls back into the [`CustomScan`] implementation
+//!    to build the plan node and then to execute it, one row at a time.
+//!
+//! Rows returned by [`CustomScan::exec`] are filtered by the scan's quals and projected by
+//! Postgres, just like any other scan.
+//!
+//! Providers which want their plans to be usable by parallel workers, or to survive being
+//! serialized, must also call [`register_custom_scan`] from `_PG_init()`.
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use crate::{pg_sys, IntoHeapTuple, PgList, PgMemoryContexts, PgRelation};
+use std::any::TypeId;
+use std::ffi::CString;
+use std::sync::Mutex;
+
+/// A Postgres Custom Scan Provider.
+///
+/// A new instance of the implementing type is created every time the custom scan node is executed
+/// (see [`CustomScan::begin_scan`]).
+pub trait CustomScan: Sized {
+    /// The name of the custom scan provider, as shown by `EXPLAIN`
+    const NAME: &'static str;
+
+    /// The `CUSTOMPATH_*` flags describing what the scan supports
+    const FLAGS: u32 = 0;
+
+    /// The type of the rows returned by the scan
+    type Row: IntoHeapTuple;
+
+    /// Adjust the `CustomScan` plan node built for the custom path the planner chose.
+    ///
+    /// pgrx fills in the node so it scans the path's base relation with the path's
+    /// `custom_private` list.  Custom paths over joins, which have no base relation, must at least
+    /// set `custom_scan_tlist` to describe the rows they return.
+    fn plan_custom_path(
+        _root: *mut pg_sys::PlannerInfo,
+        _rel: *mut pg_sys::RelOptInfo,
+        _best_path: *mut pg_sys::CustomPath,
+        _cscan: &mut pg_sys::CustomScan,
+    ) {
+    }
+
+    /// Start executing the scan.
+    ///
+    /// This is also called for `EXPLAIN` without `ANALYZE`, in which case
+    /// [`CustomScanInfo::is_explain_only`] is true and the scan won't be executed.
+    fn begin_scan(info: &CustomScanInfo) -> Self;
+
+    /// Return the next row of the scan, or `None` when the scan is finished
+    fn exec(&mut self) -> Option<Self::Row>;
+
+    /// Restart the scan from the beginning
+    fn rescan(&mut self);
+
+    /// Called when the scan is finished.  Any resources not cleaned up here are released when
+    /// `Self` is dropped.
+    fn end_scan(&mut self) {}
+
+    /// Additional `(label, value)` properties to show for the scan in `EXPLAIN` output
+    fn explain(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+}
+
+/// Information about the custom scan node being executed, provided to [`CustomScan::begin_scan`]
+pub struct CustomScanInfo {
+    node: *mut pg_sys::CustomScanState,
+    eflags: i32,
+}
+
+impl CustomScanInfo {
+    /// The `CustomScanState` being executed
+    pub fn as_ptr(&self) -> *mut pg_sys::CustomScanState {
+        self.node
+    }
+
+    /// The relation being scanned, or `None` if the scan replaces a join
+    pub fn relation(&self) -> Option<PgRelation> {
+        unsafe {
+            let relation = (*self.node).ss.ss_currentRelation;
+            (!relation.is_null()).then(|| PgRelation::from_pg(relation))
+        }
+    }
+
+    /// The `custom_private` list of the `CustomScan` plan node, as set during planning
+    pub fn custom_private(&self) -> PgList<pg_sys::Node> {
+        unsafe {
+            let cscan = (*self.node).ss.ps.plan.cast::<pg_sys::CustomScan>();
+            PgList::from_pg((*cscan).custom_private)
+        }
+    }
+
+    /// The executor flags the scan was started with
+    pub fn eflags(&self) -> i32 {
+        self.eflags
+    }
+
+    /// Is the scan only being started so it can be described by `EXPLAIN`?
+    pub fn is_explain_only(&self) -> bool {
+        self.eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as i32 != 0
+    }
+}
+
+/// The method tables Postgres uses to call back into the [`CustomScan`] `T`.  They're referenced
+/// by plan nodes which can outlive any memory context, so are leaked.
+struct Methods {
+    path: pg_sys::CustomPathMethods,
+    scan: pg_sys::CustomScanMethods,
+    exec: pg_sys::CustomExecMethods,
+}
+
+fn methods<T: CustomScan + 'static>() -> &'static Methods {
+    // (TypeId, address of the leaked methods), as raw pointers aren't `Send`
+    static METHODS: Mutex<Vec<(TypeId, usize)>> = Mutex::new(Vec::new());
+
+    let mut all_methods = METHODS.lock().unwrap();
+    if let Some((_, methods)) = all_methods.iter().find(|(id, _)| *id == TypeId::of::<T>()) {
+        return unsafe { &*(*methods as *const Methods) };
+    }
+
+    let name = CString::new(T::NAME).expect("custom scan name contains a NUL byte").into_raw();
+    let methods: &'static Methods = Box::leak(Box::new(Methods {
+        path: pg_sys::CustomPathMethods {
+            CustomName: name,
+            PlanCustomPath: Some(plan_custom_path::<T>),
+            ..Default::default()
+        },
+        scan: pg_sys::CustomScanMethods {
+            CustomName: name,
+            CreateCustomScanState: Some(create_custom_scan_state::<T>),
+        },
+        exec: pg_sys::CustomExecMethods {
+            CustomName: name,
+            BeginCustomScan: Some(begin_custom_scan::<T>),
+            ExecCustomScan: Some(exec_custom_scan::<T>),
+            EndCustomScan: Some(end_custom_scan::<T>),
+            ReScanCustomScan: Some(rescan_custom_scan::<T>),
+            ExplainCustomScan: Some(explain_custom_scan::<T>),
+            ..Default::default()
+        },
+    }));
+    all_methods.push((TypeId::of::<T>(), methods as *const _ as usize));
+    methods
+}
+
+/// Register the [`CustomScan`] `T` with Postgres, so its plans can be found by name.
+///
+/// This should be called from `_PG_init()`, and is required for plans using the custom scan to
+/// be copied to parallel workers.
+pub fn register_custom_scan<T: CustomScan + 'static>() {
+    unsafe {
+        pg_sys::RegisterCustomScanMethods(&methods::<T>().scan);
+    }
+}
+
+/// Create a path which scans `rel` with the [`CustomScan`] `T`.
+///
+/// The path's row estimate is copied from `rel` and its costs are zero.  Adjust them as
+/// necessary, and then offer the path to the planner with `pg_sys::add_path()`.
+///
+/// ## Safety
+///
+/// `rel` must be the valid `RelOptInfo` pointer Postgres provided to a pathlist hook
+pub unsafe fn create_custom_path<T: CustomScan + 'static>(
+    rel: *mut pg_sys::RelOptInfo,
+) -> PgBox<pg_sys::CustomPath, AllocatedByRust> {
+    let mut path = PgBox::<pg_sys::CustomPath>::alloc_node(pg_sys::NodeTag_T_CustomPath);
+    path.path.pathtype = pg_sys::NodeTag_T_CustomScan;
+    path.path.parent = rel;
+    path.path.pathtarget = (*rel).reltarget;
+    path.path.rows = (*rel).rows;
+    path.flags = T::FLAGS;
+    path.methods = &methods::<T>().path;
+    path
+}
+
+/// Our executor state.  Postgres only ever sees the `css` field, which must come first.
+#[repr(C)]
+struct ScanState<T> {
+    css: pg_sys::CustomScanState,
+    state: *mut T,
+}
+
+#[inline]
+unsafe fn scan_state<'a, T>(node: *mut pg_sys::CustomScanState) -> Option<&'a mut T> {
+    (*node.cast::<ScanState<T>>()).state.as_mut()
+}
+
+#[inline]
+unsafe fn store_heap_tuple(tuple: pg_sys::HeapTuple, slot: *mut pg_sys::TupleTableSlot) {
+    #[cfg(feature = "pg11")]
+    pg_sys::ExecStoreTuple(tuple, slot, pg_sys::InvalidBuffer as pg_sys::Buffer, true);
+
+    // the scan slot is usually virtual, so the tuple can't be stored as-is
+    #[cfg(not(feature = "pg11"))]
+    pg_sys::ExecForceStoreHeapTuple(tuple, slot, true);
+}
+
+#[pg_guard]
+unsafe extern "C" fn plan_custom_path<T: CustomScan + 'static>(
+    root: *mut pg_sys::PlannerInfo,
+    rel: *mut pg_sys::RelOptInfo,
+    best_path: *mut pg_sys::CustomPath,
+    tlist: *mut pg_sys::List,
+    clauses: *mut pg_sys::List,
+    custom_plans: *mut pg_sys::List,
+) -> *mut pg_sys::Plan {
+    let mut cscan = PgBox::<pg_sys::CustomScan>::alloc_node(pg_sys::NodeTag_T_CustomScan);
+    cscan.scan.plan.targetlist = tlist;
+    cscan.scan.plan.qual = pg_sys::extract_actual_clauses(clauses, false);
+    cscan.scan.scanrelid = (*rel).relid;
+    cscan.flags = (*best_path).flags;
+    cscan.custom_plans = custom_plans;
+    cscan.custom_private = (*best_path).custom_private;
+    cscan.methods = &methods::<T>().scan;
+
+    T::plan_custom_path(root, rel, best_path, &mut cscan);
+    cscan.into_pg().cast()
+}
+
+#[pg_guard]
+unsafe extern "C" fn create_custom_scan_state<T: CustomScan + 'static>(
+    _cscan: *mut pg_sys::CustomScan,
+) -> *mut pg_sys::Node {
+    let node = pg_sys::palloc0(std::mem::size_of::<ScanState<T>>()).cast::<ScanState<T>>();
+    (*node).css.ss.ps.type_ = pg_sys::NodeTag_T_CustomScanState;
+    (*node).css.methods = &methods::<T>().exec;
+    node.cast()
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_custom_scan<T: CustomScan>(
+    node: *mut pg_sys::CustomScanState,
+    estate: *mut pg_sys::EState,
+    eflags: i32,
+) {
+    let state = T::begin_scan(&CustomScanInfo { node, eflags });
+    (*node.cast::<ScanState<T>>()).state =
+        PgMemoryContexts::For((*estate).es_query_cxt).leak_and_drop_on_delete(state);
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_custom_scan<T: CustomScan>(
+    node: *mut pg_sys::CustomScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    // let Postgres apply the scan's quals and projection to the rows we return
+    pg_sys::ExecScan(&mut (*node).ss, Some(next_row::<T>), Some(recheck_row))
+}
+
+#[pg_guard]
+unsafe extern "C" fn next_row<T: CustomScan>(
+    node: *mut pg_sys::ScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    let slot = (*node).ss_ScanTupleSlot;
+    let state = scan_state::<T>(node.cast()).expect("custom scan was not started");
+
+    pg_sys::ExecClearTuple(slot);
+    if let Some(row) = state.exec() {
+        let tuple = row.into_heap_tuple((*slot).tts_tupleDescriptor);
+        store_heap_tuple(tuple, slot);
+    }
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn recheck_row(
+    _node: *mut pg_sys::ScanState,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    true
+}
+
+#[pg_guard]
+unsafe extern "C" fn end_custom_scan<T: CustomScan>(node: *mut pg_sys::CustomScanState) {
+    // the scan state itself is dropped along with the memory context it was allocated in
+    if let Some(state) = scan_state::<T>(node) {
+        state.end_scan();
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn rescan_custom_scan<T: CustomScan>(node: *mut pg_sys::CustomScanState) {
+    scan_state::<T>(node).expect("custom scan was not started").rescan();
+}
+
+#[pg_guard]
+unsafe extern "C" fn explain_custom_scan<T: CustomScan>(
+    node: *mut pg_sys::CustomScanState,
+    _ancestors: *mut pg_sys::List,
+    es: *mut pg_sys::ExplainState,
+) {
+    if let Some(state) = scan_state::<T>(node) {
+        for (label, value) in state.explain() {
+            let label = CString::new(label).expect("explain label contains a NUL byte");
+            let value = CString::new(value).expect("explain value contains a NUL byte");
+            pg_sys::ExplainPropertyText(label.as_ptr(), value.as_ptr(), es);
+        }
+    }
+}
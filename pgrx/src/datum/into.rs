@@ -74,6 +74,32 @@ pub trait IntoDatum {
     }
 }
 
+/// An object-safe counterpart to [`IntoDatum`], so values of different types can be passed
+/// together, such as arguments to [`SpiClient::select`](crate::spi::SpiClient::select) in a
+/// `&[&dyn IntoDatumDyn]`.
+///
+/// It's implemented for every `IntoDatum` type that is also `Clone`.
+pub trait IntoDatumDyn {
+    /// The Postgres type of this value
+    fn dyn_type_oid(&self) -> pg_sys::Oid;
+
+    /// Convert a copy of this value into a `pg_sys::Datum`
+    fn to_datum(&self) -> Option<pg_sys::Datum>;
+}
+
+impl<T> IntoDatumDyn for T
+where
+    T: IntoDatum + Clone,
+{
+    fn dyn_type_oid(&self) -> pg_sys::Oid {
+        self.composite_type_oid().unwrap_or_else(T::type_oid)
+    }
+
+    fn to_datum(&self) -> Option<pg_sys::Datum> {
+        self.clone().into_datum()
+    }
+}
+
 /// for supporting NULL as the None value of an Option<T>
 impl<T> IntoDatum for Option<T>
 where
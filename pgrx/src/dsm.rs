@@ -0,0 +1,385 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Dynamic shared memory segments and the message queues that can be built in them
+//!
+//! Unlike the shared memory set up by [`pg_shmem_init!`](crate::pg_shmem_init), a
+//! [`DsmSegment`] can be created at any time by any backend, and attached to by other
+//! processes using its [`DsmSegment::handle`].  This is typically how a backend talks to the
+//! dynamic background workers it launches:
+//!
+//! ```rust,no_run
+//! use pgrx::bgworkers::*;
+//! use pgrx::dsm::{DsmSegment, ShmMq};
+//! use pgrx::prelude::*;
+//!
+//! #[pg_extern]
+//! fn squares(n: i64) -> Vec<i64> {
+//!     let segment = DsmSegment::create(64 * 1024);
+//!     let queue = ShmMq::<i64>::create(&segment);
+//!     let worker = BackgroundWorkerBuilder::new("squares")
+//!         .set_library("my_extension")
+//!         .set_function("squares_worker")
+//!         .set_argument(Some(segment.handle().into()))
+//!         .set_extra(&n.to_string())
+//!         .enable_shmem_access(None)
+//!         .set_notify_pid(unsafe { pg_sys::MyProcPid })
+//!         .load_dynamic();
+//!
+//!     // collect everything the worker sends until it detaches from the queue
+//!     queue.receiver_for(&worker).expect("the queue already has a receiver").collect()
+//! }
+//!
+//! #[pg_guard]
+//! #[no_mangle]
+//! pub extern "C" fn squares_worker(arg: pg_sys::Datum) {
+//!     let n: i64 = BackgroundWorker::get_extra().parse().unwrap();
+//!     let segment = DsmSegment::attach(arg.value() as pg_sys::dsm_handle)
+//!         .expect("the segment no longer exists");
+//!     let queue = ShmMq::<i64>::open(&segment).expect("the segment holds no message queue");
+//!     let mut sender = queue.sender().expect("the queue already has a sender");
+//!     for i in 1..=n {
+//!         sender.send(&(i * i)).expect("the receiver went away");
+//!     }
+//! }
+//! ```
+use crate::bgworkers::DynamicBackgroundWorker;
+use crate::pg_sys;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A dynamic shared memory segment, mapped into this process.
+///
+/// The mapping belongs to the current resource owner, so for a regular backend it goes away at
+/// the end of the transaction unless [`DsmSegment::pin_mapping`] is used.  Dropping a
+/// [`DsmSegment`] detaches it, and the segment itself is destroyed once every process has
+/// detached, unless it's been [pinned](DsmSegment::pin).
+pub struct DsmSegment {
+    seg: *mut pg_sys::dsm_segment,
+    handle: pg_sys::dsm_handle,
+}
+
+impl DsmSegment {
+    /// Create a new segment of `size` bytes
+    pub fn create(size: usize) -> DsmSegment {
+        unsafe {
+            // SAFETY:  without DSM_CREATE_NULL_IF_MAXSEGMENTS, Postgres raises an ERROR rather
+            // than returning NULL
+            let seg = pg_sys::dsm_create(size, 0);
+            DsmSegment { seg, handle: pg_sys::dsm_segment_handle(seg) }
+        }
+    }
+
+    /// Attach to the existing segment identified by `handle`.
+    ///
+    /// Returns `None` if the segment no longer exists.
+    pub fn attach(handle: pg_sys::dsm_handle) -> Option<DsmSegment> {
+        unsafe {
+            let seg = pg_sys::dsm_attach(handle);
+            (!seg.is_null()).then(|| DsmSegment { seg, handle })
+        }
+    }
+
+    /// The handle other processes can [`DsmSegment::attach`] to this segment with
+    pub fn handle(&self) -> pg_sys::dsm_handle {
+        self.handle
+    }
+
+    /// The underlying [`pg_sys::dsm_segment`]
+    pub fn as_ptr(&self) -> *mut pg_sys::dsm_segment {
+        self.seg
+    }
+
+    /// The address of the segment's memory in this process
+    pub fn address(&self) -> *mut std::os::raw::c_void {
+        unsafe { pg_sys::dsm_segment_address(self.seg) }
+    }
+
+    /// The size of the segment, in bytes
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::dsm_segment_map_length(self.seg) }
+    }
+
+    /// Is the segment zero bytes long?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Keep the segment around even when no process has it attached, until the server shuts
+    /// down or it's [unpinned](DsmSegment::unpin)
+    pub fn pin(&self) {
+        unsafe { pg_sys::dsm_pin_segment(self.seg) }
+    }
+
+    /// Undo [`DsmSegment::pin`] for the segment identified by `handle`, which needn't be
+    /// attached to this process
+    pub fn unpin(handle: pg_sys::dsm_handle) {
+        unsafe { pg_sys::dsm_unpin_segment(handle) }
+    }
+
+    /// Keep this process' mapping of the segment until the session ends, rather than until
+    /// the current resource owner is released
+    pub fn pin_mapping(&self) {
+        unsafe { pg_sys::dsm_pin_mapping(self.seg) }
+    }
+
+    /// Detach from the segment, which is the same as dropping it
+    pub fn detach(self) {}
+}
+
+impl Drop for DsmSegment {
+    fn drop(&mut self) {
+        unsafe {
+            // the resource owner may have already detached the segment for us, in which case
+            // `self.seg` is no longer valid
+            if pg_sys::dsm_find_mapping(self.handle) == self.seg {
+                pg_sys::dsm_detach(self.seg);
+            }
+        }
+    }
+}
+
+/// Errors from sending to or receiving from a [`ShmMq`]
+#[derive(thiserror::Error, Debug)]
+pub enum ShmMqError {
+    /// The other end of the queue has detached, or was a background worker that failed to start
+    #[error("the other end of the message queue has detached")]
+    Detached,
+    /// The message couldn't be serialized or deserialized
+    #[error("message queue serialization error: {0}")]
+    Serialization(#[from] serde_cbor::Error),
+    /// The segment doesn't hold a queue [created](ShmMq::create) by pgrx
+    #[error("the dynamic shared memory segment doesn't hold a message queue")]
+    NotAQueue,
+    /// Some process already took this end of the queue
+    #[error("this end of the message queue is already attached")]
+    AlreadyAttached,
+}
+
+/// Written at the start of the segment by [`ShmMq::create`], ahead of the queue itself
+#[repr(C)]
+struct ShmMqHeader {
+    magic: u64,
+    sender: AtomicBool,
+    receiver: AtomicBool,
+}
+
+const SHM_MQ_MAGIC: u64 = 0x5047_5258_4d51_0001;
+
+/// The queue starts after the header, where Postgres' alignment rules allow
+const SHM_MQ_OFFSET: usize = unsafe { pg_sys::MAXALIGN(std::mem::size_of::<ShmMqHeader>()) };
+
+fn fits_queue(segment: &DsmSegment) -> bool {
+    segment.len() >= SHM_MQ_OFFSET + unsafe { pg_sys::shm_mq_minimum_size }
+}
+
+/// A single-producer, single-consumer queue of `T` messages, living in a [`DsmSegment`].
+///
+/// One process [creates](ShmMq::create) the queue and the other [opens](ShmMq::open) it, after
+/// which each takes one end as a [`ShmMqSender`] or [`ShmMqReceiver`].  Messages are
+/// serialized with CBOR, so their size isn't limited by the size of the queue.
+pub struct ShmMq<'seg, T> {
+    header: *mut ShmMqHeader,
+    mq: *mut pg_sys::shm_mq,
+    segment: &'seg DsmSegment,
+    __marker: PhantomData<T>,
+}
+
+impl<'seg, T: Serialize + DeserializeOwned> ShmMq<'seg, T> {
+    /// Create a new queue which occupies all of `segment`
+    ///
+    /// # Panics
+    ///
+    /// If `segment` is too small to hold a queue
+    pub fn create(segment: &'seg DsmSegment) -> ShmMq<'seg, T> {
+        if !fits_queue(segment) {
+            panic!("dynamic shared memory segment is too small for a message queue")
+        }
+        unsafe {
+            // SAFETY:  the segment is large enough for the header and the queue, and its address
+            // is suitably aligned for both
+            let header = segment.address().cast::<ShmMqHeader>();
+            let mq = pg_sys::shm_mq_create(
+                header.cast::<u8>().add(SHM_MQ_OFFSET).cast(),
+                segment.len() - SHM_MQ_OFFSET,
+            );
+            header.write(ShmMqHeader {
+                magic: SHM_MQ_MAGIC,
+                sender: AtomicBool::new(false),
+                receiver: AtomicBool::new(false),
+            });
+            ShmMq { header, mq, segment, __marker: PhantomData }
+        }
+    }
+
+    /// Open the queue another process [created](ShmMq::create) in `segment`
+    ///
+    /// Returns [`ShmMqError::NotAQueue`] if `segment` wasn't set up by [`ShmMq::create`].
+    pub fn open(segment: &'seg DsmSegment) -> Result<ShmMq<'seg, T>, ShmMqError> {
+        let header = segment.address().cast::<ShmMqHeader>();
+        // SAFETY:  the segment is large enough to read the header's magic number from
+        if !fits_queue(segment) || unsafe { (*header).magic } != SHM_MQ_MAGIC {
+            return Err(ShmMqError::NotAQueue);
+        }
+        let mq = unsafe { header.cast::<u8>().add(SHM_MQ_OFFSET).cast() };
+        Ok(ShmMq { header, mq, segment, __marker: PhantomData })
+    }
+
+    /// Become the queue's sender
+    ///
+    /// Returns [`ShmMqError::AlreadyAttached`] if this or another process already did.
+    pub fn sender(self) -> Result<ShmMqSender<'seg, T>, ShmMqError> {
+        self.claim(|header| &header.sender)?;
+        unsafe {
+            pg_sys::shm_mq_set_sender(self.mq, pg_sys::MyProc);
+            Ok(ShmMqSender { mqh: self.attach(std::ptr::null_mut()), __marker: PhantomData })
+        }
+    }
+
+    /// Become the queue's receiver
+    ///
+    /// Returns [`ShmMqError::AlreadyAttached`] if this or another process already did.
+    pub fn receiver(self) -> Result<ShmMqReceiver<'seg, T>, ShmMqError> {
+        self.claim(|header| &header.receiver)?;
+        unsafe {
+            pg_sys::shm_mq_set_receiver(self.mq, pg_sys::MyProc);
+            Ok(ShmMqReceiver { mqh: self.attach(std::ptr::null_mut()), __marker: PhantomData })
+        }
+    }
+
+    /// Become the queue's receiver, with `worker` as the sender.
+    ///
+    /// Should the worker exit without ever attaching to the queue, receiving fails with
+    /// [`ShmMqError::Detached`] rather than waiting forever.  Returns
+    /// [`ShmMqError::AlreadyAttached`] if this or another process is already the receiver.
+    pub fn receiver_for(
+        self,
+        worker: &DynamicBackgroundWorker,
+    ) -> Result<ShmMqReceiver<'seg, T>, ShmMqError> {
+        self.claim(|header| &header.receiver)?;
+        unsafe {
+            pg_sys::shm_mq_set_receiver(self.mq, pg_sys::MyProc);
+            Ok(ShmMqReceiver { mqh: self.attach(worker.as_ptr()), __marker: PhantomData })
+        }
+    }
+
+    /// Take one end of the queue, which Postgres would otherwise let us set twice
+    fn claim(&self, end: impl FnOnce(&ShmMqHeader) -> &AtomicBool) -> Result<(), ShmMqError> {
+        // SAFETY:  `create` or `open` made sure the header is there
+        let claimed = end(unsafe { &*self.header });
+        claimed
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| ())
+            .map_err(|_| ShmMqError::AlreadyAttached)
+    }
+
+    unsafe fn attach(
+        &self,
+        worker: *mut pg_sys::BackgroundWorkerHandle,
+    ) -> *mut pg_sys::shm_mq_handle {
+        pg_sys::shm_mq_attach(self.mq, self.segment.as_ptr(), worker)
+    }
+}
+
+/// The sending end of a [`ShmMq`]
+pub struct ShmMqSender<'seg, T> {
+    mqh: *mut pg_sys::shm_mq_handle,
+    __marker: PhantomData<(&'seg DsmSegment, T)>,
+}
+
+impl<'seg, T: Serialize> ShmMqSender<'seg, T> {
+    /// Send `value`, waiting for room in the queue if necessary
+    pub fn send(&mut self, value: &T) -> Result<(), ShmMqError> {
+        let bytes = serde_cbor::to_vec(value)?;
+        unsafe {
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            let result = pg_sys::shm_mq_send(self.mqh, bytes.len(), bytes.as_ptr().cast(), false);
+            #[cfg(any(feature = "pg15", feature = "pg16"))]
+            let result =
+                pg_sys::shm_mq_send(self.mqh, bytes.len(), bytes.as_ptr().cast(), false, true);
+
+            match result {
+                pg_sys::shm_mq_result_SHM_MQ_SUCCESS => Ok(()),
+                _ => Err(ShmMqError::Detached),
+            }
+        }
+    }
+
+    /// Wait until the receiver has attached to the queue
+    pub fn wait_for_receiver(&mut self) -> Result<(), ShmMqError> {
+        match unsafe { pg_sys::shm_mq_wait_for_attach(self.mqh) } {
+            pg_sys::shm_mq_result_SHM_MQ_SUCCESS => Ok(()),
+            _ => Err(ShmMqError::Detached),
+        }
+    }
+}
+
+impl<'seg, T> Drop for ShmMqSender<'seg, T> {
+    fn drop(&mut self) {
+        // detaching is how the receiver learns there's nothing more to come
+        unsafe { pg_sys::shm_mq_detach(self.mqh) }
+    }
+}
+
+/// The receiving end of a [`ShmMq`].
+///
+/// As an [`Iterator`], it yields messages until the sender detaches.
+pub struct ShmMqReceiver<'seg, T> {
+    mqh: *mut pg_sys::shm_mq_handle,
+    __marker: PhantomData<(&'seg DsmSegment, T)>,
+}
+
+impl<'seg, T: DeserializeOwned> ShmMqReceiver<'seg, T> {
+    /// Receive the next message, waiting for one to arrive if necessary
+    pub fn recv(&mut self) -> Result<T, ShmMqError> {
+        self.receive(false).map(|value| value.expect("blocking receive returned nothing"))
+    }
+
+    /// Receive the next message if one is available
+    pub fn try_recv(&mut self) -> Result<Option<T>, ShmMqError> {
+        self.receive(true)
+    }
+
+    fn receive(&mut self, nowait: bool) -> Result<Option<T>, ShmMqError> {
+        let mut nbytes = 0;
+        let mut data = std::ptr::null_mut();
+        unsafe {
+            match pg_sys::shm_mq_receive(self.mqh, &mut nbytes, &mut data, nowait) {
+                pg_sys::shm_mq_result_SHM_MQ_SUCCESS => {
+                    // SAFETY:  the message is valid until the next receive, and we copy it out
+                    // while deserializing
+                    let bytes = std::slice::from_raw_parts(data.cast::<u8>(), nbytes);
+                    Ok(Some(serde_cbor::from_slice(bytes)?))
+                }
+                pg_sys::shm_mq_result_SHM_MQ_WOULD_BLOCK => Ok(None),
+                _ => Err(ShmMqError::Detached),
+            }
+        }
+    }
+}
+
+impl<'seg, T: DeserializeOwned> Iterator for ShmMqReceiver<'seg, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.recv() {
+            Ok(value) => Some(value),
+            Err(ShmMqError::Detached) => None,
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+impl<'seg, T> Drop for ShmMqReceiver<'seg, T> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::shm_mq_detach(self.mqh) }
+    }
+}
@@ -0,0 +1,589 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Safe support for writing Postgres Foreign Data Wrappers
+//!
+//! Implement [`ForeignDataWrapper`] for a type and decorate the `impl` block with `#[pg_fdw]`.
+//! pgrx will generate the `fdw_handler` and validator functions along with the
+//! `CREATE FOREIGN DATA WRAPPER` statement for you.
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//!
+//! struct NumbersFdw {
+//!     current: i64,
+//!     max: i64,
+//! }
+//!
+//! #[pg_fdw]
+//! impl ForeignDataWrapper for NumbersFdw {
+//!     type Row = (i64, String);
+//!
+//!     fn begin_scan(table: &PgForeignTable) -> Self {
+//!         let max = table.options().get("max").and_then(|v| v.parse().ok()).unwrap_or(10);
+//!         NumbersFdw { current: 0, max }
+//!     }
+//!
+//!     fn iterate_scan(&mut self) -> Option<Self::Row> {
+//!         if self.current >= self.max {
+//!             return None;
+//!         }
+//!         self.current += 1;
+//!         Some((self.current, format!("row {}", self.current)))
+//!     }
+//!
+//!     fn re_scan(&mut self) {
+//!         self.current = 0;
+//!     }
+//! }
+//! ```
+//!
+//! Which can then be used like:
+//!
+//! ```sql
+//! CREATE SERVER numbers FOREIGN DATA WRAPPER numbers_fdw;
+//! CREATE FOREIGN TABLE numbers (id bigint, label text) SERVER numbers OPTIONS (max '100');
+//! SELECT * FROM numbers;
+//! ```
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use crate::{
+    pg_sys, AllocatedByPostgres, AllocatedByRust, IntoHeapTuple, PgList, PgMemoryContexts,
+    PgRelation, PgTupleDesc, TryFromDatumError,
+};
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+/// The name of the junk column used to carry [`ForeignDataWrapper::ROWID_COLUMN`] through to
+/// `UPDATE` and `DELETE`
+const ROWID_JUNK_NAME: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"pgrx_fdw_rowid\0") };
+
+// these come from the various `catalog/pg_foreign_*.h` headers, which we don't generate bindings for
+const FOREIGN_DATA_WRAPPER_RELATION_ID: pg_sys::Oid =
+    unsafe { pg_sys::Oid::from_u32_unchecked(2328) };
+const FOREIGN_SERVER_RELATION_ID: pg_sys::Oid = unsafe { pg_sys::Oid::from_u32_unchecked(1417) };
+const USER_MAPPING_RELATION_ID: pg_sys::Oid = unsafe { pg_sys::Oid::from_u32_unchecked(1418) };
+const FOREIGN_TABLE_RELATION_ID: pg_sys::Oid = unsafe { pg_sys::Oid::from_u32_unchecked(3118) };
+
+/// The catalog object whose `OPTIONS (...)` are being validated by [`ForeignDataWrapper::validate`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FdwOptionCatalog {
+    /// `CREATE/ALTER FOREIGN DATA WRAPPER ... OPTIONS (...)`
+    Wrapper,
+    /// `CREATE/ALTER SERVER ... OPTIONS (...)`
+    Server,
+    /// `CREATE/ALTER USER MAPPING ... OPTIONS (...)`
+    UserMapping,
+    /// `CREATE/ALTER FOREIGN TABLE ... OPTIONS (...)`
+    Table,
+    /// Per-column options on a foreign table
+    Column,
+    /// Some catalog pgrx doesn't know about
+    Other(pg_sys::Oid),
+}
+
+impl From<pg_sys::Oid> for FdwOptionCatalog {
+    fn from(oid: pg_sys::Oid) -> Self {
+        match oid {
+            FOREIGN_DATA_WRAPPER_RELATION_ID => FdwOptionCatalog::Wrapper,
+            FOREIGN_SERVER_RELATION_ID => FdwOptionCatalog::Server,
+            USER_MAPPING_RELATION_ID => FdwOptionCatalog::UserMapping,
+            FOREIGN_TABLE_RELATION_ID => FdwOptionCatalog::Table,
+            pg_sys::AttributeRelationId => FdwOptionCatalog::Column,
+            other => FdwOptionCatalog::Other(other),
+        }
+    }
+}
+
+/// A foreign table being scanned or modified, along with its merged `OPTIONS (...)`.
+///
+/// Options are collected from the foreign data wrapper, then the server, then the table itself,
+/// with later definitions replacing earlier ones of the same name.
+pub struct PgForeignTable {
+    relation: PgRelation,
+    options: HashMap<String, String>,
+}
+
+impl PgForeignTable {
+    /// Wrap the foreign table represented by the specified Postgres-provided `pg_sys::Relation`
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe as we cannot guarantee the provided relation pointer is valid or
+    /// that it's actually a foreign table
+    pub unsafe fn from_pg(relation: pg_sys::Relation) -> Self {
+        let relation = PgRelation::from_pg(relation);
+        let options = collect_options(relation.oid());
+        PgForeignTable { relation, options }
+    }
+
+    /// Open the foreign table with the specified oid
+    ///
+    /// ## Safety
+    ///
+    /// The caller must already hold a lock on the relation
+    pub unsafe fn open(relid: pg_sys::Oid) -> Self {
+        let relation = PgRelation::open(relid);
+        let options = collect_options(relid);
+        PgForeignTable { relation, options }
+    }
+
+    /// The underlying relation of this foreign table
+    pub fn relation(&self) -> &PgRelation {
+        &self.relation
+    }
+
+    /// The foreign table's tuple descriptor
+    pub fn tuple_desc(&self) -> PgTupleDesc<'_> {
+        self.relation.tuple_desc()
+    }
+
+    /// The merged wrapper, server, and table options
+    pub fn options(&self) -> &HashMap<String, String> {
+        &self.options
+    }
+}
+
+/// Identifies the row being updated or deleted, as read from [`ForeignDataWrapper::ROWID_COLUMN`]
+pub struct FdwRowId {
+    datum: pg_sys::Datum,
+    is_null: bool,
+    typoid: pg_sys::Oid,
+}
+
+impl FdwRowId {
+    /// Convert the row identifier into a Rust value
+    ///
+    /// Returns [`TryFromDatumError::IncompatibleTypes`] if `T` can't represent the row
+    /// identifier's [type](FdwRowId::type_oid).
+    pub fn value<T: FromDatum + IntoDatum>(&self) -> Result<Option<T>, TryFromDatumError> {
+        unsafe {
+            // SAFETY:  the datum was read from the plan slot using the attribute's own type, which
+            // try_from_datum() checks against `T`
+            T::try_from_datum(self.datum, self.is_null, self.typoid)
+        }
+    }
+
+    /// The Postgres type of the row identifier
+    pub fn type_oid(&self) -> pg_sys::Oid {
+        self.typoid
+    }
+}
+
+/// A Postgres Foreign Data Wrapper.
+///
+/// A new instance is created for every scan of a foreign table (see [`ForeignDataWrapper::begin_scan`])
+/// and for every `INSERT`/`UPDATE`/`DELETE` against one (see [`ForeignDataWrapper::begin_modify`]).
+/// The instance lives in the executor's memory context and is dropped when the query finishes,
+/// even if it finishes because of an error.
+///
+/// Use `#[pg_fdw]` on the `impl` block to generate the handler, validator and SQL.
+pub trait ForeignDataWrapper: Sized {
+    /// The rows produced by [`ForeignDataWrapper::iterate_scan`].  This is typically a tuple
+    /// of [`IntoDatum`] values, in the same order as the foreign table's columns, or a
+    /// [`PgHeapTuple`] built from [`PgForeignTable::tuple_desc`].
+    type Row: IntoHeapTuple;
+
+    /// The name of the foreign table column that uniquely identifies a row.  `UPDATE` and `DELETE`
+    /// are only supported when this is set, and the value is provided to [`ForeignDataWrapper::update`]
+    /// and [`ForeignDataWrapper::delete`].
+    const ROWID_COLUMN: Option<&'static str> = None;
+
+    /// Validate the `OPTIONS (...)` given to the specified `catalog` object.  Raise an `ERROR`
+    /// (or panic) to reject them.
+    ///
+    /// The default implementation accepts everything.
+    fn validate(_options: HashMap<String, String>, _catalog: FdwOptionCatalog) {}
+
+    /// Estimate how many rows a scan of `table` will return.
+    fn estimate_rows(_table: &PgForeignTable) -> f64 {
+        1000.0
+    }
+
+    /// Start a new scan of `table`
+    fn begin_scan(table: &PgForeignTable) -> Self;
+
+    /// Return the next row of the scan, or `None` when the scan is finished
+    fn iterate_scan(&mut self) -> Option<Self::Row>;
+
+    /// Restart the scan from the beginning
+    fn re_scan(&mut self);
+
+    /// Called when the scan is finished.  Any resources not cleaned up here are released when
+    /// `Self` is dropped.
+    fn end_scan(&mut self) {}
+
+    /// Start an `INSERT`, `UPDATE`, or `DELETE` against `table`
+    fn begin_modify(_table: &PgForeignTable) -> Self {
+        error!("this foreign data wrapper does not support INSERT, UPDATE, or DELETE")
+    }
+
+    /// Insert a new row
+    fn insert(&mut self, _new_row: PgHeapTuple<'_, AllocatedByPostgres>) {
+        error!("this foreign data wrapper does not support INSERT")
+    }
+
+    /// Replace the row identified by `rowid` with `new_row`
+    fn update(&mut self, _rowid: FdwRowId, _new_row: PgHeapTuple<'_, AllocatedByPostgres>) {
+        error!("this foreign data wrapper does not support UPDATE")
+    }
+
+    /// Delete the row identified by `rowid`
+    fn delete(&mut self, _rowid: FdwRowId) {
+        error!("this foreign data wrapper does not support DELETE")
+    }
+
+    /// Called when the modification is finished
+    fn end_modify(&mut self) {}
+}
+
+/// Build the `FdwRoutine` for the [`ForeignDataWrapper`] `T`.
+///
+/// This is what the `fdw_handler` function generated by `#[pg_fdw]` returns.
+pub fn fdw_routine<T: ForeignDataWrapper>() -> PgBox<pg_sys::FdwRoutine, AllocatedByRust> {
+    unsafe {
+        let mut routine = PgBox::<pg_sys::FdwRoutine>::alloc_node(pg_sys::NodeTag_T_FdwRoutine);
+
+        routine.GetForeignRelSize = Some(get_foreign_rel_size::<T>);
+        routine.GetForeignPaths = Some(get_foreign_paths);
+        routine.GetForeignPlan = Some(get_foreign_plan);
+        routine.BeginForeignScan = Some(begin_foreign_scan::<T>);
+        routine.IterateForeignScan = Some(iterate_foreign_scan::<T>);
+        routine.ReScanForeignScan = Some(re_scan_foreign_scan::<T>);
+        routine.EndForeignScan = Some(end_foreign_scan::<T>);
+
+        routine.AddForeignUpdateTargets = Some(add_foreign_update_targets::<T>);
+        routine.BeginForeignModify = Some(begin_foreign_modify::<T>);
+        routine.ExecForeignInsert = Some(exec_foreign_insert::<T>);
+        routine.ExecForeignUpdate = Some(exec_foreign_update::<T>);
+        routine.ExecForeignDelete = Some(exec_foreign_delete::<T>);
+        routine.EndForeignModify = Some(end_foreign_modify::<T>);
+
+        routine
+    }
+}
+
+/// Validate the options of a `CREATE/ALTER` command against the [`ForeignDataWrapper`] `T`.
+///
+/// This is what the validator function generated by `#[pg_fdw]` calls.  Postgres provides the
+/// options as an array of `"name=value"` strings.
+pub fn validate_options<T: ForeignDataWrapper>(options: Vec<String>, catalog: pg_sys::Oid) {
+    let options = options
+        .into_iter()
+        .map(|option| match option.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => (option, String::new()),
+        })
+        .collect();
+    T::validate(options, FdwOptionCatalog::from(catalog))
+}
+
+struct ModifyState<T> {
+    fdw: T,
+    rowid_attno: pg_sys::AttrNumber,
+}
+
+unsafe fn collect_options(relid: pg_sys::Oid) -> HashMap<String, String> {
+    let table = pg_sys::GetForeignTable(relid);
+    let server = pg_sys::GetForeignServer((*table).serverid);
+    let wrapper = pg_sys::GetForeignDataWrapper((*server).fdwid);
+
+    let mut options = HashMap::new();
+    for list in [(*wrapper).options, (*server).options, (*table).options] {
+        for def in PgList::<pg_sys::DefElem>::from_pg(list).iter_ptr() {
+            let name = CStr::from_ptr((*def).defname).to_string_lossy().into_owned();
+            let value = CStr::from_ptr(pg_sys::defGetString(def)).to_string_lossy().into_owned();
+            options.insert(name, value);
+        }
+    }
+    options
+}
+
+#[inline]
+unsafe fn store_heap_tuple(tuple: pg_sys::HeapTuple, slot: *mut pg_sys::TupleTableSlot) {
+    #[cfg(feature = "pg11")]
+    pg_sys::ExecStoreTuple(tuple, slot, pg_sys::InvalidBuffer as pg_sys::Buffer, true);
+
+    #[cfg(not(feature = "pg11"))]
+    pg_sys::ExecStoreHeapTuple(tuple, slot, true);
+}
+
+#[inline]
+unsafe fn slot_heap_tuple<'a>(
+    slot: *mut pg_sys::TupleTableSlot,
+) -> PgHeapTuple<'a, AllocatedByPostgres> {
+    #[cfg(feature = "pg11")]
+    let tuple = pg_sys::ExecFetchSlotTuple(slot);
+
+    #[cfg(not(feature = "pg11"))]
+    let tuple = pg_sys::ExecFetchSlotHeapTuple(slot, true, std::ptr::null_mut());
+
+    PgHeapTuple::from_heap_tuple(PgTupleDesc::from_pg_unchecked((*slot).tts_tupleDescriptor), tuple)
+}
+
+#[inline]
+unsafe fn slot_rowid<T>(
+    state: &ModifyState<T>,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> FdwRowId {
+    if state.rowid_attno == pg_sys::InvalidAttrNumber as pg_sys::AttrNumber {
+        error!("foreign table has no `ROWID_COLUMN`, so UPDATE and DELETE are not supported")
+    }
+    let attno = state.rowid_attno as i32;
+    let mut is_null = false;
+    let datum = pg_sys::slot_getattr(plan_slot, attno, &mut is_null);
+    let tupdesc = PgTupleDesc::from_pg_unchecked((*plan_slot).tts_tupleDescriptor);
+    let typoid = tupdesc.get(attno as usize - 1).expect("rowid attribute is missing").atttypid;
+    FdwRowId { datum, is_null, typoid }
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_rel_size<T: ForeignDataWrapper>(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    foreigntableid: pg_sys::Oid,
+) {
+    let table = PgForeignTable::open(foreigntableid);
+    (*baserel).rows = T::estimate_rows(&table);
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_paths(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+) {
+    let rows = (*baserel).rows;
+    let startup_cost = 10.0;
+    let total_cost = startup_cost + rows;
+    let path = pg_sys::create_foreignscan_path(
+        root,
+        baserel,
+        std::ptr::null_mut(),
+        rows,
+        startup_cost,
+        total_cost,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+    );
+    pg_sys::add_path(baserel, path.cast());
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_plan(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+    _best_path: *mut pg_sys::ForeignPath,
+    tlist: *mut pg_sys::List,
+    scan_clauses: *mut pg_sys::List,
+    outer_plan: *mut pg_sys::Plan,
+) -> *mut pg_sys::ForeignScan {
+    // we don't push anything down, so every qual is checked by the executor
+    let scan_clauses = pg_sys::extract_actual_clauses(scan_clauses, false);
+    pg_sys::make_foreignscan(
+        tlist,
+        scan_clauses,
+        (*baserel).relid,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        outer_plan,
+    )
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+    eflags: i32,
+) {
+    if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as i32 != 0 {
+        return;
+    }
+
+    let table = PgForeignTable::from_pg((*node).ss.ss_currentRelation);
+    let fdw = T::begin_scan(&table);
+    let estate = (*node).ss.ps.state;
+    (*node).fdw_state =
+        PgMemoryContexts::For((*estate).es_query_cxt).leak_and_drop_on_delete(fdw).cast();
+}
+
+#[pg_guard]
+unsafe extern "C" fn iterate_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    let slot = (*node).ss.ss_ScanTupleSlot;
+    let fdw = (*node).fdw_state.cast::<T>().as_mut().expect("foreign scan was not started");
+
+    pg_sys::ExecClearTuple(slot);
+    if let Some(row) = fdw.iterate_scan() {
+        let tuple = row.into_heap_tuple((*slot).tts_tupleDescriptor);
+        store_heap_tuple(tuple, slot);
+    }
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn re_scan_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) {
+    if let Some(fdw) = (*node).fdw_state.cast::<T>().as_mut() {
+        fdw.re_scan();
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn end_foreign_scan<T: ForeignDataWrapper>(node: *mut pg_sys::ForeignScanState) {
+    // `fdw_state` is NULL for EXPLAIN (without ANALYZE).  Otherwise we just notify the fdw here,
+    // it's dropped along with the executor's memory context
+    if let Some(fdw) = (*node).fdw_state.cast::<T>().as_mut() {
+        fdw.end_scan();
+    }
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn add_foreign_update_targets<T: ForeignDataWrapper>(
+    parsetree: *mut pg_sys::Query,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    if let Some(var) = rowid_var::<T>((*parsetree).resultRelation as _, target_relation) {
+        let resno = PgList::<pg_sys::TargetEntry>::from_pg((*parsetree).targetList).len() + 1;
+        let tle = pg_sys::makeTargetEntry(
+            var.cast(),
+            resno as pg_sys::AttrNumber,
+            pg_sys::pstrdup(ROWID_JUNK_NAME.as_ptr()),
+            true,
+        );
+        (*parsetree).targetList = pg_sys::lappend((*parsetree).targetList, tle.cast());
+    }
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16"))]
+#[pg_guard]
+unsafe extern "C" fn add_foreign_update_targets<T: ForeignDataWrapper>(
+    root: *mut pg_sys::PlannerInfo,
+    rtindex: pg_sys::Index,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    if let Some(var) = rowid_var::<T>(rtindex, target_relation) {
+        pg_sys::add_row_identity_var(root, var, rtindex, ROWID_JUNK_NAME.as_ptr());
+    }
+}
+
+unsafe fn rowid_var<T: ForeignDataWrapper>(
+    rtindex: pg_sys::Index,
+    target_relation: pg_sys::Relation,
+) -> Option<*mut pg_sys::Var> {
+    let column = T::ROWID_COLUMN?;
+    let tupdesc = PgTupleDesc::from_pg_unchecked((*target_relation).rd_att);
+    let attr = tupdesc
+        .iter()
+        .find(|attr| !attr.is_dropped() && attr.name() == column)
+        .unwrap_or_else(|| error!("`ROWID_COLUMN` \"{}\" does not exist", column));
+    Some(pg_sys::makeVar(
+        rtindex as _,
+        attr.attnum,
+        attr.atttypid,
+        attr.atttypmod,
+        attr.attcollation,
+        0,
+    ))
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_foreign_modify<T: ForeignDataWrapper>(
+    mtstate: *mut pg_sys::ModifyTableState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    _fdw_private: *mut pg_sys::List,
+    _subplan_index: i32,
+    eflags: i32,
+) {
+    if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as i32 != 0 {
+        return;
+    }
+
+    let mut rowid_attno = pg_sys::InvalidAttrNumber as pg_sys::AttrNumber;
+    if T::ROWID_COLUMN.is_some()
+        && matches!((*mtstate).operation, pg_sys::CmdType_CMD_UPDATE | pg_sys::CmdType_CMD_DELETE)
+    {
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+        let subplan = (*(*(*mtstate).mt_plans.add(_subplan_index as usize))).plan;
+
+        #[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16"))]
+        let subplan = (*(*mtstate).ps.lefttree).plan;
+
+        rowid_attno =
+            pg_sys::ExecFindJunkAttributeInTlist((*subplan).targetlist, ROWID_JUNK_NAME.as_ptr());
+    }
+
+    let table = PgForeignTable::from_pg((*rinfo).ri_RelationDesc);
+    let state = ModifyState { fdw: T::begin_modify(&table), rowid_attno };
+    let estate = (*mtstate).ps.state;
+    (*rinfo).ri_FdwState =
+        PgMemoryContexts::For((*estate).es_query_cxt).leak_and_drop_on_delete(state).cast();
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_foreign_insert<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    _plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = modify_state::<T>(rinfo);
+    state.fdw.insert(slot_heap_tuple(slot));
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_foreign_update<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = modify_state::<T>(rinfo);
+    let rowid = slot_rowid(state, plan_slot);
+    state.fdw.update(rowid, slot_heap_tuple(slot));
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_foreign_delete<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = modify_state::<T>(rinfo);
+    let rowid = slot_rowid(state, plan_slot);
+    state.fdw.delete(rowid);
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn end_foreign_modify<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+) {
+    if let Some(state) = (*rinfo).ri_FdwState.cast::<ModifyState<T>>().as_mut() {
+        state.fdw.end_modify();
+    }
+}
+
+#[inline]
+unsafe fn modify_state<'a, T>(rinfo: *mut pg_sys::ResultRelInfo) -> &'a mut ModifyState<T> {
+    (*rinfo).ri_FdwState.cast::<ModifyState<T>>().as_mut().expect("foreign modify was not started")
+}
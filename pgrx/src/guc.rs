@@ -8,10 +8,17 @@
 //LICENSE
 //LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 //! Provides a safe interface into Postgres' Configuration System (GUC)
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::pg_guard;
 use crate::{pg_sys, PgMemoryContexts};
 use core::ffi::CStr;
-pub use pgrx_macros::PostgresGucEnum;
-use std::cell::Cell;
+pub use pgrx_macros::{PostgresGuc, PostgresGucEnum};
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_void};
+use std::time::Duration;
 
 /// Defines at what level this GUC can be set
 pub enum GucContext {
@@ -116,12 +123,14 @@ where
 pub struct GucSetting<T> {
     value: Cell<usize>,
     boot_val: T,
+    /// The `GUC_UNIT_*` that duration and memory-size settings were registered with
+    unit: Cell<u32>,
 }
 
 unsafe impl Sync for GucSetting<bool> {}
 impl GucSetting<bool> {
     pub const fn new(value: bool) -> Self {
-        GucSetting { value: Cell::new(value as usize), boot_val: value }
+        GucSetting { value: Cell::new(value as usize), boot_val: value, unit: Cell::new(0) }
     }
 
     pub fn get(&self) -> bool {
@@ -136,7 +145,7 @@ impl GucSetting<bool> {
 unsafe impl Sync for GucSetting<i32> {}
 impl GucSetting<i32> {
     pub const fn new(value: i32) -> Self {
-        GucSetting { value: Cell::new(value as usize), boot_val: value }
+        GucSetting { value: Cell::new(value as usize), boot_val: value, unit: Cell::new(0) }
     }
 
     pub fn get(&self) -> i32 {
@@ -155,6 +164,7 @@ impl GucSetting<f64> {
             GucSetting {
                 value: Cell::new(std::mem::transmute::<f64, u64>(value) as usize),
                 boot_val: value,
+                unit: Cell::new(0),
             }
         }
     }
@@ -171,7 +181,7 @@ impl GucSetting<f64> {
 unsafe impl Sync for GucSetting<Option<&'static CStr>> {}
 impl GucSetting<Option<&'static CStr>> {
     pub const fn new(value: Option<&'static CStr>) -> Self {
-        GucSetting { value: Cell::new(0), boot_val: value }
+        GucSetting { value: Cell::new(0), boot_val: value, unit: Cell::new(0) }
     }
 
     pub fn get(&self) -> Option<&CStr> {
@@ -189,13 +199,151 @@ impl GucSetting<Option<&'static CStr>> {
     }
 }
 
+/// The unit a duration GUC is stored in.  Postgres also assumes it for values given without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GucTimeUnit {
+    Milliseconds,
+    Seconds,
+    Minutes,
+}
+
+impl GucTimeUnit {
+    fn from_raw(unit: u32) -> Self {
+        match unit {
+            pg_sys::GUC_UNIT_MS => GucTimeUnit::Milliseconds,
+            pg_sys::GUC_UNIT_S => GucTimeUnit::Seconds,
+            pg_sys::GUC_UNIT_MIN => GucTimeUnit::Minutes,
+            _ => panic!("{unit} is not a GUC time unit"),
+        }
+    }
+
+    fn as_raw(self) -> u32 {
+        match self {
+            GucTimeUnit::Milliseconds => pg_sys::GUC_UNIT_MS,
+            GucTimeUnit::Seconds => pg_sys::GUC_UNIT_S,
+            GucTimeUnit::Minutes => pg_sys::GUC_UNIT_MIN,
+        }
+    }
+
+    fn duration(self, count: u64) -> Duration {
+        match self {
+            GucTimeUnit::Milliseconds => Duration::from_millis(count),
+            GucTimeUnit::Seconds => Duration::from_secs(count),
+            GucTimeUnit::Minutes => Duration::from_secs(count * 60),
+        }
+    }
+
+    /// Counts the whole units in `duration`, as Postgres stores it
+    fn count(self, duration: Duration) -> i32 {
+        let count = match self {
+            GucTimeUnit::Milliseconds => duration.as_millis(),
+            GucTimeUnit::Seconds => duration.as_secs() as u128,
+            GucTimeUnit::Minutes => (duration.as_secs() / 60) as u128,
+        };
+        i32::try_from(count).unwrap_or_else(|_| panic!("{duration:?} is too long for a GUC"))
+    }
+}
+
+/// The unit a memory-size GUC is stored in.  Postgres also assumes it for values given without
+/// one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GucMemoryUnit {
+    Bytes,
+    Kilobytes,
+    /// Blocks of `BLCKSZ` bytes
+    Blocks,
+    /// Blocks of `XLOG_BLCKSZ` bytes
+    XlogBlocks,
+    Megabytes,
+}
+
+impl GucMemoryUnit {
+    fn from_raw(unit: u32) -> Self {
+        match unit {
+            pg_sys::GUC_UNIT_BYTE => GucMemoryUnit::Bytes,
+            pg_sys::GUC_UNIT_KB => GucMemoryUnit::Kilobytes,
+            pg_sys::GUC_UNIT_BLOCKS => GucMemoryUnit::Blocks,
+            pg_sys::GUC_UNIT_XBLOCKS => GucMemoryUnit::XlogBlocks,
+            pg_sys::GUC_UNIT_MB => GucMemoryUnit::Megabytes,
+            _ => panic!("{unit} is not a GUC memory unit"),
+        }
+    }
+
+    fn as_raw(self) -> u32 {
+        match self {
+            GucMemoryUnit::Bytes => pg_sys::GUC_UNIT_BYTE,
+            GucMemoryUnit::Kilobytes => pg_sys::GUC_UNIT_KB,
+            GucMemoryUnit::Blocks => pg_sys::GUC_UNIT_BLOCKS,
+            GucMemoryUnit::XlogBlocks => pg_sys::GUC_UNIT_XBLOCKS,
+            GucMemoryUnit::Megabytes => pg_sys::GUC_UNIT_MB,
+        }
+    }
+
+    /// The number of bytes in one of this unit
+    fn bytes(self) -> usize {
+        match self {
+            GucMemoryUnit::Bytes => 1,
+            GucMemoryUnit::Kilobytes => 1024,
+            GucMemoryUnit::Blocks => pg_sys::BLCKSZ as usize,
+            GucMemoryUnit::XlogBlocks => pg_sys::XLOG_BLCKSZ as usize,
+            GucMemoryUnit::Megabytes => 1024 * 1024,
+        }
+    }
+
+    /// Counts the whole units in `bytes`, as Postgres stores it
+    fn count(self, bytes: usize) -> i32 {
+        i32::try_from(bytes / self.bytes())
+            .unwrap_or_else(|_| panic!("{bytes} bytes is too large for a GUC"))
+    }
+}
+
+unsafe impl Sync for GucSetting<Duration> {}
+/// A duration, registered with [`GucRegistry::define_duration_guc`]
+impl GucSetting<Duration> {
+    pub const fn new(value: Duration) -> Self {
+        GucSetting { value: Cell::new(0), boot_val: value, unit: Cell::new(0) }
+    }
+
+    pub fn get(&self) -> Duration {
+        match self.unit.get() {
+            // not registered yet
+            0 => self.boot_val,
+            unit => GucTimeUnit::from_raw(unit).duration(self.value.get() as i32 as u64),
+        }
+    }
+
+    fn as_ptr(&self) -> *mut i32 {
+        self.value.as_ptr() as *mut _
+    }
+}
+
+unsafe impl Sync for GucSetting<usize> {}
+/// A memory size in bytes, registered with [`GucRegistry::define_memory_guc`]
+impl GucSetting<usize> {
+    pub const fn new(bytes: usize) -> Self {
+        GucSetting { value: Cell::new(0), boot_val: bytes, unit: Cell::new(0) }
+    }
+
+    pub fn get(&self) -> usize {
+        match self.unit.get() {
+            // not registered yet
+            0 => self.boot_val,
+            unit => GucMemoryUnit::from_raw(unit).bytes() * self.value.get() as i32 as usize,
+        }
+    }
+
+    fn as_ptr(&self) -> *mut i32 {
+        self.value.as_ptr() as *mut _
+    }
+}
+
 unsafe impl<T> Sync for GucSetting<T> where T: GucEnum<T> + Copy {}
 impl<T> GucSetting<T>
 where
     T: GucEnum<T> + Copy,
 {
     pub const fn new(value: T) -> Self {
-        GucSetting { value: Cell::new(0), boot_val: value }
+        GucSetting { value: Cell::new(0), boot_val: value, unit: Cell::new(0) }
     }
 
     pub fn get(&self) -> T {
@@ -207,6 +355,519 @@ where
     }
 }
 
+/// Why a [`GucHooks::check`] hook rejected a new value for its GUC.
+///
+/// Without a message, Postgres reports its usual `invalid value for parameter` error.  The
+/// detail and hint are reported alongside it, as `GUC_check_errdetail()` and
+/// `GUC_check_errhint()` would in C.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GucCheckError {
+    message: Option<String>,
+    detail: Option<String>,
+    hint: Option<String>,
+}
+
+impl GucCheckError {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace Postgres' primary error message
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Hands this error to Postgres, which reports it once the check hook returns `false`
+    unsafe fn report(self) {
+        // Postgres allocates these with `format_elog_string()`, in the `ErrorContext`
+        let error_context = PgMemoryContexts::For(pg_sys::ErrorContext);
+        if let Some(message) = self.message {
+            pg_sys::GUC_check_errmsg_string = error_context.pstrdup(&message);
+        }
+        if let Some(detail) = self.detail {
+            pg_sys::GUC_check_errdetail_string = error_context.pstrdup(&detail);
+        }
+        if let Some(hint) = self.hint {
+            pg_sys::GUC_check_errhint_string = error_context.pstrdup(&hint);
+        }
+    }
+}
+
+/// The check and assign hook signatures Postgres expects for GUCs of a given type
+pub trait GucHookType {
+    type CheckHook: Default;
+    type AssignHook: Default;
+}
+
+impl GucHookType for bool {
+    type CheckHook = pg_sys::GucBoolCheckHook;
+    type AssignHook = pg_sys::GucBoolAssignHook;
+}
+
+impl GucHookType for i32 {
+    type CheckHook = pg_sys::GucIntCheckHook;
+    type AssignHook = pg_sys::GucIntAssignHook;
+}
+
+impl GucHookType for f64 {
+    type CheckHook = pg_sys::GucRealCheckHook;
+    type AssignHook = pg_sys::GucRealAssignHook;
+}
+
+impl GucHookType for Option<&'static CStr> {
+    type CheckHook = pg_sys::GucStringCheckHook;
+    type AssignHook = pg_sys::GucStringAssignHook;
+}
+
+impl<T> GucHookType for T
+where
+    T: GucEnum<T> + Copy,
+{
+    type CheckHook = pg_sys::GucEnumCheckHook;
+    type AssignHook = pg_sys::GucEnumAssignHook;
+}
+
+/// The optional check, assign and show hooks of a GUC, given to the
+/// `GucRegistry::define_*_guc_with_hooks` functions.
+///
+/// * `check` sees every new value before it is applied, and can reject it with a [`GucCheckError`]
+/// * `assign` is told about every new value once it has been applied
+/// * `show` renders the GUC's current value for `SHOW` and `current_setting()`
+///
+/// Postgres doesn't give these hooks any context, so each one must be a fn item or a closure
+/// that captures nothing, which is checked when the extension is compiled.  They may read other
+/// statics, such as the [`GucSetting`] itself.
+///
+/// ```rust,no_run
+/// use pgrx::guc::*;
+/// use std::ffi::CStr;
+///
+/// static URL: GucSetting<Option<&'static CStr>> = GucSetting::<Option<&'static CStr>>::new(None);
+///
+/// GucRegistry::define_string_guc_with_hooks(
+///     "my_extension.url",
+///     "where to send things",
+///     "where to send things",
+///     &URL,
+///     GucContext::Userset,
+///     GucFlags::default(),
+///     GucHooks::<Option<&'static CStr>>::new().check(|url| match url {
+///         Some(url) if !url.to_bytes().starts_with(b"https://") => Err(GucCheckError::new()
+///             .with_detail("The URL must use https.")
+///             .with_hint("Prefix it with \"https://\".")),
+///         _ => Ok(()),
+///     }),
+/// );
+/// ```
+pub struct GucHooks<T: GucHookType> {
+    check: T::CheckHook,
+    assign: T::AssignHook,
+    show: pg_sys::GucShowHook,
+}
+
+impl<T: GucHookType> Default for GucHooks<T> {
+    fn default() -> Self {
+        GucHooks { check: Default::default(), assign: Default::default(), show: None }
+    }
+}
+
+impl<T: GucHookType> GucHooks<T> {
+    /// No hooks, which the methods below add to
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `show` to display the GUC's current value
+    pub fn show<F>(self, show: F) -> Self
+    where
+        F: Fn() -> String + Copy + 'static,
+    {
+        assert_stateless(&show);
+        GucHooks { show: Some(show_hook::<F>), ..self }
+    }
+}
+
+impl GucHooks<bool> {
+    /// Let `check` accept or reject each new value before it is applied
+    pub fn check<F>(self, check: F) -> Self
+    where
+        F: Fn(bool) -> Result<(), GucCheckError> + Copy + 'static,
+    {
+        assert_stateless(&check);
+        GucHooks { check: Some(check_bool_hook::<F>), ..self }
+    }
+
+    /// Tell `assign` about each new value once it has been applied
+    pub fn assign<F>(self, assign: F) -> Self
+    where
+        F: Fn(bool) + Copy + 'static,
+    {
+        assert_stateless(&assign);
+        GucHooks { assign: Some(assign_bool_hook::<F>), ..self }
+    }
+}
+
+impl GucHooks<i32> {
+    /// Let `check` accept or reject each new value before it is applied
+    pub fn check<F>(self, check: F) -> Self
+    where
+        F: Fn(i32) -> Result<(), GucCheckError> + Copy + 'static,
+    {
+        assert_stateless(&check);
+        GucHooks { check: Some(check_int_hook::<F>), ..self }
+    }
+
+    /// Tell `assign` about each new value once it has been applied
+    pub fn assign<F>(self, assign: F) -> Self
+    where
+        F: Fn(i32) + Copy + 'static,
+    {
+        assert_stateless(&assign);
+        GucHooks { assign: Some(assign_int_hook::<F>), ..self }
+    }
+}
+
+impl GucHooks<f64> {
+    /// Let `check` accept or reject each new value before it is applied
+    pub fn check<F>(self, check: F) -> Self
+    where
+        F: Fn(f64) -> Result<(), GucCheckError> + Copy + 'static,
+    {
+        assert_stateless(&check);
+        GucHooks { check: Some(check_real_hook::<F>), ..self }
+    }
+
+    /// Tell `assign` about each new value once it has been applied
+    pub fn assign<F>(self, assign: F) -> Self
+    where
+        F: Fn(f64) + Copy + 'static,
+    {
+        assert_stateless(&assign);
+        GucHooks { assign: Some(assign_real_hook::<F>), ..self }
+    }
+}
+
+impl GucHooks<Option<&'static CStr>> {
+    /// Let `check` accept or reject each new value before it is applied
+    pub fn check<F>(self, check: F) -> Self
+    where
+        F: Fn(Option<&CStr>) -> Result<(), GucCheckError> + Copy + 'static,
+    {
+        assert_stateless(&check);
+        GucHooks { check: Some(check_string_hook::<F>), ..self }
+    }
+
+    /// Tell `assign` about each new value once it has been applied
+    pub fn assign<F>(self, assign: F) -> Self
+    where
+        F: Fn(Option<&CStr>) + Copy + 'static,
+    {
+        assert_stateless(&assign);
+        GucHooks { assign: Some(assign_string_hook::<F>), ..self }
+    }
+}
+
+impl<T> GucHooks<T>
+where
+    T: GucEnum<T> + Copy,
+{
+    /// Let `check` accept or reject each new value before it is applied
+    pub fn check<F>(self, check: F) -> Self
+    where
+        F: Fn(T) -> Result<(), GucCheckError> + Copy + 'static,
+    {
+        assert_stateless(&check);
+        GucHooks { check: Some(check_enum_hook::<T, F>), ..self }
+    }
+
+    /// Tell `assign` about each new value once it has been applied
+    pub fn assign<F>(self, assign: F) -> Self
+    where
+        F: Fn(T) + Copy + 'static,
+    {
+        assert_stateless(&assign);
+        GucHooks { assign: Some(assign_enum_hook::<T, F>), ..self }
+    }
+}
+
+/// Hooks are called through a function monomorphized over their type, which is how we find them
+/// again without any context from Postgres.  That only works when the type alone is the hook,
+/// which this checks at compile time.
+fn assert_stateless<F>(_hook: &F) {
+    const {
+        assert!(
+            std::mem::size_of::<F>() == 0,
+            "GUC hooks must be fn items or closures that capture nothing"
+        )
+    }
+}
+
+/// Recreates a hook that [`assert_stateless`] accepted when it was registered
+unsafe fn stateless_hook<F: Copy>() -> F {
+    const { assert!(std::mem::size_of::<F>() == 0) }
+    // SAFETY: `F` is a zero-sized `Copy` type that we were given a value of, so there's nothing
+    // to read and nothing to drop
+    std::ptr::NonNull::<F>::dangling().as_ptr().read()
+}
+
+unsafe fn check_result(result: Result<(), GucCheckError>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(error) => {
+            error.report();
+            false
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_bool_hook<F: Fn(bool) -> Result<(), GucCheckError> + Copy>(
+    newval: *mut bool,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    check_result(stateless_hook::<F>()(*newval))
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_int_hook<F: Fn(i32) -> Result<(), GucCheckError> + Copy>(
+    newval: *mut c_int,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    check_result(stateless_hook::<F>()(*newval))
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_real_hook<F: Fn(f64) -> Result<(), GucCheckError> + Copy>(
+    newval: *mut f64,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    check_result(stateless_hook::<F>()(*newval))
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_string_hook<F: Fn(Option<&CStr>) -> Result<(), GucCheckError> + Copy>(
+    newval: *mut *mut c_char,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    let value = (!(*newval).is_null()).then(|| CStr::from_ptr(*newval));
+    check_result(stateless_hook::<F>()(value))
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_enum_hook<
+    T: GucEnum<T> + Copy,
+    F: Fn(T) -> Result<(), GucCheckError> + Copy,
+>(
+    newval: *mut c_int,
+    _extra: *mut *mut c_void,
+    _source: pg_sys::GucSource,
+) -> bool {
+    check_result(stateless_hook::<F>()(T::from_ordinal(*newval)))
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_bool_hook<F: Fn(bool) + Copy>(newval: bool, _extra: *mut c_void) {
+    stateless_hook::<F>()(newval)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_int_hook<F: Fn(i32) + Copy>(newval: c_int, _extra: *mut c_void) {
+    stateless_hook::<F>()(newval)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_real_hook<F: Fn(f64) + Copy>(newval: f64, _extra: *mut c_void) {
+    stateless_hook::<F>()(newval)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_string_hook<F: Fn(Option<&CStr>) + Copy>(
+    newval: *const c_char,
+    _extra: *mut c_void,
+) {
+    let value = (!newval.is_null()).then(|| CStr::from_ptr(newval));
+    stateless_hook::<F>()(value)
+}
+
+#[pg_guard]
+unsafe extern "C" fn assign_enum_hook<T: GucEnum<T> + Copy, F: Fn(T) + Copy>(
+    newval: c_int,
+    _extra: *mut c_void,
+) {
+    stateless_hook::<F>()(T::from_ordinal(newval))
+}
+
+thread_local! {
+    /// The last value each show hook returned, which, like the static buffer a C show hook
+    /// would use, only needs to live until Postgres has copied it
+    static SHOWN: RefCell<HashMap<TypeId, CString>> = RefCell::new(HashMap::new());
+}
+
+#[pg_guard]
+unsafe extern "C" fn show_hook<F: Fn() -> String + Copy + 'static>() -> *const c_char {
+    let shown = CString::new(stateless_hook::<F>()()).expect("GUC show hook returned a NUL byte");
+    SHOWN.with(|buffers| {
+        let mut buffers = buffers.borrow_mut();
+        let buffer = buffers.entry(TypeId::of::<F>()).or_default();
+        *buffer = shown;
+        buffer.as_ptr()
+    })
+}
+
+/// A type that [`Guc::get`] can read a GUC's value as
+pub trait GucValue: Sized {
+    /// Parses `value`, as `SHOW` would display it, for a GUC with the given `GUC_*` flags
+    unsafe fn parse(value: &CStr, flags: i32) -> Option<Self>;
+}
+
+impl GucValue for String {
+    unsafe fn parse(value: &CStr, _flags: i32) -> Option<Self> {
+        value.to_str().ok().map(String::from)
+    }
+}
+
+impl GucValue for bool {
+    unsafe fn parse(value: &CStr, _flags: i32) -> Option<Self> {
+        let mut result = false;
+        pg_sys::parse_bool(value.as_ptr(), &mut result).then_some(result)
+    }
+}
+
+impl GucValue for i32 {
+    /// Values with a unit are converted to the GUC's own unit, as Postgres stores them
+    unsafe fn parse(value: &CStr, flags: i32) -> Option<Self> {
+        let mut result = 0;
+        pg_sys::parse_int(value.as_ptr(), &mut result, flags, std::ptr::null_mut())
+            .then_some(result)
+    }
+}
+
+impl GucValue for f64 {
+    #[cfg(feature = "pg11")]
+    unsafe fn parse(value: &CStr, _flags: i32) -> Option<Self> {
+        let mut result = 0.0;
+        pg_sys::parse_real(value.as_ptr(), &mut result).then_some(result)
+    }
+
+    #[cfg(not(feature = "pg11"))]
+    unsafe fn parse(value: &CStr, flags: i32) -> Option<Self> {
+        let mut result = 0.0;
+        pg_sys::parse_real(value.as_ptr(), &mut result, flags, std::ptr::null_mut())
+            .then_some(result)
+    }
+}
+
+impl GucValue for Duration {
+    /// Only GUCs with a time unit can be read as a `Duration`
+    unsafe fn parse(value: &CStr, flags: i32) -> Option<Self> {
+        let unit = match flags as u32 & pg_sys::GUC_UNIT_TIME {
+            0 => return None,
+            unit => GucTimeUnit::from_raw(unit),
+        };
+        let count = u64::try_from(i32::parse(value, flags)?).ok()?;
+        Some(unit.duration(count))
+    }
+}
+
+impl GucValue for usize {
+    /// Only GUCs with a memory unit can be read as a size in bytes
+    unsafe fn parse(value: &CStr, flags: i32) -> Option<Self> {
+        let unit = match flags as u32 & pg_sys::GUC_UNIT_MEMORY {
+            0 => return None,
+            unit => GucMemoryUnit::from_raw(unit),
+        };
+        let count = usize::try_from(i32::parse(value, flags)?).ok()?;
+        Some(unit.bytes() * count)
+    }
+}
+
+/// Reads and temporarily changes any of the server's GUCs, including those of other extensions.
+///
+/// ```rust,no_run
+/// use pgrx::guc::Guc;
+///
+/// let work_mem = Guc::get::<usize>("work_mem");
+/// Guc::with_setting("enable_seqscan", "off", || {
+///     // plan a query without sequential scans
+/// });
+/// ```
+pub struct Guc;
+
+impl Guc {
+    /// The current value of the GUC named `name`, or `None` if there is no such GUC, it has no
+    /// value, or its value can't be read as a `T`.
+    ///
+    /// Integer GUCs with a unit are read in that unit, or as a [`Duration`] or a `usize` number of
+    /// bytes.
+    pub fn get<T: GucValue>(name: &str) -> Option<T> {
+        let name = CString::new(name).expect("GUC names cannot contain NUL bytes");
+        unsafe {
+            let value = pg_sys::GetConfigOption(name.as_ptr(), true, false);
+            if value.is_null() {
+                return None;
+            }
+            let flags = pg_sys::GetConfigOptionFlags(name.as_ptr(), true);
+            T::parse(CStr::from_ptr(value), flags)
+        }
+    }
+
+    /// Runs `f` with the GUC named `name` set to `value`, as if by `SET LOCAL`, and then puts back
+    /// its previous value, even if `f` raises an error.
+    ///
+    /// Postgres raises an error if `value` is invalid or the current user can't set the GUC.
+    pub fn with_setting<R, F: FnOnce() -> R>(name: &str, value: &str, f: F) -> R {
+        let name = CString::new(name).expect("GUC names cannot contain NUL bytes");
+        let value = CString::new(value).expect("GUC values cannot contain NUL bytes");
+        unsafe {
+            let _nest_level = GucNestLevel(pg_sys::NewGUCNestLevel());
+            let context = if pg_sys::superuser() {
+                pg_sys::GucContext_PGC_SUSET
+            } else {
+                pg_sys::GucContext_PGC_USERSET
+            };
+            pg_sys::set_config_option(
+                name.as_ptr(),
+                value.as_ptr(),
+                context,
+                pg_sys::GucSource_PGC_S_SESSION,
+                pg_sys::GucAction_GUC_ACTION_SAVE,
+                true,
+                0,
+                false,
+            );
+            f()
+        }
+    }
+}
+
+/// Undoes the GUC changes made since this nest level was started, once dropped.
+///
+/// Like a function's `SET` clause, a normal return keeps any plain `SET` made in the meantime,
+/// whereas unwinding from an error or panic discards everything.
+struct GucNestLevel(i32);
+
+impl Drop for GucNestLevel {
+    fn drop(&mut self) {
+        let is_commit = !std::thread::panicking();
+        unsafe { pg_sys::AtEOXact_GUC(is_commit, self.0) }
+    }
+}
+
 /// A struct that has associated functions to register new GUCs
 pub struct GucRegistry {}
 impl GucRegistry {
@@ -217,6 +878,26 @@ impl GucRegistry {
         setting: &GucSetting<bool>,
         context: GucContext,
         flags: GucFlags,
+    ) {
+        Self::define_bool_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            flags,
+            GucHooks::default(),
+        )
+    }
+
+    pub fn define_bool_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<bool>,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<bool>,
     ) {
         unsafe {
             pg_sys::DefineCustomBoolVariable(
@@ -227,9 +908,9 @@ impl GucRegistry {
                 setting.get(),
                 context as isize as u32,
                 flags.bits(),
-                None,
-                None,
-                None,
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             );
         }
     }
@@ -243,6 +924,30 @@ impl GucRegistry {
         max_value: i32,
         context: GucContext,
         flags: GucFlags,
+    ) {
+        Self::define_int_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            flags,
+            GucHooks::default(),
+        )
+    }
+
+    pub fn define_int_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<i32>,
+        min_value: i32,
+        max_value: i32,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<i32>,
     ) {
         unsafe {
             pg_sys::DefineCustomIntVariable(
@@ -255,9 +960,133 @@ impl GucRegistry {
                 max_value,
                 context as isize as u32,
                 flags.bits(),
-                None,
-                None,
-                None,
+                hooks.check,
+                hooks.assign,
+                hooks.show,
+            )
+        }
+    }
+
+    /// Defines an integer GUC counted in `unit`, which Postgres lets be set with any time unit,
+    /// such as `'5min'`.  Durations are rounded down to a whole `unit`.
+    pub fn define_duration_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<Duration>,
+        min_value: Duration,
+        max_value: Duration,
+        unit: GucTimeUnit,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        Self::define_duration_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            unit,
+            context,
+            flags,
+            GucHooks::default(),
+        )
+    }
+
+    /// Like [`define_duration_guc()`][Self::define_duration_guc], with hooks.  Postgres stores the
+    /// setting as an integer, so the hooks see the value counted in `unit`.
+    pub fn define_duration_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<Duration>,
+        min_value: Duration,
+        max_value: Duration,
+        unit: GucTimeUnit,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<i32>,
+    ) {
+        unsafe {
+            let boot_val = unit.count(setting.boot_val);
+            *setting.as_ptr() = boot_val;
+            setting.unit.set(unit.as_raw());
+            pg_sys::DefineCustomIntVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                boot_val,
+                unit.count(min_value),
+                unit.count(max_value),
+                context as isize as u32,
+                with_unit(flags, unit.as_raw()),
+                hooks.check,
+                hooks.assign,
+                hooks.show,
+            )
+        }
+    }
+
+    /// Defines an integer GUC counted in `unit`, which Postgres lets be set with any memory unit,
+    /// such as `'64MB'`.  Sizes are rounded down to a whole `unit`.
+    pub fn define_memory_guc(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<usize>,
+        min_value: usize,
+        max_value: usize,
+        unit: GucMemoryUnit,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        Self::define_memory_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            unit,
+            context,
+            flags,
+            GucHooks::default(),
+        )
+    }
+
+    /// Like [`define_memory_guc()`][Self::define_memory_guc], with hooks.  Postgres stores the
+    /// setting as an integer, so the hooks see the value counted in `unit`.
+    pub fn define_memory_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<usize>,
+        min_value: usize,
+        max_value: usize,
+        unit: GucMemoryUnit,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<i32>,
+    ) {
+        unsafe {
+            let boot_val = unit.count(setting.boot_val);
+            *setting.as_ptr() = boot_val;
+            setting.unit.set(unit.as_raw());
+            pg_sys::DefineCustomIntVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                boot_val,
+                unit.count(min_value),
+                unit.count(max_value),
+                context as isize as u32,
+                with_unit(flags, unit.as_raw()),
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             )
         }
     }
@@ -269,6 +1098,26 @@ impl GucRegistry {
         setting: &GucSetting<Option<&'static CStr>>,
         context: GucContext,
         flags: GucFlags,
+    ) {
+        Self::define_string_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            flags,
+            GucHooks::default(),
+        )
+    }
+
+    pub fn define_string_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<Option<&'static CStr>>,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<Option<&'static CStr>>,
     ) {
         unsafe {
             let boot_val = setting.boot_val.map_or(std::ptr::null(), |s| s.as_ptr());
@@ -281,9 +1130,9 @@ impl GucRegistry {
                 boot_val,
                 context as isize as u32,
                 flags.bits(),
-                None,
-                None,
-                None,
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             );
         }
     }
@@ -297,6 +1146,30 @@ impl GucRegistry {
         max_value: f64,
         context: GucContext,
         flags: GucFlags,
+    ) {
+        Self::define_float_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            min_value,
+            max_value,
+            context,
+            flags,
+            GucHooks::default(),
+        )
+    }
+
+    pub fn define_float_guc_with_hooks(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<f64>,
+        min_value: f64,
+        max_value: f64,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<f64>,
     ) {
         unsafe {
             pg_sys::DefineCustomRealVariable(
@@ -309,9 +1182,9 @@ impl GucRegistry {
                 max_value,
                 context as isize as u32,
                 flags.bits(),
-                None,
-                None,
-                None,
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             );
         }
     }
@@ -325,6 +1198,28 @@ impl GucRegistry {
         flags: GucFlags,
     ) where
         T: GucEnum<T> + Copy,
+    {
+        Self::define_enum_guc_with_hooks(
+            name,
+            short_description,
+            long_description,
+            setting,
+            context,
+            flags,
+            GucHooks::default(),
+        )
+    }
+
+    pub fn define_enum_guc_with_hooks<T>(
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        setting: &GucSetting<T>,
+        context: GucContext,
+        flags: GucFlags,
+        hooks: GucHooks<T>,
+    ) where
+        T: GucEnum<T> + Copy,
     {
         unsafe {
             let boot_val = setting.boot_val.to_ordinal();
@@ -338,10 +1233,213 @@ impl GucRegistry {
                 setting.get().config_matrix(),
                 context as isize as u32,
                 flags.bits(),
-                None,
-                None,
-                None,
+                hooks.check,
+                hooks.assign,
+                hooks.show,
             );
         }
     }
 }
+
+/// Replaces whatever unit `flags` has with `unit`
+fn with_unit(flags: GucFlags, unit: u32) -> i32 {
+    (flags.bits() & !(pg_sys::GUC_UNIT as i32)) | unit as i32
+}
+
+/// How [`PostgresGuc`] registers one of its fields, from the field's `#[guc(...)]` attribute
+#[doc(hidden)]
+pub struct GucOptions<T: DefineGuc> {
+    pub min: Option<T::Bound>,
+    pub max: Option<T::Bound>,
+    pub unit: Option<T::Unit>,
+    pub context: GucContext,
+    pub flags: GucFlags,
+}
+
+/// The types a [`GucSetting`] can hold, with the [`GucRegistry`] function that defines them.  This
+/// is what lets [`PostgresGuc`] register a field of any of them.
+pub trait DefineGuc: Sized {
+    /// The type of the GUC's `min` and `max`, or `()` if it has none
+    type Bound;
+    /// The type of the GUC's `unit`, or `()` if it has none
+    type Unit;
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    );
+}
+
+impl DefineGuc for bool {
+    type Bound = ();
+    type Unit = ();
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    ) {
+        GucRegistry::define_bool_guc(
+            name,
+            short_description,
+            long_description,
+            setting,
+            options.context,
+            options.flags,
+        )
+    }
+}
+
+impl DefineGuc for i32 {
+    type Bound = i32;
+    type Unit = ();
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    ) {
+        GucRegistry::define_int_guc(
+            name,
+            short_description,
+            long_description,
+            setting,
+            options.min.unwrap_or(i32::MIN),
+            options.max.unwrap_or(i32::MAX),
+            options.context,
+            options.flags,
+        )
+    }
+}
+
+impl DefineGuc for f64 {
+    type Bound = f64;
+    type Unit = ();
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    ) {
+        GucRegistry::define_float_guc(
+            name,
+            short_description,
+            long_description,
+            setting,
+            options.min.unwrap_or(f64::MIN),
+            options.max.unwrap_or(f64::MAX),
+            options.context,
+            options.flags,
+        )
+    }
+}
+
+impl DefineGuc for Option<&'static CStr> {
+    type Bound = ();
+    type Unit = ();
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    ) {
+        GucRegistry::define_string_guc(
+            name,
+            short_description,
+            long_description,
+            setting,
+            options.context,
+            options.flags,
+        )
+    }
+}
+
+impl DefineGuc for Duration {
+    type Bound = Duration;
+    /// Defaults to milliseconds
+    type Unit = GucTimeUnit;
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    ) {
+        let unit = options.unit.unwrap_or(GucTimeUnit::Milliseconds);
+        GucRegistry::define_duration_guc(
+            name,
+            short_description,
+            long_description,
+            setting,
+            options.min.unwrap_or(Duration::ZERO),
+            options.max.unwrap_or_else(|| unit.duration(i32::MAX as u64)),
+            unit,
+            options.context,
+            options.flags,
+        )
+    }
+}
+
+impl DefineGuc for usize {
+    type Bound = usize;
+    /// Defaults to kilobytes
+    type Unit = GucMemoryUnit;
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    ) {
+        let unit = options.unit.unwrap_or(GucMemoryUnit::Kilobytes);
+        GucRegistry::define_memory_guc(
+            name,
+            short_description,
+            long_description,
+            setting,
+            options.min.unwrap_or(0),
+            options.max.unwrap_or(unit.bytes() * i32::MAX as usize),
+            unit,
+            options.context,
+            options.flags,
+        )
+    }
+}
+
+impl<T> DefineGuc for T
+where
+    T: GucEnum<T> + Copy,
+{
+    type Bound = ();
+    type Unit = ();
+
+    fn define(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        options: GucOptions<Self>,
+    ) {
+        GucRegistry::define_enum_guc(
+            name,
+            short_description,
+            long_description,
+            setting,
+            options.context,
+            options.flags,
+        )
+    }
+}
@@ -11,7 +11,7 @@
 use crate as pgrx; // for #[pg_guard] support from within ourself
 use crate::prelude::*;
 use crate::{void_mut_ptr, PgBox, PgList};
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
 #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
 // JumbleState is not defined prior to postgres v14.
@@ -23,6 +23,25 @@ pub struct JumbleState {}
 #[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16"))]
 pub use pg_sys::JumbleState;
 
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    /// The hooks that are only put in place once a registered [`PgHooks`] instance asks for them
+    /// with [`PgHooks::optional_hooks`], as merely having them in place changes what Postgres does
+    pub struct OptionalHooks: u32 {
+        /// [`PgHooks::object_access`]
+        const OBJECT_ACCESS = 1 << 0;
+        /// [`PgHooks::client_authentication`]
+        const CLIENT_AUTHENTICATION = 1 << 1;
+        /// [`PgHooks::explain_one_query`]
+        const EXPLAIN_ONE_QUERY = 1 << 2;
+        /// [`PgHooks::needs_fmgr`] and [`PgHooks::fmgr`], which Postgres asks about every function
+        /// it looks up
+        const FMGR = 1 << 3;
+        /// [`PgHooks::check_password`]
+        const CHECK_PASSWORD = 1 << 4;
+    }
+}
+
 pub struct HookResult<T> {
     pub inner: T,
 }
@@ -176,6 +195,157 @@ pub trait PgHooks {
         prev_hook(pstate, query, jumble_state)
     }
 
+    /// Hook for plugins to add paths, such as custom scans, for scanning a base relation.  Called
+    /// after the planner has added its own paths to `rel`.
+    fn set_rel_pathlist(
+        &mut self,
+        root: PgBox<pg_sys::PlannerInfo>,
+        rel: PgBox<pg_sys::RelOptInfo>,
+        rti: pg_sys::Index,
+        rte: PgBox<pg_sys::RangeTblEntry>,
+        prev_hook: fn(
+            root: PgBox<pg_sys::PlannerInfo>,
+            rel: PgBox<pg_sys::RelOptInfo>,
+            rti: pg_sys::Index,
+            rte: PgBox<pg_sys::RangeTblEntry>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(root, rel, rti, rte)
+    }
+
+    /// Hook for plugins to add paths, such as custom scans, for computing a join.  Called after
+    /// the planner has added its own paths to `joinrel`.
+    fn set_join_pathlist(
+        &mut self,
+        root: PgBox<pg_sys::PlannerInfo>,
+        joinrel: PgBox<pg_sys::RelOptInfo>,
+        outerrel: PgBox<pg_sys::RelOptInfo>,
+        innerrel: PgBox<pg_sys::RelOptInfo>,
+        jointype: pg_sys::JoinType,
+        extra: PgBox<pg_sys::JoinPathExtraData>,
+        prev_hook: fn(
+            root: PgBox<pg_sys::PlannerInfo>,
+            joinrel: PgBox<pg_sys::RelOptInfo>,
+            outerrel: PgBox<pg_sys::RelOptInfo>,
+            innerrel: PgBox<pg_sys::RelOptInfo>,
+            jointype: pg_sys::JoinType,
+            extra: PgBox<pg_sys::JoinPathExtraData>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(root, joinrel, outerrel, innerrel, jointype, extra)
+    }
+
+    /// The [`OptionalHooks`] that this instance overrides, none by default.  They're put in place
+    /// when the instance is registered.
+    fn optional_hooks(&self) -> OptionalHooks {
+        OptionalHooks::empty()
+    }
+
+    /// Hook for plugins to get control when an object is created, altered, dropped, or otherwise
+    /// accessed, such as to audit DDL.  Only called if asked for with
+    /// [`OptionalHooks::OBJECT_ACCESS`].
+    fn object_access(
+        &mut self,
+        access: pg_sys::ObjectAccessType,
+        class_id: pg_sys::Oid,
+        object_id: pg_sys::Oid,
+        sub_id: i32,
+        arg: void_mut_ptr,
+        prev_hook: fn(
+            access: pg_sys::ObjectAccessType,
+            class_id: pg_sys::Oid,
+            object_id: pg_sys::Oid,
+            sub_id: i32,
+            arg: void_mut_ptr,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(access, class_id, object_id, sub_id, arg)
+    }
+
+    /// Hook for plugins to get control in ClientAuthentication(), once a connecting client has
+    /// been authenticated, or not, as told by `status`.  Raising an error refuses the connection.
+    ///
+    /// This happens before any library is loaded for the new connection, so the hook must be
+    /// registered by a library in `shared_preload_libraries`.  Only called if asked for with
+    /// [`OptionalHooks::CLIENT_AUTHENTICATION`].
+    fn client_authentication(
+        &mut self,
+        port: PgBox<pg_sys::Port>,
+        status: i32,
+        prev_hook: fn(port: PgBox<pg_sys::Port>, status: i32) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(port, status)
+    }
+
+    /// Hook for plugins to get control in ExplainOneQuery(), such as to add their own details to
+    /// the output of `EXPLAIN`.  Only called if asked for with
+    /// [`OptionalHooks::EXPLAIN_ONE_QUERY`].
+    fn explain_one_query(
+        &mut self,
+        query: PgBox<pg_sys::Query>,
+        cursor_options: i32,
+        into: PgBox<pg_sys::IntoClause>,
+        es: PgBox<pg_sys::ExplainState>,
+        query_string: &core::ffi::CStr,
+        params: PgBox<pg_sys::ParamListInfoData>,
+        query_env: PgBox<pg_sys::QueryEnvironment>,
+        prev_hook: fn(
+            query: PgBox<pg_sys::Query>,
+            cursor_options: i32,
+            into: PgBox<pg_sys::IntoClause>,
+            es: PgBox<pg_sys::ExplainState>,
+            query_string: &core::ffi::CStr,
+            params: PgBox<pg_sys::ParamListInfoData>,
+            query_env: PgBox<pg_sys::QueryEnvironment>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(query, cursor_options, into, es, query_string, params, query_env)
+    }
+
+    /// Hook for plugins to say whether calls to a function should go through
+    /// [`PgHooks::fmgr`].  Only called if asked for with [`OptionalHooks::FMGR`].
+    fn needs_fmgr(
+        &mut self,
+        fn_oid: pg_sys::Oid,
+        prev_hook: fn(fn_oid: pg_sys::Oid) -> HookResult<bool>,
+    ) -> HookResult<bool> {
+        prev_hook(fn_oid)
+    }
+
+    /// Hook for plugins to get control when a function that [`PgHooks::needs_fmgr`] asked for
+    /// starts, ends, or aborts.  `arg` is private to the hooks for the duration of the call.
+    fn fmgr(
+        &mut self,
+        event: pg_sys::FmgrHookEventType,
+        flinfo: PgBox<pg_sys::FmgrInfo>,
+        arg: *mut pg_sys::Datum,
+        prev_hook: fn(
+            event: pg_sys::FmgrHookEventType,
+            flinfo: PgBox<pg_sys::FmgrInfo>,
+            arg: *mut pg_sys::Datum,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(event, flinfo, arg)
+    }
+
+    /// Hook for plugins to check a password given to `CREATE ROLE` or `ALTER ROLE`.  Raising an
+    /// error rejects it.  Only called if asked for with [`OptionalHooks::CHECK_PASSWORD`].
+    fn check_password(
+        &mut self,
+        username: &core::ffi::CStr,
+        shadow_pass: &core::ffi::CStr,
+        password_type: pg_sys::PasswordType,
+        valid_until: Option<TimestampWithTimeZone>,
+        prev_hook: fn(
+            username: &core::ffi::CStr,
+            shadow_pass: &core::ffi::CStr,
+            password_type: pg_sys::PasswordType,
+            valid_until: Option<TimestampWithTimeZone>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(username, shadow_pass, password_type, valid_until)
+    }
+
     /// Called when the transaction aborts
     fn abort(&mut self) {}
 
@@ -184,7 +354,10 @@ pub trait PgHooks {
 }
 
 struct Hooks {
-    current_hook: Box<&'static mut (dyn PgHooks)>,
+    /// Every registered hook, in the order they're called
+    registered: Vec<RegisteredHook>,
+    /// The optional hooks we've put in place so far
+    installed: OptionalHooks,
     prev_emit_log_hook: pg_sys::emit_log_hook_type,
     prev_executor_start_hook: pg_sys::ExecutorStart_hook_type,
     prev_executor_run_hook: pg_sys::ExecutorRun_hook_type,
@@ -194,15 +367,50 @@ struct Hooks {
     prev_process_utility_hook: pg_sys::ProcessUtility_hook_type,
     prev_planner_hook: pg_sys::planner_hook_type,
     prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook_type,
+    prev_set_rel_pathlist_hook: pg_sys::set_rel_pathlist_hook_type,
+    prev_set_join_pathlist_hook: pg_sys::set_join_pathlist_hook_type,
+    prev_object_access_hook: pg_sys::object_access_hook_type,
+    prev_client_authentication_hook: pg_sys::ClientAuthentication_hook_type,
+    prev_explain_one_query_hook: pg_sys::ExplainOneQuery_hook_type,
+    prev_needs_fmgr_hook: pg_sys::needs_fmgr_hook_type,
+    prev_fmgr_hook: pg_sys::fmgr_hook_type,
+    prev_check_password_hook: pg_sys::check_password_hook_type,
+}
+
+struct RegisteredHook {
+    order: i32,
+    hook: *mut dyn PgHooks,
 }
 
 static mut HOOKS: Option<Hooks> = None;
 
-/// Register a `PgHook` instance to respond to the various hook points
+/// The index, in `Hooks::registered`, of the hook being called
+static mut CURRENT_HOOK: usize = 0;
+
+/// Register a `PgHook` instance to respond to the various hook points.
+///
+/// Any number of instances can be registered, such as one for each part of an extension, and
+/// they're called in the order they were registered.  Each is given the next one as its
+/// `prev_hook`, and the last is given the hook Postgres had before, so an instance that doesn't
+/// call its `prev_hook` decides that no other will be called.
 pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
-    if HOOKS.is_some() {
-        panic!("PgHook instance already registered");
+    register_hook_with_order(hook, 0)
+}
+
+/// Register a `PgHook` instance, like [`register_hook`], to be called after the instances
+/// registered with a lesser or equal `order`, and before those with a greater one
+pub unsafe fn register_hook_with_order(hook: &'static mut (dyn PgHooks), order: i32) {
+    if HOOKS.is_none() {
+        install_hooks();
     }
+    install_optional_hooks(hook.optional_hooks());
+    let registered = &mut HOOKS.as_mut().unwrap().registered;
+    let index = registered.partition_point(|registered| registered.order <= order);
+    registered.insert(index, RegisteredHook { order, hook });
+}
+
+/// Puts our hooks in place of Postgres', the first time a `PgHook` instance is registered
+unsafe fn install_hooks() {
     #[cfg(any(
         feature = "pg11",
         feature = "pg12",
@@ -220,7 +428,8 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
         .or(Some(pgrx_standard_executor_check_perms_wrapper));
 
     HOOKS = Some(Hooks {
-        current_hook: Box::new(hook),
+        registered: Vec::new(),
+        installed: OptionalHooks::empty(),
         prev_executor_start_hook: pg_sys::ExecutorStart_hook
             .replace(pgrx_executor_start)
             .or(Some(pgrx_standard_executor_start_wrapper)),
@@ -243,25 +452,112 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
         prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook
             .replace(pgrx_post_parse_analyze),
         prev_emit_log_hook: pg_sys::emit_log_hook.replace(pgrx_emit_log),
+        prev_set_rel_pathlist_hook: pg_sys::set_rel_pathlist_hook.replace(pgrx_set_rel_pathlist),
+        prev_set_join_pathlist_hook: pg_sys::set_join_pathlist_hook.replace(pgrx_set_join_pathlist),
+        prev_object_access_hook: None,
+        prev_client_authentication_hook: None,
+        prev_explain_one_query_hook: None,
+        prev_needs_fmgr_hook: None,
+        prev_fmgr_hook: None,
+        prev_check_password_hook: None,
     });
 
     #[pg_guard]
     unsafe extern "C" fn xact_callback(event: pg_sys::XactEvent, _data: void_mut_ptr) {
-        match event {
-            pg_sys::XactEvent_XACT_EVENT_ABORT => HOOKS.as_mut().unwrap().current_hook.abort(),
-            pg_sys::XactEvent_XACT_EVENT_PRE_COMMIT => {
-                HOOKS.as_mut().unwrap().current_hook.commit()
+        for index in 0..HOOKS.as_ref().unwrap().registered.len() {
+            let hook = HOOKS.as_ref().unwrap().registered[index].hook;
+            let hook = &mut *hook;
+            match event {
+                pg_sys::XactEvent_XACT_EVENT_ABORT => hook.abort(),
+                pg_sys::XactEvent_XACT_EVENT_PRE_COMMIT => hook.commit(),
+                _ => { /* noop */ }
             }
-            _ => { /* noop */ }
         }
     }
 
     pg_sys::RegisterXactCallback(Some(xact_callback), std::ptr::null_mut());
 }
 
+/// Puts in place those of the `wanted` optional hooks that aren't already
+unsafe fn install_optional_hooks(wanted: OptionalHooks) {
+    let hooks = HOOKS.as_mut().unwrap();
+    let missing = wanted.difference(hooks.installed);
+
+    if missing.contains(OptionalHooks::OBJECT_ACCESS) {
+        hooks.prev_object_access_hook = pg_sys::object_access_hook.replace(pgrx_object_access);
+    }
+    if missing.contains(OptionalHooks::CLIENT_AUTHENTICATION) {
+        hooks.prev_client_authentication_hook =
+            pg_sys::ClientAuthentication_hook.replace(pgrx_client_authentication);
+    }
+    if missing.contains(OptionalHooks::EXPLAIN_ONE_QUERY) {
+        hooks.prev_explain_one_query_hook = pg_sys::ExplainOneQuery_hook
+            .replace(pgrx_explain_one_query)
+            .or(Some(pgrx_standard_explain_one_query_wrapper));
+    }
+    if missing.contains(OptionalHooks::FMGR) {
+        hooks.prev_needs_fmgr_hook = pg_sys::needs_fmgr_hook.replace(pgrx_needs_fmgr);
+        hooks.prev_fmgr_hook = pg_sys::fmgr_hook.replace(pgrx_fmgr);
+    }
+    if missing.contains(OptionalHooks::CHECK_PASSWORD) {
+        hooks.prev_check_password_hook = pg_sys::check_password_hook.replace(pgrx_check_password);
+    }
+
+    hooks.installed |= missing;
+}
+
+/// One of the registered hooks, which is the one being called until it's dropped
+struct CurrentHook {
+    hook: *mut dyn PgHooks,
+    /// The index of the hook that was being called before this one
+    prev_index: usize,
+}
+
+impl CurrentHook {
+    /// The first registered hook, for when Postgres calls one of ours
+    unsafe fn first() -> Self {
+        Self::at(0).expect("no PgHook instance is registered")
+    }
+
+    /// The hook after the one being called, for when that one calls its `prev_hook`
+    unsafe fn next() -> Option<Self> {
+        Self::at(CURRENT_HOOK + 1)
+    }
+
+    unsafe fn at(index: usize) -> Option<Self> {
+        let hook = HOOKS.as_ref().unwrap().registered.get(index)?.hook;
+        let prev_index = CURRENT_HOOK;
+        CURRENT_HOOK = index;
+        Some(CurrentHook { hook, prev_index })
+    }
+}
+
+impl Deref for CurrentHook {
+    type Target = dyn PgHooks;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.hook }
+    }
+}
+
+impl DerefMut for CurrentHook {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.hook }
+    }
+}
+
+impl Drop for CurrentHook {
+    fn drop(&mut self) {
+        unsafe { CURRENT_HOOK = self.prev_index }
+    }
+}
+
 #[pg_guard]
 unsafe extern "C" fn pgrx_executor_start(query_desc: *mut pg_sys::QueryDesc, eflags: i32) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>, eflags: i32) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.executor_start(query_desc, eflags, prev);
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_start_hook.as_ref().unwrap())(
                 query_desc.into_pg(),
@@ -270,7 +566,7 @@ unsafe extern "C" fn pgrx_executor_start(query_desc: *mut pg_sys::QueryDesc, efl
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.executor_start(PgBox::from_pg(query_desc), eflags, prev);
 }
 
@@ -287,6 +583,9 @@ unsafe extern "C" fn pgrx_executor_run(
         count: u64,
         execute_once: bool,
     ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.executor_run(query_desc, direction, count, execute_once, prev);
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_run_hook.as_ref().unwrap())(
                 query_desc.into_pg(),
@@ -297,13 +596,16 @@ unsafe extern "C" fn pgrx_executor_run(
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.executor_run(PgBox::from_pg(query_desc), direction, count, execute_once, prev);
 }
 
 #[pg_guard]
 unsafe extern "C" fn pgrx_executor_finish(query_desc: *mut pg_sys::QueryDesc) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.executor_finish(query_desc, prev);
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_finish_hook.as_ref().unwrap())(
                 query_desc.into_pg(),
@@ -311,19 +613,22 @@ unsafe extern "C" fn pgrx_executor_finish(query_desc: *mut pg_sys::QueryDesc) {
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.executor_finish(PgBox::from_pg(query_desc), prev);
 }
 
 #[pg_guard]
 unsafe extern "C" fn pgrx_executor_end(query_desc: *mut pg_sys::QueryDesc) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.executor_end(query_desc, prev);
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_end_hook.as_ref().unwrap())(query_desc.into_pg())
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.executor_end(PgBox::from_pg(query_desc), prev);
 }
 
@@ -341,9 +646,17 @@ unsafe extern "C" fn pgrx_executor_check_perms(
 ) -> bool {
     fn prev(
         range_table: PgList<*mut pg_sys::RangeTblEntry>,
-        _rte_perm_infos: Option<*mut pg_sys::List>,
+        rte_perm_infos: Option<*mut pg_sys::List>,
         ereport_on_violation: bool,
     ) -> HookResult<bool> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.executor_check_perms(
+                range_table,
+                rte_perm_infos,
+                ereport_on_violation,
+                prev,
+            );
+        }
         HookResult::new(unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_check_perms_hook.as_ref().unwrap())(
                 range_table.into_pg(),
@@ -351,7 +664,7 @@ unsafe extern "C" fn pgrx_executor_check_perms(
             )
         })
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.executor_check_perms(PgList::from_pg(range_table), None, ereport_on_violation, prev).inner
 }
 
@@ -367,6 +680,14 @@ unsafe extern "C" fn pgrx_executor_check_perms(
         rte_perm_infos: Option<*mut pg_sys::List>,
         ereport_on_violation: bool,
     ) -> HookResult<bool> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.executor_check_perms(
+                range_table,
+                rte_perm_infos,
+                ereport_on_violation,
+                prev,
+            );
+        }
         HookResult::new(unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_check_perms_hook.as_ref().unwrap())(
                 range_table.into_pg(),
@@ -375,7 +696,7 @@ unsafe extern "C" fn pgrx_executor_check_perms(
             )
         })
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.executor_check_perms(
         PgList::from_pg(range_table),
         Some(rte_perm_infos),
@@ -399,13 +720,26 @@ unsafe extern "C" fn pgrx_process_utility(
     fn prev(
         pstmt: PgBox<pg_sys::PlannedStmt>,
         query_string: &core::ffi::CStr,
-        _read_only_tree: Option<bool>,
+        read_only_tree: Option<bool>,
         context: pg_sys::ProcessUtilityContext,
         params: PgBox<pg_sys::ParamListInfoData>,
         query_env: PgBox<pg_sys::QueryEnvironment>,
         dest: PgBox<pg_sys::DestReceiver>,
         completion_tag: *mut pg_sys::QueryCompletion,
     ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.process_utility_hook(
+                pstmt,
+                query_string,
+                read_only_tree,
+                context,
+                params,
+                query_env,
+                dest,
+                completion_tag,
+                prev,
+            );
+        }
         HookResult::new(unsafe {
             (HOOKS.as_mut().unwrap().prev_process_utility_hook.as_ref().unwrap())(
                 pstmt.into_pg(),
@@ -419,7 +753,7 @@ unsafe extern "C" fn pgrx_process_utility(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.process_utility_hook(
         PgBox::from_pg(pstmt),
         core::ffi::CStr::from_ptr(query_string),
@@ -455,6 +789,19 @@ unsafe extern "C" fn pgrx_process_utility(
         dest: PgBox<pg_sys::DestReceiver>,
         completion_tag: *mut pg_sys::QueryCompletion,
     ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.process_utility_hook(
+                pstmt,
+                query_string,
+                read_only_tree,
+                context,
+                params,
+                query_env,
+                dest,
+                completion_tag,
+                prev,
+            );
+        }
         HookResult::new(unsafe {
             (HOOKS.as_mut().unwrap().prev_process_utility_hook.as_ref().unwrap())(
                 pstmt.into_pg(),
@@ -469,7 +816,7 @@ unsafe extern "C" fn pgrx_process_utility(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.process_utility_hook(
         PgBox::from_pg(pstmt),
         core::ffi::CStr::from_ptr(query_string),
@@ -518,6 +865,9 @@ unsafe extern "C" fn pgrx_planner_impl(
         cursor_options: i32,
         bound_params: PgBox<pg_sys::ParamListInfoData>,
     ) -> HookResult<*mut pg_sys::PlannedStmt> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.planner(parse, query_string, cursor_options, bound_params, prev);
+        }
         HookResult::new(unsafe {
             #[cfg(any(feature = "pg11", feature = "pg12"))]
             {
@@ -539,7 +889,7 @@ unsafe extern "C" fn pgrx_planner_impl(
             }
         })
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.planner(
         PgBox::from_pg(parse),
         query_string,
@@ -559,8 +909,11 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
     fn prev(
         parse_state: PgBox<pg_sys::ParseState>,
         query: PgBox<pg_sys::Query>,
-        _jumble_state: Option<PgBox<JumbleState>>,
+        jumble_state: Option<PgBox<JumbleState>>,
     ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.post_parse_analyze(parse_state, query, jumble_state, prev);
+        }
         HookResult::new(unsafe {
             match HOOKS.as_mut().unwrap().prev_post_parse_analyze_hook.as_ref() {
                 None => (),
@@ -569,7 +922,7 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.post_parse_analyze(PgBox::from_pg(parse_state), PgBox::from_pg(query), None, prev).inner
 }
 
@@ -585,6 +938,9 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
         query: PgBox<pg_sys::Query>,
         jumble_state: Option<PgBox<JumbleState>>,
     ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.post_parse_analyze(parse_state, query, jumble_state, prev);
+        }
         HookResult::new(unsafe {
             match HOOKS.as_mut().unwrap().prev_post_parse_analyze_hook.as_ref() {
                 None => (),
@@ -595,7 +951,7 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.post_parse_analyze(
         PgBox::from_pg(parse_state),
         PgBox::from_pg(query),
@@ -608,6 +964,9 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
 #[pg_guard]
 unsafe extern "C" fn pgrx_emit_log(error_data: *mut pg_sys::ErrorData) {
     fn prev(error_data: PgBox<pg_sys::ErrorData>) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.emit_log(error_data, prev);
+        }
         HookResult::new(unsafe {
             match HOOKS.as_mut().unwrap().prev_emit_log_hook.as_ref() {
                 None => (),
@@ -616,10 +975,280 @@ unsafe extern "C" fn pgrx_emit_log(error_data: *mut pg_sys::ErrorData) {
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
+    let mut hook = CurrentHook::first();
     hook.emit_log(PgBox::from_pg(error_data), prev).inner
 }
 
+#[pg_guard]
+unsafe extern "C" fn pgrx_set_rel_pathlist(
+    root: *mut pg_sys::PlannerInfo,
+    rel: *mut pg_sys::RelOptInfo,
+    rti: pg_sys::Index,
+    rte: *mut pg_sys::RangeTblEntry,
+) {
+    fn prev(
+        root: PgBox<pg_sys::PlannerInfo>,
+        rel: PgBox<pg_sys::RelOptInfo>,
+        rti: pg_sys::Index,
+        rte: PgBox<pg_sys::RangeTblEntry>,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.set_rel_pathlist(root, rel, rti, rte, prev);
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_set_rel_pathlist_hook.as_ref() {
+                None => (),
+                Some(f) => (f)(root.as_ptr(), rel.as_ptr(), rti, rte.as_ptr()),
+            }
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.set_rel_pathlist(PgBox::from_pg(root), PgBox::from_pg(rel), rti, PgBox::from_pg(rte), prev)
+        .inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_set_join_pathlist(
+    root: *mut pg_sys::PlannerInfo,
+    joinrel: *mut pg_sys::RelOptInfo,
+    outerrel: *mut pg_sys::RelOptInfo,
+    innerrel: *mut pg_sys::RelOptInfo,
+    jointype: pg_sys::JoinType,
+    extra: *mut pg_sys::JoinPathExtraData,
+) {
+    fn prev(
+        root: PgBox<pg_sys::PlannerInfo>,
+        joinrel: PgBox<pg_sys::RelOptInfo>,
+        outerrel: PgBox<pg_sys::RelOptInfo>,
+        innerrel: PgBox<pg_sys::RelOptInfo>,
+        jointype: pg_sys::JoinType,
+        extra: PgBox<pg_sys::JoinPathExtraData>,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook
+                .set_join_pathlist(root, joinrel, outerrel, innerrel, jointype, extra, prev);
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_set_join_pathlist_hook.as_ref() {
+                None => (),
+                Some(f) => (f)(
+                    root.as_ptr(),
+                    joinrel.as_ptr(),
+                    outerrel.as_ptr(),
+                    innerrel.as_ptr(),
+                    jointype,
+                    extra.as_ptr(),
+                ),
+            }
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.set_join_pathlist(
+        PgBox::from_pg(root),
+        PgBox::from_pg(joinrel),
+        PgBox::from_pg(outerrel),
+        PgBox::from_pg(innerrel),
+        jointype,
+        PgBox::from_pg(extra),
+        prev,
+    )
+    .inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_object_access(
+    access: pg_sys::ObjectAccessType,
+    class_id: pg_sys::Oid,
+    object_id: pg_sys::Oid,
+    sub_id: i32,
+    arg: void_mut_ptr,
+) {
+    fn prev(
+        access: pg_sys::ObjectAccessType,
+        class_id: pg_sys::Oid,
+        object_id: pg_sys::Oid,
+        sub_id: i32,
+        arg: void_mut_ptr,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.object_access(access, class_id, object_id, sub_id, arg, prev);
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_object_access_hook.as_ref() {
+                None => (),
+                Some(f) => (f)(access, class_id, object_id, sub_id, arg),
+            }
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.object_access(access, class_id, object_id, sub_id, arg, prev).inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_client_authentication(port: *mut pg_sys::Port, status: i32) {
+    fn prev(port: PgBox<pg_sys::Port>, status: i32) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.client_authentication(port, status, prev);
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_client_authentication_hook.as_ref() {
+                None => (),
+                Some(f) => (f)(port.as_ptr(), status),
+            }
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.client_authentication(PgBox::from_pg(port), status, prev).inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const ::std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    fn prev(
+        query: PgBox<pg_sys::Query>,
+        cursor_options: i32,
+        into: PgBox<pg_sys::IntoClause>,
+        es: PgBox<pg_sys::ExplainState>,
+        query_string: &core::ffi::CStr,
+        params: PgBox<pg_sys::ParamListInfoData>,
+        query_env: PgBox<pg_sys::QueryEnvironment>,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.explain_one_query(
+                query,
+                cursor_options,
+                into,
+                es,
+                query_string,
+                params,
+                query_env,
+                prev,
+            );
+        }
+        HookResult::new(unsafe {
+            (HOOKS.as_mut().unwrap().prev_explain_one_query_hook.as_ref().unwrap())(
+                query.into_pg(),
+                cursor_options,
+                into.into_pg(),
+                es.into_pg(),
+                query_string.as_ptr(),
+                params.into_pg(),
+                query_env.into_pg(),
+            )
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.explain_one_query(
+        PgBox::from_pg(query),
+        cursor_options,
+        PgBox::from_pg(into),
+        PgBox::from_pg(es),
+        core::ffi::CStr::from_ptr(query_string),
+        PgBox::from_pg(params),
+        PgBox::from_pg(query_env),
+        prev,
+    )
+    .inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_needs_fmgr(fn_oid: pg_sys::Oid) -> bool {
+    fn prev(fn_oid: pg_sys::Oid) -> HookResult<bool> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.needs_fmgr(fn_oid, prev);
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_needs_fmgr_hook.as_ref() {
+                None => false,
+                Some(f) => (f)(fn_oid),
+            }
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.needs_fmgr(fn_oid, prev).inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_fmgr(
+    event: pg_sys::FmgrHookEventType,
+    flinfo: *mut pg_sys::FmgrInfo,
+    arg: *mut pg_sys::Datum,
+) {
+    fn prev(
+        event: pg_sys::FmgrHookEventType,
+        flinfo: PgBox<pg_sys::FmgrInfo>,
+        arg: *mut pg_sys::Datum,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.fmgr(event, flinfo, arg, prev);
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_fmgr_hook.as_ref() {
+                None => (),
+                Some(f) => (f)(event, flinfo.as_ptr(), arg),
+            }
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.fmgr(event, PgBox::from_pg(flinfo), arg, prev).inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_check_password(
+    username: *const ::std::os::raw::c_char,
+    shadow_pass: *const ::std::os::raw::c_char,
+    password_type: pg_sys::PasswordType,
+    validuntil_time: pg_sys::Datum,
+    validuntil_null: bool,
+) {
+    fn prev(
+        username: &core::ffi::CStr,
+        shadow_pass: &core::ffi::CStr,
+        password_type: pg_sys::PasswordType,
+        valid_until: Option<TimestampWithTimeZone>,
+    ) -> HookResult<()> {
+        if let Some(mut hook) = unsafe { CurrentHook::next() } {
+            return hook.check_password(username, shadow_pass, password_type, valid_until, prev);
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_check_password_hook.as_ref() {
+                None => (),
+                Some(f) => (f)(
+                    username.as_ptr(),
+                    shadow_pass.as_ptr(),
+                    password_type,
+                    valid_until.into_datum().unwrap_or(pg_sys::Datum::from(0)),
+                    valid_until.is_none(),
+                ),
+            }
+        })
+    }
+
+    let mut hook = CurrentHook::first();
+    hook.check_password(
+        core::ffi::CStr::from_ptr(username),
+        core::ffi::CStr::from_ptr(shadow_pass),
+        password_type,
+        TimestampWithTimeZone::from_datum(validuntil_time, validuntil_null),
+        prev,
+    )
+    .inner
+}
+
 #[pg_guard]
 unsafe extern "C" fn pgrx_standard_executor_start_wrapper(
     query_desc: *mut pg_sys::QueryDesc,
@@ -739,3 +1368,73 @@ unsafe extern "C" fn pgrx_standard_planner_wrapper(
 ) -> *mut pg_sys::PlannedStmt {
     pg_sys::standard_planner(parse, query_string, cursor_options, bound_params)
 }
+
+/// What ExplainOneQuery() does in Postgres 11 and 12 when there's no hook.  Postgres only
+/// exports this as standard_ExplainOneQuery() from version 17.
+#[cfg(any(feature = "pg11", feature = "pg12"))]
+#[pg_guard]
+unsafe extern "C" fn pgrx_standard_explain_one_query_wrapper(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const ::std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    let start = std::time::Instant::now();
+    let plan = pg_sys::pg_plan_query(query, cursor_options, params);
+    let planduration = instr_time_since(start);
+
+    pg_sys::ExplainOnePlan(plan, into, es, query_string, params, query_env, &planduration);
+}
+
+/// What ExplainOneQuery() does in Postgres 13 to 16 when there's no hook.  Postgres only
+/// exports this as standard_ExplainOneQuery() from version 17.
+#[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16"))]
+#[pg_guard]
+unsafe extern "C" fn pgrx_standard_explain_one_query_wrapper(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const ::std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    let bufusage_start = pg_sys::pgBufferUsage;
+    let start = std::time::Instant::now();
+    let plan = pg_sys::pg_plan_query(query, query_string, cursor_options, params);
+    let planduration = instr_time_since(start);
+
+    let mut bufusage = pg_sys::BufferUsage::default();
+    let bufusage = if (*es).buffers {
+        pg_sys::BufferUsageAccumDiff(&mut bufusage, &pg_sys::pgBufferUsage, &bufusage_start);
+        &bufusage as *const _
+    } else {
+        std::ptr::null()
+    };
+
+    pg_sys::ExplainOnePlan(
+        plan,
+        into,
+        es,
+        query_string,
+        params,
+        query_env,
+        &planduration,
+        bufusage,
+    );
+}
+
+/// The time since `start`, as Postgres' `INSTR_TIME_SET_CURRENT()` and `INSTR_TIME_SUBTRACT()`
+/// would have measured it
+fn instr_time_since(start: std::time::Instant) -> pg_sys::instr_time {
+    let elapsed = start.elapsed();
+    #[cfg(not(feature = "pg16"))]
+    let time =
+        pg_sys::instr_time { tv_sec: elapsed.as_secs() as _, tv_nsec: elapsed.subsec_nanos() as _ };
+    #[cfg(feature = "pg16")]
+    let time = pg_sys::instr_time { ticks: elapsed.as_nanos() as _ };
+    time
+}
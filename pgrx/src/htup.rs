@@ -8,6 +8,7 @@
 //LICENSE
 //LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 //! Utility functions for working with [`pg_sys::HeapTuple`][crate::pg_sys::HeapTuple] and [`pg_sys::HeapTupleHeader`][crate::pg_sys::HeapTupleHeader] structs
+use crate::heap_tuple::PgHeapTuple;
 use crate::*;
 use seq_macro::seq;
 use std::num::NonZeroUsize;
@@ -214,3 +215,10 @@ seq!(I in 0..32 {
         });
     )*
 });
+
+/// An already-formed tuple is its own heap tuple.  Its `TupleDesc` must match the one requested.
+impl<'a> IntoHeapTuple for PgHeapTuple<'a, AllocatedByRust> {
+    unsafe fn into_heap_tuple(self, _tupdesc: pg_sys::TupleDesc) -> *mut pg_sys::HeapTupleData {
+        self.into_pg()
+    }
+}
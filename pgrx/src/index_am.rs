@@ -0,0 +1,580 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Safe support for writing Postgres Index Access Methods
+//!
+//! Implement [`IndexAccessMethod`] for a type and decorate the `impl` block with `#[pg_index_am]`.
+//! pgrx will generate the `index_am_handler` function along with the
+//! `CREATE ACCESS METHOD ... TYPE INDEX` statement for you.  Operator classes for the new access
+//! method still need to be created with [`extension_sql!`](crate::extension_sql).
+//!
+//! A new instance of the implementing type is created for every index scan (see
+//! [`IndexAccessMethod::begin_scan`]).  Everything else is an associated function which is handed
+//! the relations it operates on.
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use crate::{pg_sys, PgMemoryContexts, PgRelation};
+
+/// The outcome of [`IndexAccessMethod::build`]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct IndexBuildStats {
+    /// The number of tuples found in the heap
+    pub heap_tuples: f64,
+    /// The number of tuples added to the index
+    pub index_tuples: f64,
+}
+
+/// The planner's cost estimate for an index scan, as returned by [`IndexAccessMethod::cost_estimate`]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct IndexCostEstimate {
+    pub startup_cost: pg_sys::Cost,
+    pub total_cost: pg_sys::Cost,
+    pub selectivity: pg_sys::Selectivity,
+    pub correlation: f64,
+    pub pages: f64,
+}
+
+/// A heap tuple matched by an index scan
+#[derive(Debug, Copy, Clone)]
+pub struct IndexScanItem {
+    /// The location of the matching heap tuple
+    pub heap_tid: pg_sys::ItemPointerData,
+    /// Should the executor recheck the scan keys against the heap tuple?
+    pub recheck: bool,
+}
+
+/// Asks `VACUUM` if an index entry points to a dead heap tuple, during [`IndexAccessMethod::bulk_delete`]
+pub struct BulkDeleteCallback {
+    callback: pg_sys::IndexBulkDeleteCallback,
+    state: *mut std::os::raw::c_void,
+}
+
+impl BulkDeleteCallback {
+    /// Returns true if the heap tuple at `heap_tid` is dead, and its index entry should be removed
+    pub fn is_dead(&self, heap_tid: pg_sys::ItemPointerData) -> bool {
+        let mut heap_tid = heap_tid;
+        match self.callback {
+            // SAFETY:  Postgres gave us this callback and its state together
+            Some(callback) => unsafe { callback(&mut heap_tid, self.state) },
+            None => false,
+        }
+    }
+}
+
+/// The bitmap being built by [`IndexAccessMethod::get_bitmap`]
+pub struct TidBitmap(*mut pg_sys::TIDBitmap);
+
+impl TidBitmap {
+    /// Add the heap tuple at `heap_tid` to the bitmap
+    pub fn add(&mut self, heap_tid: pg_sys::ItemPointerData, recheck: bool) {
+        let mut heap_tid = heap_tid;
+        unsafe {
+            // SAFETY:  the bitmap came from Postgres and lives for the duration of the scan
+            pg_sys::tbm_add_tuples(self.0, &mut heap_tid, 1, recheck);
+        }
+    }
+}
+
+/// A Postgres Index Access Method.
+///
+/// Use `#[pg_index_am]` on the `impl` block to generate the handler function and SQL.
+pub trait IndexAccessMethod: Sized {
+    /// Number of operator strategies, or 0 if the access method doesn't have a fixed set
+    const STRATEGIES: u16 = 0;
+    /// Number of support functions
+    const SUPPORT: u16 = 0;
+    /// Does the access method support ordered scans sorted by the indexed column's value?
+    const CAN_ORDER: bool = false;
+    /// Does the access method support `ORDER BY` an operator result?
+    const CAN_ORDER_BY_OP: bool = false;
+    /// Does the access method support backward scanning?
+    const CAN_BACKWARD: bool = false;
+    /// Does the access method support `UNIQUE` indexes?
+    const CAN_UNIQUE: bool = false;
+    /// Does the access method support multi-column indexes?
+    const CAN_MULTICOL: bool = false;
+    /// Can a scan omit a restriction on the first index column?
+    const OPTIONAL_KEY: bool = false;
+    /// Does the access method handle `ScalarArrayOpExpr` quals?
+    const SEARCH_ARRAY: bool = false;
+    /// Does the access method handle `IS NULL`/`IS NOT NULL` quals?
+    const SEARCH_NULLS: bool = false;
+    /// Can the index storage type differ from the column's type?
+    const STORAGE: bool = false;
+    /// Can the index be clustered on?
+    const CLUSTERABLE: bool = false;
+    /// Does the access method handle predicate locks?
+    const PREDICATE_LOCKS: bool = false;
+    /// The type of the data stored in the index, or `InvalidOid` if it varies
+    const KEY_TYPE: pg_sys::Oid = pg_sys::InvalidOid;
+
+    /// Build a new index over the existing contents of `heap`.
+    ///
+    /// [`index_build_heap_scan`] can be used to visit each of the heap's tuples.
+    fn build(
+        heap: &PgRelation,
+        index: &PgRelation,
+        index_info: *mut pg_sys::IndexInfo,
+    ) -> IndexBuildStats;
+
+    /// Build an empty index in the `INIT_FORKNUM` fork, for unlogged tables
+    fn build_empty(_index: &PgRelation) {}
+
+    /// Insert a new entry for the heap tuple at `heap_tid` into the index.
+    ///
+    /// Only meaningful for unique indexes, the return value indicates if the uniqueness check
+    /// was deferred and the tuple is known to be unique.
+    fn insert(
+        index: &PgRelation,
+        values: &[pg_sys::Datum],
+        isnull: &[bool],
+        heap_tid: pg_sys::ItemPointerData,
+        heap: &PgRelation,
+        check_unique: pg_sys::IndexUniqueCheck,
+    ) -> bool;
+
+    /// Remove every index entry for which `callback` says the heap tuple is dead
+    fn bulk_delete(
+        info: &pg_sys::IndexVacuumInfo,
+        stats: &mut pg_sys::IndexBulkDeleteResult,
+        callback: &BulkDeleteCallback,
+    );
+
+    /// Clean up after `VACUUM`, updating `stats` as necessary.
+    ///
+    /// Not called for `ANALYZE`-only operations.
+    fn vacuum_cleanup(_info: &pg_sys::IndexVacuumInfo, _stats: &mut pg_sys::IndexBulkDeleteResult) {
+    }
+
+    /// Estimate the costs of an index scan.
+    ///
+    /// The default implementation uses Postgres' `genericcostestimate()`.
+    fn cost_estimate(
+        root: *mut pg_sys::PlannerInfo,
+        path: *mut pg_sys::IndexPath,
+        loop_count: f64,
+    ) -> IndexCostEstimate {
+        unsafe { generic_cost_estimate(root, path, loop_count) }
+    }
+
+    /// Parse and validate the index's `WITH (...)` options.
+    ///
+    /// The default implementation doesn't support any options.
+    fn options(reloptions: pg_sys::Datum, validate: bool) -> *mut pg_sys::bytea {
+        if validate && !reloptions.is_null() {
+            error!("this index access method does not support any options")
+        }
+        std::ptr::null_mut()
+    }
+
+    /// Validate the operator class `opclass`
+    fn validate(_opclass: pg_sys::Oid) -> bool {
+        true
+    }
+
+    /// Start a new scan of `index`
+    fn begin_scan(index: &PgRelation, nkeys: i32, norderbys: i32) -> Self;
+
+    /// (Re)start the scan with the given keys
+    fn rescan(&mut self, keys: &[pg_sys::ScanKeyData], orderbys: &[pg_sys::ScanKeyData]);
+
+    /// Return the next matching heap tuple, or `None` when the scan is finished
+    fn get_tuple(&mut self, direction: pg_sys::ScanDirection) -> Option<IndexScanItem>;
+
+    /// Add every matching heap tuple to `tbm`, returning how many were added.
+    ///
+    /// The default implementation adds everything returned by [`IndexAccessMethod::get_tuple`].
+    fn get_bitmap(&mut self, tbm: &mut TidBitmap) -> i64 {
+        let mut ntids = 0;
+        while let Some(item) = self.get_tuple(pg_sys::ScanDirection_ForwardScanDirection) {
+            tbm.add(item.heap_tid, item.recheck);
+            ntids += 1;
+        }
+        ntids
+    }
+
+    /// Called when the scan is finished.  Any resources not cleaned up here are released when
+    /// `Self` is dropped.
+    fn end_scan(&mut self) {}
+}
+
+/// Build the `IndexAmRoutine` for the [`IndexAccessMethod`] `T`.
+///
+/// This is what the handler function generated by `#[pg_index_am]` returns.
+pub fn index_am_routine<T: IndexAccessMethod>() -> PgBox<pg_sys::IndexAmRoutine, AllocatedByRust> {
+    unsafe {
+        let mut routine =
+            PgBox::<pg_sys::IndexAmRoutine>::alloc_node(pg_sys::NodeTag_T_IndexAmRoutine);
+
+        routine.amstrategies = T::STRATEGIES;
+        routine.amsupport = T::SUPPORT;
+        routine.amcanorder = T::CAN_ORDER;
+        routine.amcanorderbyop = T::CAN_ORDER_BY_OP;
+        routine.amcanbackward = T::CAN_BACKWARD;
+        routine.amcanunique = T::CAN_UNIQUE;
+        routine.amcanmulticol = T::CAN_MULTICOL;
+        routine.amoptionalkey = T::OPTIONAL_KEY;
+        routine.amsearcharray = T::SEARCH_ARRAY;
+        routine.amsearchnulls = T::SEARCH_NULLS;
+        routine.amstorage = T::STORAGE;
+        routine.amclusterable = T::CLUSTERABLE;
+        routine.ampredlocks = T::PREDICATE_LOCKS;
+        routine.amcanparallel = false;
+        routine.amcaninclude = false;
+        routine.amkeytype = T::KEY_TYPE;
+
+        routine.ambuild = Some(ambuild::<T>);
+        routine.ambuildempty = Some(ambuildempty::<T>);
+        routine.aminsert = Some(aminsert::<T>);
+        routine.ambulkdelete = Some(ambulkdelete::<T>);
+        routine.amvacuumcleanup = Some(amvacuumcleanup::<T>);
+        routine.amcostestimate = Some(amcostestimate::<T>);
+        routine.amoptions = Some(amoptions::<T>);
+        routine.amvalidate = Some(amvalidate::<T>);
+        routine.ambeginscan = Some(ambeginscan::<T>);
+        routine.amrescan = Some(amrescan::<T>);
+        routine.amgettuple = Some(amgettuple::<T>);
+        routine.amgetbitmap = Some(amgetbitmap::<T>);
+        routine.amendscan = Some(amendscan::<T>);
+
+        routine
+    }
+}
+
+/// Visit every live tuple in `heap`, providing the index column values computed from it
+/// and its location.  Returns the number of tuples visited.
+///
+/// This is the typical way to implement [`IndexAccessMethod::build`].
+pub fn index_build_heap_scan<F>(
+    heap: &PgRelation,
+    index: &PgRelation,
+    index_info: *mut pg_sys::IndexInfo,
+    mut callback: F,
+) -> f64
+where
+    F: FnMut(pg_sys::ItemPointerData, &[pg_sys::Datum], &[bool], bool),
+{
+    struct BuildState<'a> {
+        natts: usize,
+        ntuples: f64,
+        callback: &'a mut dyn FnMut(pg_sys::ItemPointerData, &[pg_sys::Datum], &[bool], bool),
+    }
+
+    #[cfg(any(feature = "pg11", feature = "pg12"))]
+    #[pg_guard]
+    unsafe extern "C" fn build_callback(
+        _index: pg_sys::Relation,
+        htup: pg_sys::HeapTuple,
+        values: *mut pg_sys::Datum,
+        isnull: *mut bool,
+        tuple_is_alive: bool,
+        state: *mut std::os::raw::c_void,
+    ) {
+        build_callback_internal((*htup).t_self, values, isnull, tuple_is_alive, state)
+    }
+
+    #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16"))]
+    #[pg_guard]
+    unsafe extern "C" fn build_callback(
+        _index: pg_sys::Relation,
+        tid: pg_sys::ItemPointer,
+        values: *mut pg_sys::Datum,
+        isnull: *mut bool,
+        tuple_is_alive: bool,
+        state: *mut std::os::raw::c_void,
+    ) {
+        build_callback_internal(*tid, values, isnull, tuple_is_alive, state)
+    }
+
+    unsafe fn build_callback_internal(
+        tid: pg_sys::ItemPointerData,
+        values: *mut pg_sys::Datum,
+        isnull: *mut bool,
+        tuple_is_alive: bool,
+        state: *mut std::os::raw::c_void,
+    ) {
+        let state = state.cast::<BuildState>().as_mut().unwrap();
+        let values = std::slice::from_raw_parts(values, state.natts);
+        let isnull = std::slice::from_raw_parts(isnull, state.natts);
+        state.ntuples += 1.0;
+        (state.callback)(tid, values, isnull, tuple_is_alive);
+    }
+
+    let mut state =
+        BuildState { natts: index.tuple_desc().len(), ntuples: 0.0, callback: &mut callback };
+    unsafe {
+        pg_sys::IndexBuildHeapScan(
+            heap.as_ptr(),
+            index.as_ptr(),
+            index_info,
+            Some(build_callback),
+            &mut state,
+        );
+    }
+    state.ntuples
+}
+
+/// Estimate the costs of an index scan using Postgres' `genericcostestimate()`
+///
+/// ## Safety
+///
+/// `root` and `path` must be the valid pointers Postgres provided to `amcostestimate`
+pub unsafe fn generic_cost_estimate(
+    root: *mut pg_sys::PlannerInfo,
+    path: *mut pg_sys::IndexPath,
+    loop_count: f64,
+) -> IndexCostEstimate {
+    let mut costs = pg_sys::GenericCosts::default();
+
+    #[cfg(feature = "pg11")]
+    pg_sys::genericcostestimate(
+        root,
+        path,
+        loop_count,
+        pg_sys::deconstruct_indexquals(path),
+        &mut costs,
+    );
+
+    #[cfg(not(feature = "pg11"))]
+    pg_sys::genericcostestimate(root, path, loop_count, &mut costs);
+
+    IndexCostEstimate {
+        startup_cost: costs.indexStartupCost,
+        total_cost: costs.indexTotalCost,
+        selectivity: costs.indexSelectivity,
+        correlation: costs.indexCorrelation,
+        pages: costs.numIndexPages,
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambuild<T: IndexAccessMethod>(
+    heap_relation: pg_sys::Relation,
+    index_relation: pg_sys::Relation,
+    index_info: *mut pg_sys::IndexInfo,
+) -> *mut pg_sys::IndexBuildResult {
+    let heap = PgRelation::from_pg(heap_relation);
+    let index = PgRelation::from_pg(index_relation);
+    let stats = T::build(&heap, &index, index_info);
+
+    let mut result = PgBox::<pg_sys::IndexBuildResult>::alloc0();
+    result.heap_tuples = stats.heap_tuples;
+    result.index_tuples = stats.index_tuples;
+    result.into_pg()
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambuildempty<T: IndexAccessMethod>(index_relation: pg_sys::Relation) {
+    T::build_empty(&PgRelation::from_pg(index_relation));
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn aminsert<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+    heap_relation: pg_sys::Relation,
+    check_unique: pg_sys::IndexUniqueCheck,
+    _index_info: *mut pg_sys::IndexInfo,
+) -> bool {
+    aminsert_internal::<T>(index_relation, values, isnull, heap_tid, heap_relation, check_unique)
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16"))]
+#[pg_guard]
+unsafe extern "C" fn aminsert<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+    heap_relation: pg_sys::Relation,
+    check_unique: pg_sys::IndexUniqueCheck,
+    _index_unchanged: bool,
+    _index_info: *mut pg_sys::IndexInfo,
+) -> bool {
+    aminsert_internal::<T>(index_relation, values, isnull, heap_tid, heap_relation, check_unique)
+}
+
+unsafe fn aminsert_internal<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+    heap_relation: pg_sys::Relation,
+    check_unique: pg_sys::IndexUniqueCheck,
+) -> bool {
+    let index = PgRelation::from_pg(index_relation);
+    let heap = PgRelation::from_pg(heap_relation);
+    let natts = index.tuple_desc().len();
+    let values = std::slice::from_raw_parts(values, natts);
+    let isnull = std::slice::from_raw_parts(isnull, natts);
+    T::insert(&index, values, isnull, *heap_tid, &heap, check_unique)
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambulkdelete<T: IndexAccessMethod>(
+    info: *mut pg_sys::IndexVacuumInfo,
+    stats: *mut pg_sys::IndexBulkDeleteResult,
+    callback: pg_sys::IndexBulkDeleteCallback,
+    callback_state: *mut std::os::raw::c_void,
+) -> *mut pg_sys::IndexBulkDeleteResult {
+    let stats = if stats.is_null() {
+        PgBox::<pg_sys::IndexBulkDeleteResult>::alloc0().into_pg()
+    } else {
+        stats
+    };
+    let callback = BulkDeleteCallback { callback, state: callback_state };
+    T::bulk_delete(&*info, &mut *stats, &callback);
+    stats
+}
+
+#[pg_guard]
+unsafe extern "C" fn amvacuumcleanup<T: IndexAccessMethod>(
+    info: *mut pg_sys::IndexVacuumInfo,
+    stats: *mut pg_sys::IndexBulkDeleteResult,
+) -> *mut pg_sys::IndexBulkDeleteResult {
+    if (*info).analyze_only {
+        return stats;
+    }
+
+    let stats = if stats.is_null() {
+        PgBox::<pg_sys::IndexBulkDeleteResult>::alloc0().into_pg()
+    } else {
+        stats
+    };
+    T::vacuum_cleanup(&*info, &mut *stats);
+    stats
+}
+
+#[pg_guard]
+unsafe extern "C" fn amcostestimate<T: IndexAccessMethod>(
+    root: *mut pg_sys::PlannerInfo,
+    path: *mut pg_sys::IndexPath,
+    loop_count: f64,
+    index_startup_cost: *mut pg_sys::Cost,
+    index_total_cost: *mut pg_sys::Cost,
+    index_selectivity: *mut pg_sys::Selectivity,
+    index_correlation: *mut f64,
+    index_pages: *mut f64,
+) {
+    let estimate = T::cost_estimate(root, path, loop_count);
+    *index_startup_cost = estimate.startup_cost;
+    *index_total_cost = estimate.total_cost;
+    *index_selectivity = estimate.selectivity;
+    *index_correlation = estimate.correlation;
+    *index_pages = estimate.pages;
+}
+
+#[pg_guard]
+unsafe extern "C" fn amoptions<T: IndexAccessMethod>(
+    reloptions: pg_sys::Datum,
+    validate: bool,
+) -> *mut pg_sys::bytea {
+    T::options(reloptions, validate)
+}
+
+#[pg_guard]
+unsafe extern "C" fn amvalidate<T: IndexAccessMethod>(opclassoid: pg_sys::Oid) -> bool {
+    T::validate(opclassoid)
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambeginscan<T: IndexAccessMethod>(
+    index_relation: pg_sys::Relation,
+    nkeys: i32,
+    norderbys: i32,
+) -> pg_sys::IndexScanDesc {
+    let scan = pg_sys::RelationGetIndexScan(index_relation, nkeys, norderbys);
+    let state = T::begin_scan(&PgRelation::from_pg(index_relation), nkeys, norderbys);
+    // the memory context drops the state if the scan is aborted by an ERROR, otherwise
+    // amendscan() drops it and leaves `None` behind
+    (*scan).opaque =
+        PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(Some(state)).cast();
+    scan
+}
+
+#[pg_guard]
+unsafe extern "C" fn amrescan<T: IndexAccessMethod>(
+    scan: pg_sys::IndexScanDesc,
+    keys: pg_sys::ScanKey,
+    nkeys: i32,
+    orderbys: pg_sys::ScanKey,
+    norderbys: i32,
+) {
+    // remember the new keys in the scan descriptor, like every built-in access method does
+    if !keys.is_null() && (*scan).numberOfKeys > 0 {
+        std::ptr::copy(keys, (*scan).keyData, nkeys as usize);
+    }
+    if !orderbys.is_null() && (*scan).numberOfOrderBys > 0 {
+        std::ptr::copy(orderbys, (*scan).orderByData, norderbys as usize);
+    }
+
+    let keys = scan_keys((*scan).keyData, (*scan).numberOfKeys);
+    let orderbys = scan_keys((*scan).orderByData, (*scan).numberOfOrderBys);
+    scan_state::<T>(scan).rescan(keys, orderbys);
+}
+
+#[pg_guard]
+unsafe extern "C" fn amgettuple<T: IndexAccessMethod>(
+    scan: pg_sys::IndexScanDesc,
+    direction: pg_sys::ScanDirection,
+) -> bool {
+    match scan_state::<T>(scan).get_tuple(direction) {
+        Some(item) => {
+            #[cfg(feature = "pg11")]
+            {
+                (*scan).xs_ctup.t_self = item.heap_tid;
+            }
+            #[cfg(not(feature = "pg11"))]
+            {
+                (*scan).xs_heaptid = item.heap_tid;
+            }
+            (*scan).xs_recheck = item.recheck;
+            true
+        }
+        None => false,
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn amgetbitmap<T: IndexAccessMethod>(
+    scan: pg_sys::IndexScanDesc,
+    tbm: *mut pg_sys::TIDBitmap,
+) -> i64 {
+    scan_state::<T>(scan).get_bitmap(&mut TidBitmap(tbm))
+}
+
+#[pg_guard]
+unsafe extern "C" fn amendscan<T: IndexAccessMethod>(scan: pg_sys::IndexScanDesc) {
+    if let Some(mut state) = (*scan).opaque.cast::<Option<T>>().as_mut().and_then(Option::take) {
+        state.end_scan();
+    }
+    (*scan).opaque = std::ptr::null_mut();
+}
+
+#[inline]
+unsafe fn scan_state<'a, T>(scan: pg_sys::IndexScanDesc) -> &'a mut T {
+    (*scan)
+        .opaque
+        .cast::<Option<T>>()
+        .as_mut()
+        .and_then(Option::as_mut)
+        .expect("index scan was not started")
+}
+
+#[inline]
+unsafe fn scan_keys<'a>(keys: pg_sys::ScanKey, nkeys: i32) -> &'a [pg_sys::ScanKeyData] {
+    if keys.is_null() || nkeys <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(keys, nkeys as usize)
+    }
+}
@@ -23,6 +23,10 @@ use pgrx_sql_entity_graph::metadata::{
 /// iterator *can* borrow from its environment, following Rust's normal borrowing rules.  If no
 /// borrowing is necessary, the `'static` lifetime should be used.
 ///
+/// Postgres normally calls the function once per row, pulling one item from the iterator each
+/// time.  With `#[pg_extern(materialize)]`, the iterator is instead drained into a tuplestore
+/// during a single call, which suits large result sets.
+///
 /// # Examples
 ///
 /// This example simply returns a set of integers in the range `1..=5`.
@@ -94,6 +98,8 @@ where
 /// iterator *can* borrow from its environment, following Rust's normal borrowing rules.  If no
 /// borrowing is necessary, the `'static` lifetime should be used.
 ///
+/// As with [`SetOfIterator`], `#[pg_extern(materialize)]` produces every row in a single call.
+///
 /// # Examples
 ///
 /// This example returns a table of employee information.
@@ -42,15 +42,21 @@ pub mod array;
 pub mod atomics;
 pub mod bgworkers;
 pub mod callbacks;
+#[cfg(feature = "cshim")]
+pub mod custom_scan;
 pub mod datum;
+pub mod dsm;
 pub mod enum_helper;
 pub mod fcinfo;
+#[cfg(feature = "cshim")]
+pub mod fdw;
 pub mod ffi;
 pub mod guc;
 pub mod heap_tuple;
 #[cfg(feature = "cshim")]
 pub mod hooks;
 pub mod htup;
+pub mod index_am;
 pub mod inoutfuncs;
 pub mod itemptr;
 pub mod iter;
@@ -62,6 +68,10 @@ pub mod misc;
 #[cfg(feature = "cshim")]
 pub mod namespace;
 pub mod nodes;
+pub mod notify;
+#[cfg(feature = "cshim")]
+pub mod output_plugin;
+pub mod parallel;
 pub mod pgbox;
 pub mod rel;
 pub mod shmem;
@@ -69,10 +79,25 @@ pub mod spi;
 #[cfg(feature = "cshim")]
 pub mod spinlock;
 pub mod srf;
+pub mod stats;
 pub mod stringinfo;
+#[cfg(all(
+    feature = "cshim",
+    any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16")
+))]
+pub mod support;
+#[cfg(any(
+    feature = "pg12",
+    feature = "pg13",
+    feature = "pg14",
+    feature = "pg15",
+    feature = "pg16"
+))]
+pub mod table_am;
 pub mod trigger_support;
 pub mod tupdesc;
 pub mod varlena;
+pub mod window;
 pub mod wrappers;
 pub mod xid;
 
@@ -103,6 +128,7 @@ pub use memcxt::*;
 #[cfg(feature = "cshim")]
 pub use namespace::*;
 pub use nodes::*;
+pub use notify::notify;
 pub use pgbox::*;
 pub use rel::*;
 pub use shmem::*;
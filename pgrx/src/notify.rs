@@ -0,0 +1,159 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Sending and receiving Postgres' `LISTEN`/`NOTIFY` notifications.
+//!
+//! Any backend can send notifications with [`notify()`].  A background worker can receive them
+//! by listening on a channel with [`BackgroundWorker::listen`], and then collecting them with
+//! [`BackgroundWorker::notifications`] whenever its latch is set:
+//!
+//! ```rust,no_run
+//! use pgrx::bgworkers::BackgroundWorker;
+//! use std::time::Duration;
+//!
+//! BackgroundWorker::listen("jobs");
+//! while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {
+//!     for notification in BackgroundWorker::notifications() {
+//!         pgrx::log!("job {} from {}", notification.payload, notification.sender_pid);
+//!     }
+//! }
+//! ```
+//!
+//! [`BackgroundWorker::listen`]: crate::bgworkers::BackgroundWorker::listen
+//! [`BackgroundWorker::notifications`]: crate::bgworkers::BackgroundWorker::notifications
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::{pg_guard, pg_sys, PgTryBuilder};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+
+/// A notification sent on a channel with `NOTIFY`, `pg_notify()` or [`notify()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub channel: String,
+    pub payload: String,
+    /// The process ID of the backend that sent this notification
+    pub sender_pid: i32,
+}
+
+/// Sends `payload` to every backend listening on `channel`, as `pg_notify()` does.
+///
+/// Like `NOTIFY`, this is part of the current transaction: the notification is only sent if the
+/// transaction commits.
+pub fn notify(channel: &str, payload: &str) {
+    let channel = CString::new(channel).expect("channel names cannot contain NUL bytes");
+    let payload = CString::new(payload).expect("payloads cannot contain NUL bytes");
+    unsafe { pg_sys::Async_Notify(channel.as_ptr(), payload.as_ptr()) }
+}
+
+pub(crate) fn listen(channel: &str) {
+    let channel = CString::new(channel).expect("channel names cannot contain NUL bytes");
+    unsafe { pg_sys::Async_Listen(channel.as_ptr()) }
+}
+
+pub(crate) fn unlisten(channel: &str) {
+    let channel = CString::new(channel).expect("channel names cannot contain NUL bytes");
+    unsafe { pg_sys::Async_Unlisten(channel.as_ptr()) }
+}
+
+thread_local! {
+    static RECEIVED: RefCell<Vec<Notification>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Reads the notifications that Postgres has signalled this backend about.
+///
+/// Postgres only knows how to send notifications to a client, so we briefly stand in for one
+/// and collect what it sends.  The real client output is put back afterwards, even if Postgres
+/// raises an error.  Nothing can be read while in a transaction.
+pub(crate) fn receive() -> Vec<Notification> {
+    unsafe {
+        if std::ptr::read_volatile(std::ptr::addr_of!(pg_sys::notifyInterruptPending)) == 0 {
+            return Vec::new();
+        }
+
+        // before pg14 there are also the COPY methods, which Postgres won't use here
+        #[allow(clippy::needless_update)]
+        let methods = pg_sys::PQcommMethods {
+            comm_reset: Some(comm_reset),
+            flush: Some(flush),
+            flush_if_writable: Some(flush),
+            is_send_pending: Some(is_send_pending),
+            putmessage: Some(putmessage),
+            putmessage_noblock: Some(putmessage_noblock),
+            ..Default::default()
+        };
+
+        let dest = pg_sys::whereToSendOutput;
+        let comm_methods = pg_sys::PqCommMethods;
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+        let protocol = pg_sys::FrontendProtocol;
+
+        pg_sys::whereToSendOutput = pg_sys::CommandDest_DestRemote;
+        pg_sys::PqCommMethods = std::ptr::addr_of!(methods) as *mut _;
+        // notification payloads are only sent to clients speaking protocol 3.0
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+        {
+            pg_sys::FrontendProtocol = 3 << 16;
+        }
+
+        PgTryBuilder::new(|| {
+            #[cfg(any(feature = "pg15", feature = "pg16"))]
+            pg_sys::ProcessNotifyInterrupt(true);
+            #[cfg(not(any(feature = "pg15", feature = "pg16")))]
+            pg_sys::ProcessNotifyInterrupt();
+        })
+        .finally(|| {
+            pg_sys::whereToSendOutput = dest;
+            pg_sys::PqCommMethods = comm_methods;
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+            {
+                pg_sys::FrontendProtocol = protocol;
+            }
+        })
+        .execute();
+    }
+    RECEIVED.with(|received| received.take())
+}
+
+#[pg_guard]
+unsafe extern "C" fn comm_reset() {}
+
+#[pg_guard]
+unsafe extern "C" fn flush() -> c_int {
+    0
+}
+
+#[pg_guard]
+unsafe extern "C" fn is_send_pending() -> bool {
+    false
+}
+
+#[pg_guard]
+unsafe extern "C" fn putmessage(msgtype: c_char, s: *const c_char, len: usize) -> c_int {
+    // anything but a NotificationResponse, such as a NOTICE, isn't for us
+    if msgtype as u8 != b'A' {
+        return 0;
+    }
+
+    let message = std::slice::from_raw_parts(s.cast::<u8>(), len);
+    let (sender_pid, strings) = message.split_at(4);
+    let mut strings = strings.split(|b| *b == 0).map(|s| String::from_utf8_lossy(s).into_owned());
+    let notification = Notification {
+        sender_pid: i32::from_be_bytes(sender_pid.try_into().unwrap()),
+        channel: strings.next().unwrap_or_default(),
+        payload: strings.next().unwrap_or_default(),
+    };
+    RECEIVED.with(|received| received.borrow_mut().push(notification));
+    0
+}
+
+#[pg_guard]
+unsafe extern "C" fn putmessage_noblock(msgtype: c_char, s: *const c_char, len: usize) {
+    putmessage(msgtype, s, len);
+}
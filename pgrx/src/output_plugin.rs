@@ -0,0 +1,389 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Safe support for writing [logical decoding output plugins](https://www.postgresql.org/docs/current/logicaldecoding-output-plugin.html)
+//!
+//! Implement [`OutputPlugin`] for a type and name it with [`pg_output_plugin!`](crate::pg_output_plugin),
+//! which generates the `_PG_output_plugin_init` function Postgres looks for.  The extension's
+//! shared library name is then usable as a plugin name, e.g. in
+//! `pg_create_logical_replication_slot('my_slot', 'my_extension')`.
+//!
+//! A new instance of the implementing type is created by [`OutputPlugin::startup`] for each
+//! decoding session, and dropped when the session ends.
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::output_plugin::{Change, DecodingContext, ReorderBufferTxn};
+//! use pgrx::PgRelation;
+//!
+//! struct ChangeCounter(usize);
+//!
+//! impl OutputPlugin for ChangeCounter {
+//!     fn startup(_ctx: &mut DecodingContext, _options: &[(String, Option<String>)], _is_init: bool) -> Self {
+//!         ChangeCounter(0)
+//!     }
+//!
+//!     fn change(&mut self, _ctx: &mut DecodingContext, _txn: &ReorderBufferTxn, _relation: &PgRelation, _change: Change) {
+//!         self.0 += 1;
+//!     }
+//!
+//!     fn commit(&mut self, ctx: &mut DecodingContext, txn: &ReorderBufferTxn, _commit_lsn: pg_sys::XLogRecPtr) {
+//!         ctx.write(format!("{} changed {} rows", txn.xid(), std::mem::take(&mut self.0)));
+//!     }
+//! }
+//!
+//! pgrx::pg_output_plugin!(ChangeCounter);
+//! ```
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use crate::{pg_sys, AllocatedByPostgres, PgList, PgMemoryContexts, PgRelation};
+use std::ffi::CStr;
+
+/// A row change decoded from the WAL, as seen by [`OutputPlugin::change`].
+///
+/// A tuple is `None` when the WAL doesn't carry it, such as the old version of a row whose
+/// table has `REPLICA IDENTITY NOTHING`.
+pub enum Change<'a> {
+    Insert {
+        new: Option<PgHeapTuple<'a, AllocatedByPostgres>>,
+    },
+    Update {
+        old: Option<PgHeapTuple<'a, AllocatedByPostgres>>,
+        new: Option<PgHeapTuple<'a, AllocatedByPostgres>>,
+    },
+    Delete {
+        old: Option<PgHeapTuple<'a, AllocatedByPostgres>>,
+    },
+}
+
+/// The decoding session an [`OutputPlugin`] callback is running in
+pub struct DecodingContext {
+    ctx: *mut pg_sys::LogicalDecodingContext,
+}
+
+impl DecodingContext {
+    /// The underlying [`pg_sys::LogicalDecodingContext`]
+    pub fn as_ptr(&self) -> *mut pg_sys::LogicalDecodingContext {
+        self.ctx
+    }
+
+    /// The memory context which lives as long as the decoding session
+    pub fn memory_context(&self) -> PgMemoryContexts {
+        unsafe { PgMemoryContexts::For((*self.ctx).context) }
+    }
+
+    /// Send `data` to the consumer as a single row of output
+    pub fn write(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        unsafe {
+            // SAFETY:  Postgres only calls the output plugin while it's able to accept writes,
+            // and `out` is the buffer it gave us to fill between preparing and writing
+            pg_sys::OutputPluginPrepareWrite(self.ctx, true);
+            pg_sys::appendBinaryStringInfo(
+                (*self.ctx).out,
+                data.as_ptr().cast(),
+                data.len().try_into().expect("output plugin write is too large"),
+            );
+            pg_sys::OutputPluginWrite(self.ctx, true);
+        }
+    }
+}
+
+/// A decoded transaction
+pub struct ReorderBufferTxn(*mut pg_sys::ReorderBufferTXN);
+
+impl ReorderBufferTxn {
+    /// The underlying [`pg_sys::ReorderBufferTXN`]
+    pub fn as_ptr(&self) -> *mut pg_sys::ReorderBufferTXN {
+        self.0
+    }
+
+    /// The transaction's id
+    pub fn xid(&self) -> pg_sys::TransactionId {
+        unsafe { (*self.0).xid }
+    }
+
+    /// The location of the transaction's commit record
+    pub fn final_lsn(&self) -> pg_sys::XLogRecPtr {
+        unsafe { (*self.0).final_lsn }
+    }
+
+    /// The location just past the transaction's commit record
+    pub fn end_lsn(&self) -> pg_sys::XLogRecPtr {
+        unsafe { (*self.0).end_lsn }
+    }
+
+    /// When the transaction committed
+    pub fn commit_time(&self) -> Option<TimestampWithTimeZone> {
+        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        let commit_time = unsafe { (*self.0).commit_time };
+        #[cfg(any(feature = "pg15", feature = "pg16"))]
+        let commit_time = unsafe { (*self.0).xact_time.commit_time };
+
+        TimestampWithTimeZone::try_from(commit_time).ok()
+    }
+}
+
+/// A Postgres logical decoding output plugin.
+///
+/// Use [`pg_output_plugin!`](crate::pg_output_plugin) to register the implementing type with
+/// Postgres.  Every callback other than [`OutputPlugin::startup`] and [`OutputPlugin::change`]
+/// is optional.
+pub trait OutputPlugin: Sized {
+    /// Does the plugin produce binary output?  Binary output can't be consumed through the
+    /// SQL-level `pg_logical_slot_get_changes()` function.
+    const BINARY_OUTPUT: bool = false;
+
+    /// Start a decoding session, given the options the consumer passed to the plugin.
+    ///
+    /// `is_init` is true when the session is only being started to create a replication slot.
+    fn startup(
+        ctx: &mut DecodingContext,
+        options: &[(String, Option<String>)],
+        is_init: bool,
+    ) -> Self;
+
+    /// A transaction is about to be decoded
+    fn begin(&mut self, _ctx: &mut DecodingContext, _txn: &ReorderBufferTxn) {}
+
+    /// A row of `relation` was inserted, updated, or deleted by `txn`
+    fn change(
+        &mut self,
+        ctx: &mut DecodingContext,
+        txn: &ReorderBufferTxn,
+        relation: &PgRelation,
+        change: Change,
+    );
+
+    /// `txn` truncated `relations`
+    fn truncate(
+        &mut self,
+        _ctx: &mut DecodingContext,
+        _txn: &ReorderBufferTxn,
+        _relations: &[PgRelation],
+        _cascade: bool,
+        _restart_seqs: bool,
+    ) {
+    }
+
+    /// A message was written with `pg_logical_emit_message()`.
+    ///
+    /// `txn` is `None` for non-transactional messages, which are decoded as soon as they're
+    /// written.
+    fn message(
+        &mut self,
+        _ctx: &mut DecodingContext,
+        _txn: Option<&ReorderBufferTxn>,
+        _message_lsn: pg_sys::XLogRecPtr,
+        _transactional: bool,
+        _prefix: &str,
+        _message: &[u8],
+    ) {
+    }
+
+    /// All of `txn` has been decoded
+    fn commit(
+        &mut self,
+        _ctx: &mut DecodingContext,
+        _txn: &ReorderBufferTxn,
+        _commit_lsn: pg_sys::XLogRecPtr,
+    ) {
+    }
+
+    /// The decoding session is ending.  The plugin is dropped afterwards.
+    fn shutdown(&mut self, _ctx: &mut DecodingContext) {}
+}
+
+/// Generate the `_PG_output_plugin_init` function for the [`OutputPlugin`] `$plugin`.
+///
+/// Postgres only looks for one output plugin per shared library, so this can only be used once
+/// per extension.
+#[macro_export]
+macro_rules! pg_output_plugin {
+    ($plugin:ty) => {
+        #[no_mangle]
+        #[doc(hidden)]
+        pub unsafe extern "C" fn _PG_output_plugin_init(
+            cb: *mut $crate::pg_sys::OutputPluginCallbacks,
+        ) {
+            $crate::output_plugin::output_plugin_init::<$plugin>(cb)
+        }
+    };
+}
+
+/// Fill in `cb` with the callbacks for the [`OutputPlugin`] `T`.
+///
+/// This is what the `_PG_output_plugin_init` function generated by
+/// [`pg_output_plugin!`](crate::pg_output_plugin) does.
+///
+/// # Safety
+///
+/// `cb` must be the valid pointer Postgres provides to `_PG_output_plugin_init`
+pub unsafe fn output_plugin_init<T: OutputPlugin>(cb: *mut pg_sys::OutputPluginCallbacks) {
+    let cb = cb.as_mut().expect("OutputPluginCallbacks is null");
+    cb.startup_cb = Some(startup_cb::<T>);
+    cb.begin_cb = Some(begin_cb::<T>);
+    cb.change_cb = Some(change_cb::<T>);
+    cb.truncate_cb = Some(truncate_cb::<T>);
+    cb.message_cb = Some(message_cb::<T>);
+    cb.commit_cb = Some(commit_cb::<T>);
+    cb.shutdown_cb = Some(shutdown_cb::<T>);
+}
+
+/// The plugin and context for a callback.
+///
+/// # Safety
+///
+/// `ctx` must be a valid pointer whose plugin state was created by [`startup_cb`] for `T`
+unsafe fn plugin_for<'a, T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+) -> (&'a mut T, DecodingContext) {
+    let plugin = (*ctx).output_plugin_private.cast::<T>();
+    (plugin.as_mut().expect("output plugin was not started"), DecodingContext { ctx })
+}
+
+/// Build a [`PgHeapTuple`] from one of the tuples of a decoded change
+unsafe fn change_tuple<'a>(
+    relation: &'a PgRelation,
+    tuple: *mut pg_sys::ReorderBufferTupleBuf,
+) -> Option<PgHeapTuple<'a, AllocatedByPostgres>> {
+    tuple
+        .as_mut()
+        .map(|tuple| PgHeapTuple::from_heap_tuple(relation.tuple_desc(), &mut tuple.tuple))
+}
+
+#[pg_guard]
+unsafe extern "C" fn startup_cb<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    options: *mut pg_sys::OutputPluginOptions,
+    is_init: bool,
+) {
+    (*options).output_type = if T::BINARY_OUTPUT {
+        pg_sys::OutputPluginOutputType_OUTPUT_PLUGIN_BINARY_OUTPUT
+    } else {
+        pg_sys::OutputPluginOutputType_OUTPUT_PLUGIN_TEXTUAL_OUTPUT
+    };
+
+    let plugin_options = PgList::<pg_sys::DefElem>::from_pg((*ctx).output_plugin_options)
+        .iter_ptr()
+        .map(|def| {
+            let name = CStr::from_ptr((*def).defname).to_string_lossy().into_owned();
+            let value = (!(*def).arg.is_null())
+                .then(|| CStr::from_ptr(pg_sys::defGetString(def)).to_string_lossy().into_owned());
+            (name, value)
+        })
+        .collect::<Vec<_>>();
+
+    let plugin = T::startup(&mut DecodingContext { ctx }, &plugin_options, is_init);
+
+    // the plugin is dropped when Postgres deletes the decoding context's memory context, which
+    // it does after calling `shutdown_cb`, or when the session is aborted
+    (*ctx).output_plugin_private =
+        PgMemoryContexts::For((*ctx).context).leak_and_drop_on_delete(plugin).cast();
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_cb<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+) {
+    let (plugin, mut ctx) = plugin_for::<T>(ctx);
+    plugin.begin(&mut ctx, &ReorderBufferTxn(txn));
+}
+
+#[pg_guard]
+unsafe extern "C" fn change_cb<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+    relation: pg_sys::Relation,
+    change: *mut pg_sys::ReorderBufferChange,
+) {
+    let (plugin, mut ctx) = plugin_for::<T>(ctx);
+    let relation = PgRelation::from_pg(relation);
+    let tp = (*change).data.tp;
+    let change = match (*change).action {
+        pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_INSERT => {
+            Change::Insert { new: change_tuple(&relation, tp.newtuple) }
+        }
+        pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_UPDATE => Change::Update {
+            old: change_tuple(&relation, tp.oldtuple),
+            new: change_tuple(&relation, tp.newtuple),
+        },
+        pg_sys::ReorderBufferChangeType_REORDER_BUFFER_CHANGE_DELETE => {
+            Change::Delete { old: change_tuple(&relation, tp.oldtuple) }
+        }
+        // Postgres only hands output plugins the above kinds of changes
+        other => panic!("unexpected ReorderBufferChangeType: {other}"),
+    };
+    plugin.change(&mut ctx, &ReorderBufferTxn(txn), &relation, change);
+}
+
+#[pg_guard]
+unsafe extern "C" fn truncate_cb<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+    nrelations: std::os::raw::c_int,
+    relations: *mut pg_sys::Relation,
+    change: *mut pg_sys::ReorderBufferChange,
+) {
+    let (plugin, mut ctx) = plugin_for::<T>(ctx);
+    let relations = std::slice::from_raw_parts(relations, nrelations as usize)
+        .iter()
+        .map(|relation| PgRelation::from_pg(*relation))
+        .collect::<Vec<_>>();
+    let truncate = (*change).data.truncate;
+    plugin.truncate(
+        &mut ctx,
+        &ReorderBufferTxn(txn),
+        &relations,
+        truncate.cascade,
+        truncate.restart_seqs,
+    );
+}
+
+#[pg_guard]
+unsafe extern "C" fn message_cb<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+    message_lsn: pg_sys::XLogRecPtr,
+    transactional: bool,
+    prefix: *const std::os::raw::c_char,
+    message_size: pg_sys::Size,
+    message: *const std::os::raw::c_char,
+) {
+    let (plugin, mut ctx) = plugin_for::<T>(ctx);
+    let txn = (!txn.is_null()).then(|| ReorderBufferTxn(txn));
+    let prefix = CStr::from_ptr(prefix).to_string_lossy();
+    let message = if message_size == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(message.cast::<u8>(), message_size)
+    };
+    plugin.message(&mut ctx, txn.as_ref(), message_lsn, transactional, &prefix, message);
+}
+
+#[pg_guard]
+unsafe extern "C" fn commit_cb<T: OutputPlugin>(
+    ctx: *mut pg_sys::LogicalDecodingContext,
+    txn: *mut pg_sys::ReorderBufferTXN,
+    commit_lsn: pg_sys::XLogRecPtr,
+) {
+    let (plugin, mut ctx) = plugin_for::<T>(ctx);
+    plugin.commit(&mut ctx, &ReorderBufferTxn(txn), commit_lsn);
+}
+
+#[pg_guard]
+unsafe extern "C" fn shutdown_cb<T: OutputPlugin>(ctx: *mut pg_sys::LogicalDecodingContext) {
+    // startup may have failed before the plugin was created
+    if (*ctx).output_plugin_private.is_null() {
+        return;
+    }
+    let (plugin, mut ctx) = plugin_for::<T>(ctx);
+    plugin.shutdown(&mut ctx);
+}
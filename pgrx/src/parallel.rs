@@ -0,0 +1,273 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Fanning work out across Postgres' parallel workers, as parallel index builds do.
+//!
+//! The backend that creates a [`ParallelContext`] is its leader.  Before launching any workers,
+//! it places the state they share in the context's dynamic shared memory segment, each value
+//! under a [`ParallelKey`].  Each worker runs an entry point exported from the extension's
+//! library, which finds that state again through [`ParallelWorker`].  Errors raised by a worker
+//! are raised again in the leader when it waits for the workers to finish.
+//!
+//! ```rust,no_run
+//! use pgrx::parallel::*;
+//! use pgrx::prelude::*;
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//!
+//! const TOTAL: ParallelKey<AtomicU64> = ParallelKey::new(1);
+//!
+//! #[pg_extern]
+//! fn count_in_parallel() -> i64 {
+//!     let mut pcxt = ParallelContextBuilder::new("my_extension", "count_worker", 4)
+//!         .shared(TOTAL, AtomicU64::new(0))
+//!         .build();
+//!     pcxt.launch();
+//!     pcxt.wait();
+//!     pcxt.shared(TOTAL).load(Ordering::SeqCst) as i64
+//! }
+//!
+//! #[pg_guard]
+//! #[no_mangle]
+//! pub extern "C" fn count_worker(_seg: *mut pg_sys::dsm_segment, toc: *mut pg_sys::shm_toc) {
+//!     let worker = unsafe { ParallelWorker::from_toc(toc) };
+//!     worker.shared(TOTAL).fetch_add(1, Ordering::SeqCst);
+//! }
+//! ```
+use crate::{pg_sys, PGRXSharedMemory};
+use std::any::TypeId;
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::CString;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// Postgres reserves the keys from here up for the state it shares with every parallel worker
+const FIRST_RESERVED_KEY: u64 = 0xFFFF_FFFF_FFFF_0000;
+
+/// Identifies a `T` in the shared memory of a [`ParallelContext`], for both its leader and its
+/// workers.  Declaring keys as constants keeps both sides agreeing on the type behind each.
+pub struct ParallelKey<T> {
+    key: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ParallelKey<T> {
+    pub const fn new(key: u64) -> Self {
+        assert!(key < FIRST_RESERVED_KEY, "Postgres reserves this parallel key");
+        ParallelKey { key, _marker: PhantomData }
+    }
+}
+
+impl<T> Clone for ParallelKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ParallelKey<T> {}
+
+/// A value to be moved into the shared memory of a [`ParallelContext`] once it exists
+struct SharedValue {
+    key: u64,
+    tag: TypeTag,
+    write: Box<dyn FnOnce(*mut u8)>,
+}
+
+/// Stored ahead of each shared value, so a [`ParallelKey`] of another type can't read it
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+struct TypeTag {
+    size: usize,
+    type_hash: u64,
+}
+
+impl TypeTag {
+    fn of<T: 'static>() -> Self {
+        // the leader and its workers run the same library, so they hash the same `TypeId`s alike
+        let mut hasher = DefaultHasher::new();
+        TypeId::of::<T>().hash(&mut hasher);
+        TypeTag { size: std::mem::size_of::<T>(), type_hash: hasher.finish() }
+    }
+}
+
+/// Where the value starts, past its [`TypeTag`]
+fn value_offset() -> usize {
+    buffer_align(std::mem::size_of::<TypeTag>())
+}
+
+/// Describes a [`ParallelContext`]: the worker entry point it launches, how many of them, and
+/// the state they share.
+pub struct ParallelContextBuilder {
+    library: CString,
+    function: CString,
+    nworkers: i32,
+    shared: Vec<SharedValue>,
+}
+
+impl ParallelContextBuilder {
+    /// `function` is the name of a `#[pg_guard]`, `#[no_mangle]` entry point in `library` with
+    /// the signature `extern "C" fn(*mut pg_sys::dsm_segment, *mut pg_sys::shm_toc)`
+    pub fn new(library: &str, function: &str, nworkers: usize) -> Self {
+        ParallelContextBuilder {
+            library: CString::new(library).expect("library names cannot contain NUL bytes"),
+            function: CString::new(function).expect("function names cannot contain NUL bytes"),
+            nworkers: nworkers.try_into().expect("too many parallel workers"),
+            shared: Vec::new(),
+        }
+    }
+
+    /// Places `value` in shared memory under `key`
+    pub fn shared<T: PGRXSharedMemory + Sync + 'static>(
+        mut self,
+        key: ParallelKey<T>,
+        value: T,
+    ) -> Self {
+        assert!(
+            std::mem::align_of::<T>() <= pg_sys::ALIGNOF_BUFFER as usize,
+            "parallel shared state cannot be aligned to more than {} bytes",
+            pg_sys::ALIGNOF_BUFFER
+        );
+        self.shared.push(SharedValue {
+            key: key.key,
+            tag: TypeTag::of::<T>(),
+            write: Box::new(move |ptr| unsafe { ptr.cast::<T>().write(value) }),
+        });
+        self
+    }
+
+    /// Enters parallel mode and sets up the context's shared memory, ready for
+    /// [`ParallelContext::launch`]
+    pub fn build(self) -> ParallelContext {
+        unsafe {
+            pg_sys::EnterParallelMode();
+
+            // serializable transactions can't be run in parallel before Postgres 12
+            #[cfg(feature = "pg11")]
+            let pcxt = pg_sys::CreateParallelContext(
+                self.library.as_ptr(),
+                self.function.as_ptr(),
+                self.nworkers,
+                false,
+            );
+            #[cfg(not(feature = "pg11"))]
+            let pcxt = pg_sys::CreateParallelContext(
+                self.library.as_ptr(),
+                self.function.as_ptr(),
+                self.nworkers,
+            );
+            let pcxt = ParallelContext { pcxt };
+
+            // as shm_toc_estimate_chunk() and shm_toc_estimate_keys() would
+            let estimator = &mut (*pcxt.pcxt).estimator;
+            for value in &self.shared {
+                estimator.space_for_chunks += value_offset() + buffer_align(value.tag.size);
+            }
+            estimator.number_of_keys += self.shared.len();
+
+            pg_sys::InitializeParallelDSM(pcxt.pcxt);
+
+            let toc = (*pcxt.pcxt).toc;
+            for value in self.shared {
+                let ptr = pg_sys::shm_toc_allocate(toc, value_offset() + value.tag.size);
+                ptr.cast::<TypeTag>().write(value.tag);
+                (value.write)(ptr.cast::<u8>().add(value_offset()));
+                pg_sys::shm_toc_insert(toc, value.key, ptr);
+            }
+            pcxt
+        }
+    }
+}
+
+fn buffer_align(size: usize) -> usize {
+    let align = pg_sys::ALIGNOF_BUFFER as usize;
+    (size + align - 1) & !(align - 1)
+}
+
+/// Parallel workers sharing state with this backend, their leader.  See the
+/// [module documentation](self).
+///
+/// Dropping the context waits for any workers still running, and leaves parallel mode.
+pub struct ParallelContext {
+    pcxt: *mut pg_sys::ParallelContext,
+}
+
+impl ParallelContext {
+    /// Launches the workers, returning how many could be.  That may be fewer than were asked
+    /// for, or even none, when Postgres is short of background worker slots, so the leader must
+    /// be prepared to do the work itself.
+    pub fn launch(&mut self) -> usize {
+        unsafe {
+            pg_sys::LaunchParallelWorkers(self.pcxt);
+            (*self.pcxt).nworkers_launched as usize
+        }
+    }
+
+    /// Waits for every launched worker to finish, raising the first error any of them raised
+    pub fn wait(&mut self) {
+        unsafe { pg_sys::WaitForParallelWorkersToFinish(self.pcxt) }
+    }
+
+    /// The value placed in shared memory under `key`
+    ///
+    /// # Panics
+    ///
+    /// If the value was placed there with a [`ParallelKey`] of a different type
+    pub fn shared<T: PGRXSharedMemory + Sync + 'static>(&self, key: ParallelKey<T>) -> &T {
+        unsafe { lookup((*self.pcxt).toc, key) }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::ParallelContext {
+        self.pcxt
+    }
+}
+
+impl Drop for ParallelContext {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::DestroyParallelContext(self.pcxt);
+            pg_sys::ExitParallelMode();
+        }
+    }
+}
+
+/// A parallel worker's view of the [`ParallelContext`] that launched it
+pub struct ParallelWorker {
+    toc: *mut pg_sys::shm_toc,
+}
+
+impl ParallelWorker {
+    /// # Safety
+    ///
+    /// `toc` must be the table of contents given to this parallel worker's entry point
+    pub unsafe fn from_toc(toc: *mut pg_sys::shm_toc) -> Self {
+        ParallelWorker { toc }
+    }
+
+    /// This worker's number, from zero up to the number of workers launched
+    pub fn number(&self) -> usize {
+        unsafe { pg_sys::ParallelWorkerNumber as usize }
+    }
+
+    /// The value the leader placed in shared memory under `key`
+    ///
+    /// # Panics
+    ///
+    /// If the value was placed there with a [`ParallelKey`] of a different type
+    pub fn shared<T: PGRXSharedMemory + Sync + 'static>(&self, key: ParallelKey<T>) -> &T {
+        unsafe { lookup(self.toc, key) }
+    }
+}
+
+unsafe fn lookup<'a, T: 'static>(toc: *mut pg_sys::shm_toc, key: ParallelKey<T>) -> &'a T {
+    // raises an error if there's no such key
+    let ptr = pg_sys::shm_toc_lookup(toc, key.key, false);
+    if ptr.cast::<TypeTag>().read() != TypeTag::of::<T>() {
+        panic!("parallel key {} holds a value of another type", key.key)
+    }
+    &*ptr.cast::<u8>().add(value_offset()).cast::<T>()
+}
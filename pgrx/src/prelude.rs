@@ -29,8 +29,8 @@ pub use crate::pgbox::{AllocatedByPostgres, AllocatedByRust, PgBox, WhoAllocated
 
 pub use crate::datum::{
     datetime_support::*, AnyNumeric, Array, ArraySliceError, Date, FromDatum, Interval, IntoDatum,
-    Numeric, PgVarlena, PostgresType, Range, RangeBound, RangeSubType, Time, TimeWithTimeZone,
-    Timestamp, TimestampWithTimeZone, VariadicArray,
+    IntoDatumDyn, Numeric, PgVarlena, PostgresType, Range, RangeBound, RangeSubType, Time,
+    TimeWithTimeZone, Timestamp, TimestampWithTimeZone, VariadicArray,
 };
 pub use crate::inoutfuncs::{InOutFuncs, JsonInOutFuncs, PgVarlenaInOutFuncs};
 
@@ -42,6 +42,41 @@ pub use crate::trigger_support::{
 // Aggregate support
 pub use crate::aggregate::{Aggregate, FinalizeModify, ParallelOption};
 
+// Custom Scan support
+#[cfg(feature = "cshim")]
+pub use crate::custom_scan::{CustomScan, CustomScanInfo};
+
+// Foreign Data Wrapper support
+#[cfg(feature = "cshim")]
+pub use crate::fdw::{FdwOptionCatalog, FdwRowId, ForeignDataWrapper, PgForeignTable};
+
+// Index Access Method support
+pub use crate::index_am::{IndexAccessMethod, IndexBuildStats, IndexScanItem};
+
+// Logical decoding output plugin support
+#[cfg(feature = "cshim")]
+pub use crate::output_plugin::OutputPlugin;
+
+// Planner support function support
+#[cfg(all(
+    feature = "cshim",
+    any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15", feature = "pg16")
+))]
+pub use crate::support::SupportRequest;
+
+// Table Access Method support
+#[cfg(any(
+    feature = "pg12",
+    feature = "pg13",
+    feature = "pg14",
+    feature = "pg15",
+    feature = "pg16"
+))]
+pub use crate::table_am::{TableAccessMethod, TableScan, TupleSlot};
+
+// Window function support
+pub use crate::window::{WindowContext, WindowSeek};
+
 pub use crate::pg_sys::oids::PgOid;
 pub use crate::pg_sys::pg_try::PgTryBuilder;
 pub use crate::pg_sys::utils::name_data_to_str;
@@ -49,7 +84,7 @@ pub use crate::pg_sys::PgBuiltInOids;
 
 // It's a database, gotta query it somehow.
 pub use crate::spi;
-pub use crate::spi::Spi;
+pub use crate::spi::{CallContext, FromSpiRow, Spi};
 
 // Logging and Error support
 pub use crate::pg_sys::elog::PgLogLevel;
@@ -229,6 +229,17 @@ unsafe impl PGRXSharedMemory for isize {}
 unsafe impl PGRXSharedMemory for f32 {}
 unsafe impl PGRXSharedMemory for f64 {}
 unsafe impl<T> PGRXSharedMemory for [T] where T: PGRXSharedMemory + Default {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicBool {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicI8 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicI16 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicI32 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicI64 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicIsize {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicU8 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicU16 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicU32 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicU64 {}
+unsafe impl PGRXSharedMemory for std::sync::atomic::AtomicUsize {}
 unsafe impl<A, B> PGRXSharedMemory for (A, B)
 where
     A: PGRXSharedMemory + Default,
@@ -9,9 +9,14 @@
 //LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 //! Safe access to Postgres' *Server Programming Interface* (SPI).
 
-use crate::{pg_sys, FromDatum, IntoDatum, Json, PgMemoryContexts, PgOid, TryFromDatumError};
+use crate::{
+    pg_sys, FromDatum, IntoDatum, IntoDatumDyn, Json, PgMemoryContexts, PgOid, TryFromDatumError,
+};
 use core::fmt::Formatter;
 use pgrx_pg_sys::panic::ErrorReportable;
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
 use std::ffi::{CStr, CString};
 use std::fmt::Debug;
 use std::marker::PhantomData;
@@ -169,6 +174,14 @@ pub enum Error {
     #[error("Argument count mismatch (expected {expected}, got {got})")]
     PreparedStatementArgumentMismatch { expected: usize, got: usize },
 
+    /// An argument's type doesn't match the prepared statement's parameter type
+    #[error("Argument ${position} type mismatch (expected {expected}, got {got})")]
+    PreparedStatementArgumentTypeMismatch {
+        position: usize,
+        expected: pg_sys::Oid,
+        got: pg_sys::Oid,
+    },
+
     /// [`SpiTupleTable`] is positioned outside its bounds
     #[error("SpiTupleTable positioned before the start or after the end")]
     InvalidPosition,
@@ -180,6 +193,18 @@ pub enum Error {
     /// The [`pg_sys::SPI_tuptable`] is null
     #[error("The active `SPI_tuptable` is NULL")]
     NoTupleTable,
+
+    /// A column read by name isn't in the result set
+    #[error("Column `{0}` not found")]
+    ColumnNotFound(String),
+
+    /// A column's type isn't compatible with the Rust type it was read as
+    #[error("Column `{column}` of type {column_type} cannot be read as the Rust type {rust_type}")]
+    ColumnTypeMismatch { column: String, column_type: String, rust_type: &'static str },
+
+    /// A column read as a non-`Option` Rust type was NULL
+    #[error("Column `{0}` is NULL")]
+    NullColumn(String),
 }
 
 pub struct Spi;
@@ -251,6 +276,12 @@ impl SpiConnection {
         Spi::check_status(unsafe { pg_sys::SPI_connect() })?;
         Ok(SpiConnection(PhantomData))
     }
+
+    /// Connect to Postgres' SPI system with `SPI_connect_ext()` options, like `SPI_OPT_NONATOMIC`
+    fn connect_ext(options: u32) -> Result<Self> {
+        Spi::check_status(unsafe { pg_sys::SPI_connect_ext(options as i32) })?;
+        Ok(SpiConnection(PhantomData))
+    }
 }
 
 impl Drop for SpiConnection {
@@ -268,44 +299,274 @@ impl SpiConnection {
     fn client(&self) -> SpiClient<'_> {
         SpiClient { __marker: PhantomData }
     }
+
+    /// Return a client that can also end the current transaction, scoped to this connection.
+    fn nonatomic_client(&self) -> NonatomicSpiClient<'_> {
+        NonatomicSpiClient { __marker: PhantomData }
+    }
+}
+
+/// How a [`#[pg_procedure]`](macro@crate::pg_procedure) was invoked.
+///
+/// Declare an argument of this type to receive it.  It isn't part of the procedure's SQL signature.
+///
+/// A procedure may only commit or roll back the transaction when it's run nonatomically, which
+/// Postgres decides: a top-level `CALL` outside of a transaction block is nonatomic, while a `CALL`
+/// inside `BEGIN ... COMMIT` or from a function is atomic.
+#[derive(Debug, Copy, Clone)]
+pub struct CallContext {
+    atomic: bool,
+}
+
+impl CallContext {
+    /// # Safety
+    ///
+    /// `fcinfo` must be a valid [`pg_sys::FunctionCallInfo`] pointer, allocated by Postgres.
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        let context = (*fcinfo).context;
+        // only `CALL` passes a `CallContext`.  Anything else has to be atomic
+        let atomic = context.is_null()
+            || !crate::is_a(context, pg_sys::NodeTag_T_CallContext)
+            || (*context.cast::<pg_sys::CallContext>()).atomic;
+        CallContext { atomic }
+    }
+
+    /// Is the procedure running atomically, without being able to commit or roll back?
+    pub fn is_atomic(&self) -> bool {
+        self.atomic
+    }
+}
+
+unsafe impl SqlTranslatable for CallContext {
+    fn argument_sql() -> std::result::Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::Skip)
+    }
+    fn return_sql() -> std::result::Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::Skip))
+    }
+}
+
+/// A connection from [`Spi::connect_nonatomic()`] that can end the current transaction.
+///
+/// Ending a transaction releases the tuple tables and cursors opened within it.  They borrow the
+/// [`SpiClient`] returned by [`NonatomicSpiClient::client()`], which borrows this, so none of them
+/// can still be alive when [`commit()`][Self::commit] or [`rollback()`][Self::rollback] is called.
+pub struct NonatomicSpiClient<'conn> {
+    __marker: PhantomData<&'conn SpiConnection>,
+}
+
+impl<'conn> NonatomicSpiClient<'conn> {
+    /// Borrow a client for running queries in the current transaction
+    pub fn client(&self) -> SpiClient<'_> {
+        SpiClient { __marker: PhantomData }
+    }
+
+    /// Commit the current transaction, and start a new one
+    ///
+    /// Raises an ERROR if the procedure is running atomically.
+    pub fn commit(&mut self) {
+        unsafe {
+            // SAFETY:  we're connected to SPI, and nothing from the transaction is still borrowed
+            pg_sys::SPI_commit();
+            // only needed before Postgres 15, where SPI_commit() didn't start the next transaction
+            // itself.  Afterwards, it's a no-op
+            pg_sys::SPI_start_transaction();
+        }
+    }
+
+    /// Roll back the current transaction, and start a new one
+    ///
+    /// Raises an ERROR if the procedure is running atomically.
+    pub fn rollback(&mut self) {
+        unsafe {
+            // SAFETY:  we're connected to SPI, and nothing from the transaction is still borrowed
+            pg_sys::SPI_rollback();
+            // only needed before Postgres 15, where SPI_rollback() didn't start the next
+            // transaction itself.  Afterwards, it's a no-op
+            pg_sys::SPI_start_transaction();
+        }
+    }
+}
+
+/// Arguments for a [`Query`]
+///
+/// Most of the time this is a tuple of [`IntoDatum`] values, such as `(42, "hello")`, whose
+/// Postgres types come from [`IntoDatum::type_oid()`].  Arguments whose count or types are only
+/// known at runtime can be passed as a `&[&dyn IntoDatumDyn]`.  `None` or `()` means no arguments.
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// # fn foo() -> spi::Result<()> {
+/// let sum = Spi::get_one_with_args::<i32>("SELECT $1 + $2", (1, 2))?;
+///
+/// let name = String::from("pgrx");
+/// let args: [&dyn IntoDatumDyn; 2] = [&name, &"pgrx"];
+/// let same = Spi::get_one_with_args::<bool>("SELECT $1 = $2", &args)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Each query also accepts its original [`Query::Arguments`] form, the `Args` parameter: the
+/// `Option<Vec<(PgOid, Option<pg_sys::Datum>)>>` of explicitly typed values for a `&str` query, and
+/// the `Option<Vec<Option<pg_sys::Datum>>>` of bare values for a prepared statement.  Prepared
+/// statements check the types of all other forms against their parameters, but bare values can't
+/// be checked, so the caller must make sure each matches its parameter's type.
+pub trait SpiArgs<Args = Option<Vec<(PgOid, Option<pg_sys::Datum>)>>> {
+    /// Whether [`SpiArgs::into_spi_args()`] returns the values' real types
+    const TYPED: bool = true;
+
+    fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>>;
+}
+
+impl SpiArgs for Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+    fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+        self
+    }
+}
+
+impl SpiArgs<Option<Vec<Option<pg_sys::Datum>>>> for Option<Vec<Option<pg_sys::Datum>>> {
+    const TYPED: bool = false;
+
+    fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+        self.map(|values| values.into_iter().map(|datum| (PgOid::Invalid, datum)).collect())
+    }
+}
+
+impl<Args> SpiArgs<Args> for Vec<(PgOid, Option<pg_sys::Datum>)> {
+    fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+        Some(self)
+    }
+}
+
+impl<Args> SpiArgs<Args> for () {
+    fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+        None
+    }
 }
 
+impl<Args> SpiArgs<Args> for &[&dyn IntoDatumDyn] {
+    fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+        Some(self.iter().map(|arg| (PgOid::from(arg.dyn_type_oid()), arg.to_datum())).collect())
+    }
+}
+
+impl<Args, const N: usize> SpiArgs<Args> for &[&dyn IntoDatumDyn; N] {
+    fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+        SpiArgs::<Args>::into_spi_args(self.as_slice())
+    }
+}
+
+/// Argument types for [`SpiClient::prepare`]
+///
+/// `PhantomData<(A, B, ...)>` takes the Postgres types from each [`IntoDatum::type_oid()`].
+/// `None` means no arguments, and `Some(Vec<PgOid>)` lists the types explicitly.
+pub trait SpiArgTypes {
+    fn into_arg_types(self) -> Vec<PgOid>;
+}
+
+impl SpiArgTypes for Option<Vec<PgOid>> {
+    fn into_arg_types(self) -> Vec<PgOid> {
+        self.unwrap_or_default()
+    }
+}
+
+fn spi_arg<T: IntoDatum>(value: T) -> (PgOid, Option<pg_sys::Datum>) {
+    let oid = value.composite_type_oid().unwrap_or_else(T::type_oid);
+    (PgOid::from(oid), value.into_datum())
+}
+
+macro_rules! impl_spi_args_for_tuple {
+    ($($ty:ident $value:ident),+) => {
+        impl<Args, $($ty: IntoDatum),+> SpiArgs<Args> for ($($ty,)+) {
+            fn into_spi_args(self) -> Option<Vec<(PgOid, Option<pg_sys::Datum>)>> {
+                let ($($value,)+) = self;
+                Some(vec![$(spi_arg($value)),+])
+            }
+        }
+
+        impl<$($ty: IntoDatum),+> SpiArgTypes for PhantomData<($($ty,)+)> {
+            fn into_arg_types(self) -> Vec<PgOid> {
+                vec![$(PgOid::from($ty::type_oid())),+]
+            }
+        }
+    };
+}
+
+impl_spi_args_for_tuple!(A a);
+impl_spi_args_for_tuple!(A a, B b);
+impl_spi_args_for_tuple!(A a, B b, C c);
+impl_spi_args_for_tuple!(A a, B b, C c, D d);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e, F f);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e, F f, G g);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e, F f, G g, H h);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j, K k);
+impl_spi_args_for_tuple!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j, K k, L l);
+
 /// A generalized interface to what constitutes a query
 ///
 /// Its primary purpose is to abstract away differences between
 /// one-off statements and prepared statements, but it can potentially
 /// be implemented for other types, provided they can be converted into a query.
 pub trait Query<'conn> {
+    /// The original form of this query's arguments, which [`SpiArgs`] accepts alongside the others
     type Arguments;
     type Result;
 
     /// Execute a query given a client and other arguments
-    fn execute(
+    fn execute<A: SpiArgs<Self::Arguments>>(
         self,
         client: &SpiClient<'conn>,
         limit: Option<libc::c_long>,
-        arguments: Self::Arguments,
+        arguments: A,
     ) -> Self::Result;
 
     /// Open a cursor for the query
-    fn open_cursor(self, client: &SpiClient<'conn>, args: Self::Arguments) -> SpiCursor<'conn>;
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arguments don't match the query's parameters.  Use
+    /// [`Query::try_open_cursor`] to get an [`Error`] instead.
+    fn open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        client: &SpiClient<'conn>,
+        args: A,
+    ) -> SpiCursor<'conn>
+    where
+        Self: Sized,
+    {
+        self.try_open_cursor(client, args).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Open a cursor for the query, checking the arguments against the query's parameters
+    fn try_open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        client: &SpiClient<'conn>,
+        args: A,
+    ) -> Result<SpiCursor<'conn>>;
 }
 
 impl<'conn> Query<'conn> for &String {
     type Arguments = Option<Vec<(PgOid, Option<pg_sys::Datum>)>>;
     type Result = Result<SpiTupleTable<'conn>>;
 
-    fn execute(
+    fn execute<A: SpiArgs<Self::Arguments>>(
         self,
         client: &SpiClient<'conn>,
         limit: Option<libc::c_long>,
-        arguments: Self::Arguments,
+        arguments: A,
     ) -> Self::Result {
         self.as_str().execute(client, limit, arguments)
     }
 
-    fn open_cursor(self, client: &SpiClient<'conn>, args: Self::Arguments) -> SpiCursor<'conn> {
-        self.as_str().open_cursor(client, args)
+    fn try_open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        client: &SpiClient<'conn>,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
+        self.as_str().try_open_cursor(client, args)
     }
 }
 
@@ -323,11 +584,11 @@ impl<'conn> Query<'conn> for &str {
     /// # Panics
     ///
     /// This function will panic if somehow the specified query contains a null byte.
-    fn execute(
+    fn execute<A: SpiArgs<Self::Arguments>>(
         self,
         _client: &SpiClient<'conn>,
         limit: Option<libc::c_long>,
-        arguments: Self::Arguments,
+        arguments: A,
     ) -> Self::Result {
         // SAFETY: no concurrent access
         unsafe {
@@ -335,7 +596,7 @@ impl<'conn> Query<'conn> for &str {
         }
 
         let src = CString::new(self).expect("query contained a null byte");
-        let status_code = match arguments {
+        let status_code = match arguments.into_spi_args() {
             Some(args) => {
                 let nargs = args.len();
                 let (types, data): (Vec<_>, Vec<_>) = args.into_iter().unzip();
@@ -369,9 +630,13 @@ impl<'conn> Query<'conn> for &str {
         Ok(SpiClient::prepare_tuple_table(status_code)?)
     }
 
-    fn open_cursor(self, _client: &SpiClient<'conn>, args: Self::Arguments) -> SpiCursor<'conn> {
+    fn try_open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        _client: &SpiClient<'conn>,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
         let src = CString::new(self).expect("query contained a null byte");
-        let args = args.unwrap_or_default();
+        let args = args.into_spi_args().unwrap_or_default();
 
         let nargs = args.len();
         let (types, data): (Vec<_>, Vec<_>) = args.into_iter().unzip();
@@ -392,7 +657,7 @@ impl<'conn> Query<'conn> for &str {
                 0,
             ))
         };
-        SpiCursor { ptr, __marker: PhantomData }
+        Ok(SpiCursor { ptr, __marker: PhantomData })
     }
 }
 
@@ -409,6 +674,8 @@ pub struct SpiTupleTable<'conn> {
 pub struct SpiHeapTupleDataEntry<'conn> {
     datum: Option<pg_sys::Datum>,
     type_oid: pg_sys::Oid,
+    // where `value()` converts to, rather than the parent of `CurrentMemoryContext`
+    memcxt: Option<pg_sys::MemoryContext>,
     __marker: PhantomData<&'conn ()>,
 }
 
@@ -419,6 +686,39 @@ pub struct SpiHeapTupleData<'conn> {
     entries: Vec<SpiHeapTupleDataEntry<'conn>>,
 }
 
+/// A type that can be read from a row returned by SPI, usually through `#[derive(FromSpiRow)]`
+///
+/// It's also implemented for tuples of up to twelve `Option`s, such as
+/// `(Option<i32>, Option<String>)`, which read the row's columns by position.
+///
+/// See [`SpiHeapTupleData::into()`] and [`Spi::get_row()`].
+pub trait FromSpiRow: Sized {
+    fn from_spi_row(row: &SpiHeapTupleData<'_>) -> Result<Self>;
+}
+
+macro_rules! impl_from_spi_row_for_tuple {
+    ($($ty:ident $ordinal:literal),+) => {
+        impl<$($ty: FromDatum + IntoDatum),+> FromSpiRow for ($(Option<$ty>,)+) {
+            fn from_spi_row(row: &SpiHeapTupleData<'_>) -> Result<Self> {
+                Ok(($(row.get::<$ty>($ordinal)?,)+))
+            }
+        }
+    };
+}
+
+impl_from_spi_row_for_tuple!(A 1);
+impl_from_spi_row_for_tuple!(A 1, B 2);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5, F 6);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9, J 10);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9, J 10, K 11);
+impl_from_spi_row_for_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9, J 10, K 11, L 12);
+
 impl Spi {
     pub fn get_one<A: FromDatum + IntoDatum>(query: &str) -> Result<Option<A>> {
         Spi::connect(|mut client| client.update(query, Some(1), None)?.first().get_one())
@@ -427,7 +727,7 @@ impl Spi {
     pub fn get_two<A: FromDatum + IntoDatum, B: FromDatum + IntoDatum>(
         query: &str,
     ) -> Result<(Option<A>, Option<B>)> {
-        Spi::connect(|mut client| client.update(query, Some(1), None)?.first().get_two::<A, B>())
+        Spi::get_row(query)
     }
 
     pub fn get_three<
@@ -437,25 +737,45 @@ impl Spi {
     >(
         query: &str,
     ) -> Result<(Option<A>, Option<B>, Option<C>)> {
-        Spi::connect(|mut client| {
-            client.update(query, Some(1), None)?.first().get_three::<A, B, C>()
-        })
+        Spi::get_row(query)
+    }
+
+    /// Read the first row of a query's results as any [`FromSpiRow`] type, such as a tuple of
+    /// `Option`s with as many columns as needed.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// # fn foo() -> spi::Result<()> {
+    /// let (a, b, c, d): (Option<i32>, Option<String>, Option<bool>, Option<f64>) =
+    ///     Spi::get_row("SELECT 1, 'two', true, 4.0::float8")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPosition`] if the query returned no rows.
+    pub fn get_row<T: FromSpiRow>(query: &str) -> Result<T> {
+        Spi::get_row_with_args(query, None)
+    }
+
+    /// Read every row of a query's results as any [`FromSpiRow`] type
+    pub fn get_all<T: FromSpiRow>(query: &str) -> Result<Vec<T>> {
+        Spi::get_all_with_args(query, None)
     }
 
     pub fn get_one_with_args<A: FromDatum + IntoDatum>(
         query: &str,
-        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+        args: impl SpiArgs,
     ) -> Result<Option<A>> {
-        Spi::connect(|mut client| client.update(query, Some(1), Some(args))?.first().get_one())
+        Spi::connect(|mut client| client.update(query, Some(1), args)?.first().get_one())
     }
 
     pub fn get_two_with_args<A: FromDatum + IntoDatum, B: FromDatum + IntoDatum>(
         query: &str,
-        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+        args: impl SpiArgs,
     ) -> Result<(Option<A>, Option<B>)> {
-        Spi::connect(|mut client| {
-            client.update(query, Some(1), Some(args))?.first().get_two::<A, B>()
-        })
+        Spi::get_row_with_args(query, args)
     }
 
     pub fn get_three_with_args<
@@ -464,11 +784,19 @@ impl Spi {
         C: FromDatum + IntoDatum,
     >(
         query: &str,
-        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+        args: impl SpiArgs,
     ) -> Result<(Option<A>, Option<B>, Option<C>)> {
-        Spi::connect(|mut client| {
-            client.update(query, Some(1), Some(args))?.first().get_three::<A, B, C>()
-        })
+        Spi::get_row_with_args(query, args)
+    }
+
+    /// Like [`Spi::get_row()`], with arguments
+    pub fn get_row_with_args<T: FromSpiRow>(query: &str, args: impl SpiArgs) -> Result<T> {
+        Spi::connect(|mut client| client.update(query, Some(1), args)?.first().get_row())
+    }
+
+    /// Like [`Spi::get_all()`], with arguments
+    pub fn get_all_with_args<T: FromSpiRow>(query: &str, args: impl SpiArgs) -> Result<Vec<T>> {
+        Spi::connect(|mut client| client.update(query, None, args)?.map(|row| row.into()).collect())
     }
 
     /// just run an arbitrary SQL statement.
@@ -485,10 +813,7 @@ impl Spi {
     /// ## Safety
     ///
     /// The statement runs in read/write mode
-    pub fn run_with_args(
-        query: &str,
-        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
-    ) -> std::result::Result<(), Error> {
+    pub fn run_with_args(query: &str, args: impl SpiArgs) -> std::result::Result<(), Error> {
         Spi::connect(|mut client| client.update(query, None, args).map(|_| ()))
     }
 
@@ -498,10 +823,7 @@ impl Spi {
     }
 
     /// explain a query with args, returning its result in json form
-    pub fn explain_with_args(
-        query: &str,
-        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
-    ) -> Result<Json> {
+    pub fn explain_with_args(query: &str, args: impl SpiArgs) -> Result<Json> {
         Ok(Spi::connect(|mut client| {
             client
                 .update(&format!("EXPLAIN (format json) {}", query), None, args)?
@@ -571,6 +893,28 @@ impl Spi {
         f(connection.client())
     }
 
+    /// Execute SPI commands via the provided `NonatomicSpiClient`, which can also commit and roll
+    /// back the current transaction.
+    ///
+    /// The connection is only nonatomic if the [`CallContext`] of the calling
+    /// [`#[pg_procedure]`](macro@crate::pg_procedure) allows it.  Otherwise, committing or rolling
+    /// back raises an ERROR, just like it would from PL/pgSQL.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if for some reason it's unable to "connect" to Postgres' SPI
+    /// system, in the same way as [`Spi::connect()`].
+    pub fn connect_nonatomic<R, F: FnOnce(NonatomicSpiClient<'_>) -> R>(
+        call: CallContext,
+        f: F,
+    ) -> R {
+        let options = if call.is_atomic() { 0 } else { pg_sys::SPI_OPT_NONATOMIC };
+        let connection = SpiConnection::connect_ext(options)
+            .expect("SPI_connect indicated an unexpected failure");
+
+        f(connection.nonatomic_client())
+    }
+
     #[track_caller]
     pub fn check_status(status_code: i32) -> std::result::Result<SpiOkCodes, Error> {
         match SpiOkCodes::try_from(status_code) {
@@ -583,31 +927,31 @@ impl Spi {
 
 impl<'conn> SpiClient<'conn> {
     /// perform a SELECT statement
-    pub fn select<Q: Query<'conn>>(
+    pub fn select<Q: Query<'conn>, A: SpiArgs<Q::Arguments>>(
         &self,
         query: Q,
         limit: Option<libc::c_long>,
-        args: Q::Arguments,
+        args: A,
     ) -> Q::Result {
         self.execute(query, limit, args)
     }
 
     /// perform any query (including utility statements) that modify the database in some way
-    pub fn update<Q: Query<'conn>>(
+    pub fn update<Q: Query<'conn>, A: SpiArgs<Q::Arguments>>(
         &mut self,
         query: Q,
         limit: Option<libc::c_long>,
-        args: Q::Arguments,
+        args: A,
     ) -> Q::Result {
         Spi::mark_mutable();
         self.execute(query, limit, args)
     }
 
-    fn execute<Q: Query<'conn>>(
+    fn execute<Q: Query<'conn>, A: SpiArgs<Q::Arguments>>(
         &self,
         query: Q,
         limit: Option<libc::c_long>,
-        args: Q::Arguments,
+        args: A,
     ) -> Q::Result {
         query.execute(&self, limit, args)
     }
@@ -637,24 +981,47 @@ impl<'conn> SpiClient<'conn> {
     /// Rows may be then fetched using [`SpiCursor::fetch`].
     ///
     /// See [`SpiCursor`] docs for usage details.
-    pub fn open_cursor<Q: Query<'conn>>(&self, query: Q, args: Q::Arguments) -> SpiCursor<'conn> {
+    pub fn open_cursor<Q: Query<'conn>, A: SpiArgs<Q::Arguments>>(
+        &self,
+        query: Q,
+        args: A,
+    ) -> SpiCursor<'conn> {
         query.open_cursor(&self, args)
     }
 
+    /// Like [`SpiClient::open_cursor`], but returns an [`Error`] if the arguments don't match
+    pub fn try_open_cursor<Q: Query<'conn>, A: SpiArgs<Q::Arguments>>(
+        &self,
+        query: Q,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
+        query.try_open_cursor(self, args)
+    }
+
     /// Set up a cursor that will execute the specified update (mutating) query
     ///
     /// Rows may be then fetched using [`SpiCursor::fetch`].
     ///
     /// See [`SpiCursor`] docs for usage details.
-    pub fn open_cursor_mut<Q: Query<'conn>>(
+    pub fn open_cursor_mut<Q: Query<'conn>, A: SpiArgs<Q::Arguments>>(
         &mut self,
         query: Q,
-        args: Q::Arguments,
+        args: A,
     ) -> SpiCursor<'conn> {
         Spi::mark_mutable();
         query.open_cursor(self, args)
     }
 
+    /// Like [`SpiClient::open_cursor_mut`], but returns an [`Error`] if the arguments don't match
+    pub fn try_open_cursor_mut<Q: Query<'conn>, A: SpiArgs<Q::Arguments>>(
+        &mut self,
+        query: Q,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
+        Spi::mark_mutable();
+        query.try_open_cursor(self, args)
+    }
+
     /// Find a cursor in transaction by name
     ///
     /// A cursor for a query can be opened using [`SpiClient::open_cursor`].
@@ -690,7 +1057,8 @@ type CursorName = String;
 /// this is a Pgrx limitation that might get lifted in the future.
 ///
 /// In the meantime, if you're using cursors to limit memory usage, make sure to use
-/// multiple separate Spi sessions, retrieving the cursor by name.
+/// multiple separate Spi sessions, retrieving the cursor by name, or iterate over the rows with
+/// [`SpiCursor::into_iter()`], which frees each batch as soon as it has been read.
 ///
 /// # Examples
 /// ## Simple cursor
@@ -728,12 +1096,34 @@ type CursorName = String;
 /// })
 /// # }
 /// ```
+///
+/// ## Iterating over all rows
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// #[derive(FromSpiRow)]
+/// struct Row {
+///     n: i32,
+/// }
+///
+/// # fn foo() -> spi::Result<()> {
+/// Spi::connect(|client| {
+///     let cursor = client.open_cursor("SELECT n FROM generate_series(1, 1000000) n", None);
+///     let mut sum = 0i64;
+///     for row in cursor.into_iter::<Row>(1000) {
+///         sum += row?.n as i64;
+///     }
+///     Ok::<_, spi::Error>(sum)
+///     // <--- only one batch of 1000 rows was held in memory at a time
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct SpiCursor<'client> {
     ptr: NonNull<pg_sys::PortalData>,
     __marker: PhantomData<&'client SpiClient<'client>>,
 }
 
-impl SpiCursor<'_> {
+impl<'client> SpiCursor<'client> {
     /// Fetch up to `count` rows from the cursor, moving forward
     ///
     /// If `fetch` runs off the end of the available rows, an empty [`SpiTupleTable`] is returned.
@@ -747,6 +1137,35 @@ impl SpiCursor<'_> {
         Ok(SpiClient::prepare_tuple_table(SpiOkCodes::Fetch as i32)?)
     }
 
+    /// Consume the cursor, returning an iterator over all of its remaining rows
+    ///
+    /// Rows are fetched `batch_size` at a time, and each batch is freed once all of its rows
+    /// have been read, so the whole result set is never held in memory at once.  Each row is
+    /// converted with [`FromSpiRow`], and the cursor is closed when the iterator is dropped.
+    ///
+    /// Any Postgres memory used to convert a row is freed along with its batch, so `T` must own
+    /// its data: borrowed values, such as a `&str` read from a `text` column, would dangle.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `batch_size` is not positive.
+    pub fn into_iter<T: FromSpiRow + 'static>(
+        self,
+        batch_size: libc::c_long,
+    ) -> SpiCursorIter<'client, T> {
+        assert!(batch_size > 0, "batch_size must be positive");
+        SpiCursorIter {
+            cursor: self,
+            batch_size,
+            memcxt: PgMemoryContexts::new("SpiCursorIter batch"),
+            table: None,
+            size: 0,
+            current: 0,
+            done: false,
+            __marker: PhantomData,
+        }
+    }
+
     /// Consume the cursor, returning its name
     ///
     /// The actual Postgres cursor is kept alive for the duration of the transaction.
@@ -779,6 +1198,91 @@ impl Drop for SpiCursor<'_> {
     }
 }
 
+/// An iterator over the rows of an [`SpiCursor`], created by [`SpiCursor::into_iter()`]
+pub struct SpiCursorIter<'client, T> {
+    cursor: SpiCursor<'client>,
+    batch_size: libc::c_long,
+    // rows are converted in here, and it's reset along with each batch
+    memcxt: PgMemoryContexts,
+    table: Option<NonNull<pg_sys::SPITupleTable>>,
+    size: usize,
+    current: usize,
+    done: bool,
+    __marker: PhantomData<T>,
+}
+
+impl<T> SpiCursorIter<'_, T> {
+    fn fetch_batch(&mut self) {
+        // SAFETY: no concurrent access, and the cursor is valid for as long as we own it
+        unsafe {
+            pg_sys::SPI_tuptable = std::ptr::null_mut();
+            pg_sys::SPI_cursor_fetch(self.cursor.ptr.as_mut(), true, self.batch_size);
+            self.table = NonNull::new(pg_sys::SPI_tuptable);
+            self.size = pg_sys::SPI_processed as usize;
+        }
+        self.current = 0;
+        // a short batch means the cursor has run out of rows
+        self.done = self.size < self.batch_size as usize;
+    }
+
+    fn free_batch(&mut self) {
+        if let Some(table) = self.table.take() {
+            // SAFETY: the table came from our own fetch, and no row borrowing it outlives `next()`
+            unsafe {
+                if pg_sys::SPI_tuptable == table.as_ptr() {
+                    pg_sys::SPI_tuptable = std::ptr::null_mut();
+                }
+                pg_sys::SPI_freetuptable(table.as_ptr());
+                // SAFETY: `T: 'static`, so nothing converted from the batch can borrow from it
+                self.memcxt.reset();
+            }
+        }
+    }
+}
+
+impl<T: FromSpiRow + 'static> Iterator for SpiCursorIter<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(table) = self.table {
+                if self.current < self.size {
+                    // SAFETY: `current` is within the rows of the batch we fetched
+                    let row = unsafe {
+                        let table = table.as_ref();
+                        SpiHeapTupleData::new(table.tupdesc, *table.vals.add(self.current))
+                    };
+                    self.current += 1;
+                    return Some(match row {
+                        Ok(Some(row)) => {
+                            T::from_spi_row(&row.in_memory_context(self.memcxt.value()))
+                        }
+                        Ok(None) => Err(Error::NoTupleTable),
+                        Err(e) => Err(e),
+                    });
+                }
+                self.free_batch();
+            }
+
+            if self.done {
+                return None;
+            }
+            self.fetch_batch();
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.size - self.current, if self.done { Some(self.size - self.current) } else { None })
+    }
+}
+
+impl<T> Drop for SpiCursorIter<'_, T> {
+    fn drop(&mut self) {
+        self.free_batch();
+    }
+}
+
 /// Client lifetime-bound prepared statement
 pub struct PreparedStatement<'conn> {
     plan: NonNull<pg_sys::_SPI_plan>,
@@ -808,17 +1312,21 @@ impl<'conn> Query<'conn> for &OwnedPreparedStatement {
     type Arguments = Option<Vec<Option<pg_sys::Datum>>>;
     type Result = Result<SpiTupleTable<'conn>>;
 
-    fn execute(
+    fn execute<A: SpiArgs<Self::Arguments>>(
         self,
         client: &SpiClient<'conn>,
         limit: Option<libc::c_long>,
-        arguments: Self::Arguments,
+        arguments: A,
     ) -> Self::Result {
         (&self.0).execute(client, limit, arguments)
     }
 
-    fn open_cursor(self, client: &SpiClient<'conn>, args: Self::Arguments) -> SpiCursor<'conn> {
-        (&self.0).open_cursor(client, args)
+    fn try_open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        client: &SpiClient<'conn>,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
+        (&self.0).try_open_cursor(client, args)
     }
 }
 
@@ -826,17 +1334,21 @@ impl<'conn> Query<'conn> for OwnedPreparedStatement {
     type Arguments = Option<Vec<Option<pg_sys::Datum>>>;
     type Result = Result<SpiTupleTable<'conn>>;
 
-    fn execute(
+    fn execute<A: SpiArgs<Self::Arguments>>(
         self,
         client: &SpiClient<'conn>,
         limit: Option<libc::c_long>,
-        arguments: Self::Arguments,
+        arguments: A,
     ) -> Self::Result {
         (&self.0).execute(client, limit, arguments)
     }
 
-    fn open_cursor(self, client: &SpiClient<'conn>, args: Self::Arguments) -> SpiCursor<'conn> {
-        (&self.0).open_cursor(client, args)
+    fn try_open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        client: &SpiClient<'conn>,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
+        (&self.0).try_open_cursor(client, args)
     }
 }
 
@@ -853,32 +1365,55 @@ impl<'conn> PreparedStatement<'conn> {
         }
         OwnedPreparedStatement(PreparedStatement { __marker: PhantomData, plan: self.plan })
     }
+
+    /// Checks the arguments' count and types against the plan's parameters
+    fn prepare_args<A: SpiArgs<Args>, Args>(
+        &self,
+        args: A,
+    ) -> Result<(Vec<pg_sys::Datum>, Vec<std::os::raw::c_char>)> {
+        let args = args.into_spi_args().unwrap_or_default();
+        let nargs = args.len();
+
+        // SAFETY: self.plan is initialized in `SpiClient::prepare`
+        let expected = unsafe { pg_sys::SPI_getargcount(self.plan.as_ptr()) } as usize;
+
+        if nargs != expected {
+            return Err(Error::PreparedStatementArgumentMismatch { expected, got: nargs });
+        }
+
+        if A::TYPED {
+            for (i, (oid, _)) in args.iter().enumerate() {
+                // SAFETY: self.plan is initialized in `SpiClient::prepare` and `i` is in bounds
+                let expected = unsafe { pg_sys::SPI_getargtypeid(self.plan.as_ptr(), i as i32) };
+                if oid.value() != expected {
+                    return Err(Error::PreparedStatementArgumentTypeMismatch {
+                        position: i + 1,
+                        expected,
+                        got: oid.value(),
+                    });
+                }
+            }
+        }
+
+        Ok(args.into_iter().map(|(_, datum)| prepare_datum(datum)).unzip())
+    }
 }
 
 impl<'conn: 'stmt, 'stmt> Query<'conn> for &'stmt PreparedStatement<'conn> {
     type Arguments = Option<Vec<Option<pg_sys::Datum>>>;
     type Result = Result<SpiTupleTable<'conn>>;
 
-    fn execute(
+    fn execute<A: SpiArgs<Self::Arguments>>(
         self,
         _client: &SpiClient<'conn>,
         limit: Option<libc::c_long>,
-        arguments: Self::Arguments,
+        arguments: A,
     ) -> Self::Result {
         // SAFETY: no concurrent access
         unsafe {
             pg_sys::SPI_tuptable = std::ptr::null_mut();
         }
-        let args = arguments.unwrap_or_default();
-        let nargs = args.len();
-
-        let expected = unsafe { pg_sys::SPI_getargcount(self.plan.as_ptr()) } as usize;
-
-        if nargs != expected {
-            return Err(Error::PreparedStatementArgumentMismatch { expected, got: nargs });
-        }
-
-        let (mut datums, mut nulls): (Vec<_>, Vec<_>) = args.into_iter().map(prepare_datum).unzip();
+        let (mut datums, mut nulls) = self.prepare_args(arguments)?;
 
         // SAFETY: all arguments are prepared above
         let status_code = unsafe {
@@ -894,10 +1429,12 @@ impl<'conn: 'stmt, 'stmt> Query<'conn> for &'stmt PreparedStatement<'conn> {
         Ok(SpiClient::prepare_tuple_table(status_code)?)
     }
 
-    fn open_cursor(self, _client: &SpiClient<'conn>, args: Self::Arguments) -> SpiCursor<'conn> {
-        let args = args.unwrap_or_default();
-
-        let (mut datums, nulls): (Vec<_>, Vec<_>) = args.into_iter().map(prepare_datum).unzip();
+    fn try_open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        _client: &SpiClient<'conn>,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
+        let (mut datums, nulls) = self.prepare_args(args)?;
 
         // SAFETY: arguments are prepared above and SPI_cursor_open will never return the null
         // pointer.  It'll raise an ERROR if something is invalid for it to create the cursor
@@ -910,7 +1447,7 @@ impl<'conn: 'stmt, 'stmt> Query<'conn> for &'stmt PreparedStatement<'conn> {
                 Spi::is_xact_still_immutable(),
             ))
         };
-        SpiCursor { ptr, __marker: PhantomData }
+        Ok(SpiCursor { ptr, __marker: PhantomData })
     }
 }
 
@@ -918,17 +1455,21 @@ impl<'conn> Query<'conn> for PreparedStatement<'conn> {
     type Arguments = Option<Vec<Option<pg_sys::Datum>>>;
     type Result = Result<SpiTupleTable<'conn>>;
 
-    fn execute(
+    fn execute<A: SpiArgs<Self::Arguments>>(
         self,
         client: &SpiClient<'conn>,
         limit: Option<libc::c_long>,
-        arguments: Self::Arguments,
+        arguments: A,
     ) -> Self::Result {
         (&self).execute(client, limit, arguments)
     }
 
-    fn open_cursor(self, client: &SpiClient<'conn>, args: Self::Arguments) -> SpiCursor<'conn> {
-        (&self).open_cursor(client, args)
+    fn try_open_cursor<A: SpiArgs<Self::Arguments>>(
+        self,
+        client: &SpiClient<'conn>,
+        args: A,
+    ) -> Result<SpiCursor<'conn>> {
+        (&self).try_open_cursor(client, args)
     }
 }
 
@@ -938,13 +1479,13 @@ impl<'conn> SpiClient<'conn> {
     /// # Panics
     ///
     /// This function will panic if the supplied `query` string contained a NULL byte
-    pub fn prepare(
+    pub fn prepare<T: SpiArgTypes>(
         &self,
         query: &str,
-        args: Option<Vec<PgOid>>,
+        args: T,
     ) -> Result<PreparedStatement<'conn>> {
         let src = CString::new(query).expect("query contained a null byte");
-        let args = args.unwrap_or_default();
+        let args = args.into_arg_types();
         let nargs = args.len();
 
         // SAFETY: all arguments are prepared above
@@ -1003,9 +1544,7 @@ impl<'conn> SpiTupleTable<'conn> {
     pub fn get_two<A: FromDatum + IntoDatum, B: FromDatum + IntoDatum>(
         &self,
     ) -> Result<(Option<A>, Option<B>)> {
-        let a = self.get::<A>(1)?;
-        let b = self.get::<B>(2)?;
-        Ok((a, b))
+        self.get_row()
     }
 
     pub fn get_three<
@@ -1015,10 +1554,18 @@ impl<'conn> SpiTupleTable<'conn> {
     >(
         &self,
     ) -> Result<(Option<A>, Option<B>, Option<C>)> {
-        let a = self.get::<A>(1)?;
-        let b = self.get::<B>(2)?;
-        let c = self.get::<C>(3)?;
-        Ok((a, b, c))
+        self.get_row()
+    }
+
+    /// Read the current row as any [`FromSpiRow`] type, such as a tuple of `Option`s
+    ///
+    /// # Errors
+    ///
+    /// If we have no backing tuple table a [`Error::NoTupleTable`] is returned, and if we're not
+    /// positioned on a row an [`Error::InvalidPosition`] is returned
+    pub fn get_row<T: FromSpiRow>(&self) -> Result<T> {
+        self.get_spi_tuptable()?;
+        self.get_heap_tuple()?.ok_or(Error::InvalidPosition)?.into()
     }
 
     #[inline(always)]
@@ -1247,6 +1794,7 @@ impl<'conn> SpiHeapTupleData<'conn> {
                 data.entries.push(SpiHeapTupleDataEntry {
                     datum: if is_null { None } else { Some(datum) },
                     type_oid: pg_sys::SPI_gettypeid(tupdesc as _, i),
+                    memcxt: None,
                     __marker: PhantomData,
                 });
             }
@@ -1255,6 +1803,14 @@ impl<'conn> SpiHeapTupleData<'conn> {
         Ok(Some(data))
     }
 
+    /// Convert this row's values into `memcxt`, instead of the parent of `CurrentMemoryContext`
+    fn in_memory_context(mut self, memcxt: pg_sys::MemoryContext) -> Self {
+        for entry in &mut self.entries {
+            entry.memcxt = Some(memcxt);
+        }
+        self
+    }
+
     /// Get a typed value from this HeapTuple by its ordinal position.
     ///
     /// The ordinal position is 1-based
@@ -1280,6 +1836,40 @@ impl<'conn> SpiHeapTupleData<'conn> {
         self.get_datum_by_name(name.as_ref()).map(|entry| entry.value())?
     }
 
+    /// Get a typed value from this HeapTuple by its column name, like [`get_by_name()`][Self::get_by_name],
+    /// but with errors that say which column was wrong.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::ColumnNotFound`] if there's no such column, or an
+    /// [`Error::ColumnTypeMismatch`] if the desired Rust type is incompatible with the column's type
+    pub fn column<T: IntoDatum + FromDatum>(&self, name: &str) -> Result<Option<T>> {
+        let entry =
+            self.get_datum_by_name(name).map_err(|_| Error::ColumnNotFound(String::from(name)))?;
+        entry.value().map_err(|e| match e {
+            Error::DatumError(TryFromDatumError::IncompatibleTypes {
+                rust_type,
+                datum_type,
+                ..
+            }) => Error::ColumnTypeMismatch {
+                column: String::from(name),
+                column_type: datum_type,
+                rust_type,
+            },
+            e => e,
+        })
+    }
+
+    /// Read this HeapTuple as a [`FromSpiRow`] type
+    ///
+    /// # Errors
+    ///
+    /// Returns whichever [`Error`] the type's [`FromSpiRow::from_spi_row()`] does, which is usually
+    /// one from [`column()`][Self::column] or an [`Error::NullColumn`]
+    pub fn into<T: FromSpiRow>(self) -> Result<T> {
+        T::from_spi_row(&self)
+    }
+
     /// Get a raw Datum from this HeapTuple by its ordinal position.
     ///
     /// The ordinal position is 1-based.
@@ -1335,6 +1925,7 @@ impl<'conn> SpiHeapTupleData<'conn> {
         self.entries[ordinal - 1] = SpiHeapTupleDataEntry {
             datum: datum.into_datum(),
             type_oid: T::type_oid(),
+            memcxt: None,
             __marker: PhantomData,
         };
         Ok(())
@@ -1389,9 +1980,12 @@ impl<'conn> SpiHeapTupleDataEntry<'conn> {
         match self.datum.as_ref() {
             Some(datum) => unsafe {
                 T::try_from_datum_in_memory_context(
-                    PgMemoryContexts::CurrentMemoryContext
-                        .parent()
-                        .expect("parent memory context is absent"),
+                    match self.memcxt {
+                        Some(memcxt) => PgMemoryContexts::For(memcxt),
+                        None => PgMemoryContexts::CurrentMemoryContext
+                            .parent()
+                            .expect("parent memory context is absent"),
+                    },
                     *datum,
                     false,
                     self.type_oid,
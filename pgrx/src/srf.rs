@@ -11,7 +11,7 @@
 //! Helper implementations for returning sets and tables from `#[pg_extern]`-style functions
 use crate::iter::{SetOfIterator, TableIterator};
 use crate::{
-    pg_return_null, pg_sys, srf_first_call_init, srf_is_first_call, srf_per_call_setup,
+    is_a, pg_return_null, pg_sys, srf_first_call_init, srf_is_first_call, srf_per_call_setup,
     srf_return_done, srf_return_next, IntoDatum, IntoHeapTuple, PgMemoryContexts,
 };
 
@@ -71,6 +71,65 @@ impl<'a, T: IntoDatum> SetOfIterator<'a, T> {
             }
         }
     }
+    #[doc(hidden)]
+    pub unsafe fn srf_materialize<F: FnOnce() -> Option<SetOfIterator<'a, T>>>(
+        fcinfo: pg_sys::FunctionCallInfo,
+        first_call_func: F,
+    ) -> pg_sys::Datum {
+        let rsinfo = materialize_rsinfo(fcinfo);
+
+        // a `SETOF` composite type is materialized as the composite's own rows, while any other
+        // `SETOF T` is materialized as single-column tuples, shaped the way the caller expects
+        let (tupdesc, composite) =
+            PgMemoryContexts::For((*(*rsinfo).econtext).ecxt_per_query_memory).switch_to(|_| {
+                let mut tupdesc = std::ptr::null_mut();
+                if pg_sys::get_call_result_type(fcinfo, std::ptr::null_mut(), &mut tupdesc)
+                    == pg_sys::TypeFuncClass_TYPEFUNC_COMPOSITE
+                {
+                    (pg_sys::BlessTupleDesc(tupdesc), true)
+                } else if (*rsinfo).expectedDesc.is_null() {
+                    pg_sys::error!(
+                        "materialize mode requires the caller to provide a tuple descriptor"
+                    );
+                } else {
+                    (pg_sys::CreateTupleDescCopy((*rsinfo).expectedDesc), false)
+                }
+            });
+
+        let setof_iterator = first_call_func();
+        materialize(rsinfo, tupdesc, setof_iterator, |tupstore, tupdesc, value| {
+            match value.into_datum() {
+                Some(datum) if composite => {
+                    let htup_header =
+                        pg_sys::pg_detoast_datum(datum.cast_mut_ptr()) as pg_sys::HeapTupleHeader;
+                    let mut tuple = pg_sys::HeapTupleData {
+                        t_len: crate::heap_tuple_header_get_datum_length(htup_header) as u32,
+                        t_data: htup_header,
+                        ..Default::default()
+                    };
+                    pg_sys::tuplestore_puttuple(tupstore, &mut tuple);
+                }
+                Some(mut datum) => {
+                    let mut isnull = false;
+                    pg_sys::tuplestore_putvalues(tupstore, tupdesc, &mut datum, &mut isnull);
+                }
+                None => {
+                    // a NULL composite is a row of all NULLs
+                    let natts = (*tupdesc).natts as usize;
+                    let mut datums = vec![pg_sys::Datum::from(0); natts];
+                    let mut nulls = vec![true; natts];
+                    pg_sys::tuplestore_putvalues(
+                        tupstore,
+                        tupdesc,
+                        datums.as_mut_ptr(),
+                        nulls.as_mut_ptr(),
+                    );
+                }
+            }
+        });
+
+        pg_sys::Datum::from(0)
+    }
 }
 
 impl<'a, T: IntoHeapTuple> TableIterator<'a, T> {
@@ -140,4 +199,78 @@ impl<'a, T: IntoHeapTuple> TableIterator<'a, T> {
             }
         }
     }
+    #[doc(hidden)]
+    pub unsafe fn srf_materialize<F: FnOnce() -> Option<TableIterator<'a, T>>>(
+        fcinfo: pg_sys::FunctionCallInfo,
+        first_call_func: F,
+    ) -> pg_sys::Datum {
+        let rsinfo = materialize_rsinfo(fcinfo);
+
+        // Build a tuple descriptor for our result type
+        let tupdesc =
+            PgMemoryContexts::For((*(*rsinfo).econtext).ecxt_per_query_memory).switch_to(|_| {
+                let mut tupdesc = std::ptr::null_mut();
+                if pg_sys::get_call_result_type(fcinfo, std::ptr::null_mut(), &mut tupdesc)
+                    != pg_sys::TypeFuncClass_TYPEFUNC_COMPOSITE
+                {
+                    pg_sys::error!("return type must be a row type");
+                }
+                pg_sys::BlessTupleDesc(tupdesc)
+            });
+
+        let table_iterator = first_call_func();
+        materialize(rsinfo, tupdesc, table_iterator, |tupstore, tupdesc, tuple| {
+            let heap_tuple = tuple.into_heap_tuple(tupdesc);
+            pg_sys::tuplestore_puttuple(tupstore, heap_tuple);
+        });
+
+        pg_sys::Datum::from(0)
+    }
+}
+
+/// Ensure the caller of a materialize-mode SRF is able to accept a tuplestore, returning its
+/// [`pg_sys::ReturnSetInfo`]
+unsafe fn materialize_rsinfo(fcinfo: pg_sys::FunctionCallInfo) -> *mut pg_sys::ReturnSetInfo {
+    let rsinfo = (*fcinfo).resultinfo.cast::<pg_sys::ReturnSetInfo>();
+    if rsinfo.is_null() || !is_a(rsinfo.cast(), pg_sys::NodeTag_T_ReturnSetInfo) {
+        pg_sys::error!("set-valued function called in context that cannot accept a set");
+    }
+    if ((*rsinfo).allowedModes as pg_sys::SetFunctionReturnMode
+        & pg_sys::SetFunctionReturnMode_SFRM_Materialize)
+        == 0
+    {
+        pg_sys::error!("materialize mode required, but it is not allowed in this context");
+    }
+    rsinfo
+}
+
+/// Drain `iter` into a new tuplestore using `put`, and hand that tuplestore to the caller
+/// through `rsinfo`
+unsafe fn materialize<I, T>(
+    rsinfo: *mut pg_sys::ReturnSetInfo,
+    tupdesc: pg_sys::TupleDesc,
+    iter: Option<I>,
+    mut put: impl FnMut(*mut pg_sys::Tuplestorestate, pg_sys::TupleDesc, T),
+) where
+    I: Iterator<Item = T>,
+{
+    // the tuplestore keeps its tuples in the memory context it was created in, which must
+    // outlive this call
+    let random_access = ((*rsinfo).allowedModes as pg_sys::SetFunctionReturnMode
+        & pg_sys::SetFunctionReturnMode_SFRM_Materialize_Random)
+        != 0;
+    let tupstore = PgMemoryContexts::For((*(*rsinfo).econtext).ecxt_per_query_memory)
+        .switch_to(|_| pg_sys::tuplestore_begin_heap(random_access, false, pg_sys::work_mem));
+
+    // the tuplestore copies each row it's given, so whatever Postgres memory we need to build
+    // a row can be thrown away once it's been stored
+    let mut per_row = PgMemoryContexts::new("pgrx materialize row");
+    for value in iter.into_iter().flatten() {
+        per_row.switch_to(|_| put(tupstore, tupdesc, value));
+        per_row.reset();
+    }
+
+    (*rsinfo).returnMode = pg_sys::SetFunctionReturnMode_SFRM_Materialize;
+    (*rsinfo).setResult = tupstore;
+    (*rsinfo).setDesc = tupdesc;
 }
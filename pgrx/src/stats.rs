@@ -0,0 +1,602 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Cumulative statistics kept in shared memory, in the manner of `pg_stat_statements`.
+//!
+//! A [`PgStats`] counts events, or observes values, per key.  Every backend updates the same
+//! statistics, which last until they're reset or Postgres stops, and can be saved to a file when
+//! Postgres shuts down cleanly to be loaded again when it starts.
+//!
+//! [`pg_stats!`] declares the statistics along with a SQL view of them, and a SQL function to
+//! reset them:
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::stats::PgStats;
+//! use pgrx::{pg_shmem_init, pg_stats, PgSharedMemoryInitialization};
+//!
+//! pg_stats! {
+//!     /// How long each of our functions took, in milliseconds
+//!     static FUNCTION_TIMES: PgStats<pg_sys::Oid, 1024, 3> =
+//!         PgStats::with_histogram([1.0, 10.0, f64::INFINITY]).persist("pg_stat/my_extension.stat");
+//!     view = function_times;
+//!     reset = function_times_reset;
+//! }
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     pg_shmem_init!(FUNCTION_TIMES);
+//! }
+//!
+//! fn timed(function: pg_sys::Oid, work: impl FnOnce()) {
+//!     let start = std::time::Instant::now();
+//!     work();
+//!     FUNCTION_TIMES.observe(function, start.elapsed().as_secs_f64() * 1000.0);
+//! }
+//! ```
+//!
+//! As with any shared memory, the extension must be loaded through `shared_preload_libraries`.
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::lwlock::PgLwLock;
+use crate::pgrx_sql_entity_graph::{PgrxSql, SqlGraphEntity, ToSql};
+use crate::shmem::{PGRXSharedMemory, PgSharedMem, PgSharedMemoryInitialization};
+use crate::{pg_guard, pg_sys, warning};
+use std::cell::UnsafeCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
+
+/// Identifies a file of saved statistics, and the version of its format
+const FILE_HEADER: u32 = 0x5047_5302;
+
+/// A key of a [`PgStats`], which is kept in shared memory and saved to disk field by field.
+///
+/// # Safety
+///
+/// The key must be plain data that means the same in every backend, so nothing like a pointer.
+/// [`StatsKey::load`] must return an equal key from the bytes [`StatsKey::save`] wrote.
+pub unsafe trait StatsKey: Copy + Eq + Hash + Send + Sync + 'static {
+    /// How many bytes the key is saved as
+    const SIZE: usize;
+
+    /// Appends the key's [`StatsKey::SIZE`] bytes to `bytes`
+    fn save(&self, bytes: &mut Vec<u8>);
+
+    /// The key that was saved as `bytes`, which are [`StatsKey::SIZE`] long
+    fn load(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_stats_key_for_int {
+    ($($int:ty),*) => {
+        $(
+            unsafe impl StatsKey for $int {
+                const SIZE: usize = std::mem::size_of::<$int>();
+
+                fn save(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend(self.to_ne_bytes())
+                }
+
+                fn load(bytes: &[u8]) -> Self {
+                    <$int>::from_ne_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+impl_stats_key_for_int!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+unsafe impl StatsKey for bool {
+    const SIZE: usize = 1;
+
+    fn save(&self, bytes: &mut Vec<u8>) {
+        bytes.push(*self as u8)
+    }
+
+    fn load(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+unsafe impl StatsKey for pg_sys::Oid {
+    const SIZE: usize = u32::SIZE;
+
+    fn save(&self, bytes: &mut Vec<u8>) {
+        self.as_u32().save(bytes)
+    }
+
+    fn load(bytes: &[u8]) -> Self {
+        // SAFETY: it was a valid `Oid` when saved
+        unsafe { pg_sys::Oid::from_u32_unchecked(u32::load(bytes)) }
+    }
+}
+
+macro_rules! impl_stats_key_for_tuple {
+    ($($t:ident $i:tt),*) => {
+        unsafe impl<$($t: StatsKey),*> StatsKey for ($($t,)*) {
+            const SIZE: usize = 0 $(+ $t::SIZE)*;
+
+            fn save(&self, bytes: &mut Vec<u8>) {
+                $(self.$i.save(bytes);)*
+            }
+
+            fn load(bytes: &[u8]) -> Self {
+                let mut end = 0;
+                ($({
+                    end += $t::SIZE;
+                    $t::load(&bytes[end - $t::SIZE..end])
+                },)*)
+            }
+        }
+    };
+}
+
+impl_stats_key_for_tuple!(A 0, B 1);
+impl_stats_key_for_tuple!(A 0, B 1, C 2);
+
+/// The statistics gathered for one key of a [`PgStats`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StatsEntry<const B: usize> {
+    /// How many events were counted, or values observed
+    pub count: i64,
+    /// The sum of the observed values
+    pub total: f64,
+    /// How many observed values fell into each bucket of the histogram
+    pub histogram: [i64; B],
+}
+
+impl<const B: usize> Default for StatsEntry<B> {
+    fn default() -> Self {
+        StatsEntry { count: 0, total: 0.0, histogram: [0; B] }
+    }
+}
+
+const SLOT_EMPTY: u32 = 0;
+const SLOT_CLAIMED: u32 = 1;
+const SLOT_READY: u32 = 2;
+
+/// One slot of a [`StatsTable`].  Its key is written once, by the backend that claims the slot,
+/// before the slot is marked ready, and its counters are updated in place by any backend.
+pub struct StatsSlot<K, const B: usize> {
+    state: AtomicU32,
+    key: UnsafeCell<MaybeUninit<K>>,
+    count: AtomicI64,
+    total: AtomicU64,
+    histogram: [AtomicI64; B],
+}
+
+// SAFETY: the key is only written while the slot is claimed, and only read once it's ready
+unsafe impl<K: StatsKey, const B: usize> Sync for StatsSlot<K, B> {}
+
+impl<K, const B: usize> Default for StatsSlot<K, B> {
+    fn default() -> Self {
+        StatsSlot {
+            state: AtomicU32::new(SLOT_EMPTY),
+            key: UnsafeCell::new(MaybeUninit::uninit()),
+            count: AtomicI64::new(0),
+            total: AtomicU64::new(0),
+            histogram: [(); B].map(|_| AtomicI64::new(0)),
+        }
+    }
+}
+
+impl<K: StatsKey, const B: usize> StatsSlot<K, B> {
+    /// The slot's key, or `None` if it's empty
+    fn key(&self) -> Option<K> {
+        loop {
+            match self.state.load(Ordering::Acquire) {
+                SLOT_EMPTY => return None,
+                // SAFETY: the key was written before the slot was marked ready
+                SLOT_READY => return Some(unsafe { (*self.key.get()).assume_init() }),
+                // another backend is about to write the key
+                _ => std::hint::spin_loop(),
+            }
+        }
+    }
+
+    /// Marks an empty slot as holding `key`, unless another backend claimed it first
+    fn claim(&self, key: K) -> bool {
+        let claimed = self
+            .state
+            .compare_exchange(SLOT_EMPTY, SLOT_CLAIMED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok();
+        if claimed {
+            // SAFETY: nobody else reads or writes the key until we mark the slot ready
+            unsafe { (*self.key.get()).write(key) };
+            self.state.store(SLOT_READY, Ordering::Release);
+        }
+        claimed
+    }
+
+    fn add_total(&self, value: f64) {
+        let _ = self.total.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+            Some((f64::from_bits(total) + value).to_bits())
+        });
+    }
+
+    /// A copy of the statistics, which other backends may be updating as we read them
+    fn entry(&self) -> StatsEntry<B> {
+        StatsEntry {
+            count: self.count.load(Ordering::Relaxed),
+            total: f64::from_bits(self.total.load(Ordering::Relaxed)),
+            histogram: std::array::from_fn(|i| self.histogram[i].load(Ordering::Relaxed)),
+        }
+    }
+
+    fn store(&self, entry: &StatsEntry<B>) {
+        self.count.store(entry.count, Ordering::Relaxed);
+        self.total.store(entry.total.to_bits(), Ordering::Relaxed);
+        for (count, saved) in self.histogram.iter().zip(entry.histogram) {
+            count.store(saved, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The shared memory behind a [`PgStats`]: a hash table of `N` slots, which are never removed
+/// until the statistics are reset.
+///
+/// Backends find and update slots while holding its lock in shared mode, so only resetting and
+/// loading the statistics needs it exclusively.
+pub struct StatsTable<K, const N: usize, const B: usize> {
+    slots: [StatsSlot<K, B>; N],
+}
+
+impl<K, const N: usize, const B: usize> Default for StatsTable<K, N, B> {
+    fn default() -> Self {
+        StatsTable { slots: [(); N].map(|_| StatsSlot::default()) }
+    }
+}
+
+unsafe impl<K: StatsKey, const N: usize, const B: usize> PGRXSharedMemory for StatsTable<K, N, B> {}
+
+impl<K: StatsKey, const N: usize, const B: usize> StatsTable<K, N, B> {
+    /// The slot holding `key`, claiming an empty one for it if `insert`.  There's no such slot if
+    /// the key is new and every slot is taken.
+    fn slot(&self, key: K, insert: bool) -> Option<&StatsSlot<K, B>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let mut index = hasher.finish() as usize % N;
+
+        let mut probes = 0;
+        while probes < N {
+            let slot = &self.slots[index];
+            match slot.key() {
+                Some(existing) if existing == key => return Some(slot),
+                Some(_) => {}
+                // as slots are never removed, `key` would have been in this one or an earlier one
+                None if !insert => return None,
+                None if slot.claim(key) => return Some(slot),
+                // another backend took the slot first, perhaps for the same key, so look again
+                None => continue,
+            }
+            index = (index + 1) % N;
+            probes += 1;
+        }
+        None
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (K, StatsEntry<B>)> + '_ {
+        self.slots.iter().filter_map(|slot| Some((slot.key()?, slot.entry())))
+    }
+
+    fn clear(&mut self) {
+        for slot in &mut self.slots {
+            *slot = StatsSlot::default();
+        }
+    }
+}
+
+/// Statistics for up to `N` keys of type `K`, each with a histogram of `B` buckets.  See the
+/// [module documentation](self).
+///
+/// Once `N` distinct keys have been seen, events for new keys are ignored until the statistics
+/// are reset.
+pub struct PgStats<K, const N: usize, const B: usize = 0> {
+    table: PgLwLock<StatsTable<K, N, B>>,
+    bounds: [f64; B],
+    file: Option<&'static str>,
+}
+
+impl<K, const N: usize> PgStats<K, N> {
+    /// Statistics that only count events
+    pub const fn new() -> Self {
+        PgStats { table: PgLwLock::new(), bounds: [], file: None }
+    }
+}
+
+impl<K, const N: usize> Default for PgStats<K, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, const N: usize, const B: usize> PgStats<K, N, B> {
+    /// Statistics that also sort observed values into a histogram.  Each of the ascending
+    /// `bounds` is the inclusive upper bound of its bucket, and values above the last bound are
+    /// counted but left out of the histogram, unless it's `f64::INFINITY`.
+    pub const fn with_histogram(bounds: [f64; B]) -> Self {
+        PgStats { table: PgLwLock::new(), bounds, file: None }
+    }
+
+    /// Saves these statistics to `path`, relative to the data directory, when Postgres shuts down
+    /// cleanly, and loads them from there when it starts.  As with Postgres' own statistics,
+    /// they start out empty again after a crash.
+    pub const fn persist(mut self, path: &'static str) -> Self {
+        self.file = Some(path);
+        self
+    }
+}
+
+impl<K: StatsKey, const N: usize, const B: usize> PgStats<K, N, B> {
+    /// Counts `n` events for `key`
+    pub fn add(&self, key: K, n: i64) {
+        let table = self.table.share();
+        if let Some(slot) = table.slot(key, true) {
+            slot.count.fetch_add(n, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts one event for `key`
+    pub fn increment(&self, key: K) {
+        self.add(key, 1)
+    }
+
+    /// Counts one event for `key`, adding `value` to its total and histogram
+    pub fn observe(&self, key: K, value: f64) {
+        let bucket = self.bounds.iter().position(|bound| value <= *bound);
+        let table = self.table.share();
+        if let Some(slot) = table.slot(key, true) {
+            slot.count.fetch_add(1, Ordering::Relaxed);
+            slot.add_total(value);
+            if let Some(bucket) = bucket {
+                slot.histogram[bucket].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The statistics gathered for `key`, if any
+    pub fn get(&self, key: &K) -> Option<StatsEntry<B>> {
+        self.table.share().slot(*key, false).map(StatsSlot::entry)
+    }
+
+    /// A copy of the statistics gathered for every key
+    pub fn entries(&self) -> Vec<(K, StatsEntry<B>)> {
+        self.table.share().iter().collect()
+    }
+
+    /// The inclusive upper bounds of the histogram's buckets
+    pub fn bounds(&self) -> &[f64; B] {
+        &self.bounds
+    }
+
+    /// Forgets the statistics gathered for every key
+    pub fn reset(&self) {
+        self.table.exclusive().clear();
+    }
+
+    /// Writes the statistics gathered for every key to the file at `path`, which is relative to
+    /// the data directory, replacing it.
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let temp = format!("{path}.tmp");
+        let result = std::fs::File::create(&temp)
+            .and_then(|mut file| {
+                file.write_all(&self.serialize())?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&temp, path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result
+    }
+
+    /// Replaces the statistics of each key in the file at `path`, which [`PgStats::save`] wrote,
+    /// with the ones saved there.
+    pub fn load(&self, path: &str) -> std::io::Result<()> {
+        let bytes = std::fs::read(path)?;
+        let entries = self.deserialize(&bytes).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid statistics file")
+        })?;
+
+        let table = self.table.exclusive();
+        for (key, entry) in entries {
+            if let Some(slot) = table.slot(key, true) {
+                slot.store(&entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// A header describing the layout of each entry, followed by each key's bytes and its entry
+    fn serialize(&self) -> Vec<u8> {
+        let entries = self.entries();
+        let mut bytes = Vec::new();
+        bytes.extend(FILE_HEADER.to_ne_bytes());
+        bytes.extend((K::SIZE as u32).to_ne_bytes());
+        bytes.extend((B as u32).to_ne_bytes());
+        bytes.extend((entries.len() as u64).to_ne_bytes());
+        for (key, entry) in entries {
+            key.save(&mut bytes);
+            bytes.extend(entry.count.to_ne_bytes());
+            bytes.extend(entry.total.to_ne_bytes());
+            for count in entry.histogram {
+                bytes.extend(count.to_ne_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Option<Vec<(K, StatsEntry<B>)>> {
+        fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+            if bytes.len() < len {
+                return None;
+            }
+            let (taken, rest) = bytes.split_at(len);
+            *bytes = rest;
+            Some(taken)
+        }
+        fn take_u32(bytes: &mut &[u8]) -> Option<u32> {
+            Some(u32::from_ne_bytes(take(bytes, 4)?.try_into().unwrap()))
+        }
+        fn take_u64(bytes: &mut &[u8]) -> Option<u64> {
+            Some(u64::from_ne_bytes(take(bytes, 8)?.try_into().unwrap()))
+        }
+
+        let mut bytes = bytes;
+        if take_u32(&mut bytes)? != FILE_HEADER
+            || take_u32(&mut bytes)? != K::SIZE as u32
+            || take_u32(&mut bytes)? != B as u32
+        {
+            return None;
+        }
+
+        let len = take_u64(&mut bytes)?;
+        let mut entries = Vec::new();
+        for _ in 0..len {
+            let key = K::load(take(&mut bytes, K::SIZE)?);
+            let mut entry = StatsEntry::<B> {
+                count: take_u64(&mut bytes)? as i64,
+                total: f64::from_bits(take_u64(&mut bytes)?),
+                histogram: [0; B],
+            };
+            for count in &mut entry.histogram {
+                *count = take_u64(&mut bytes)? as i64;
+            }
+            entries.push((key, entry));
+        }
+        bytes.is_empty().then_some(entries)
+    }
+
+    /// Loads the statistics saved at the last clean shutdown, if any
+    fn load_on_startup(&self, path: &str) {
+        match self.load(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                warning!("ignoring invalid statistics file \"{path}\"")
+            }
+            Err(e) => {
+                warning!("could not read statistics file \"{path}\": {e}");
+                return;
+            }
+        }
+
+        // don't load them again if we crash
+        if let Err(e) = std::fs::remove_file(path) {
+            warning!("could not remove statistics file \"{path}\": {e}");
+        }
+    }
+}
+
+impl<K: StatsKey, const N: usize, const B: usize> PgSharedMemoryInitialization
+    for PgStats<K, N, B>
+{
+    fn pg_init(&'static self) {
+        PgSharedMem::pg_init_locked(&self.table);
+    }
+
+    fn shmem_init(&'static self) {
+        PgSharedMem::shmem_init_locked(&self.table);
+
+        // only the postmaster loads and saves the statistics, as it outlives every backend
+        if let Some(path) = self.file {
+            unsafe {
+                if !pg_sys::IsUnderPostmaster {
+                    self.load_on_startup(path);
+                    pg_sys::on_shmem_exit(
+                        Some(save_on_exit::<K, N, B>),
+                        pg_sys::Datum::from(self as *const Self as usize),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn save_on_exit<K: StatsKey, const N: usize, const B: usize>(
+    code: std::os::raw::c_int,
+    arg: pg_sys::Datum,
+) {
+    // as with Postgres' own statistics, there's nothing worth saving after a crash
+    if code != 0 {
+        return;
+    }
+
+    let stats = &*arg.cast_mut_ptr::<PgStats<K, N, B>>();
+    if let Some(path) = stats.file {
+        if let Err(e) = stats.save(path) {
+            warning!("could not write statistics file \"{path}\": {e}");
+        }
+    }
+}
+
+/// The SQL for the reset function of [`pg_stats!`], which only superusers may call until they
+/// grant it to others
+#[doc(hidden)]
+pub fn reset_function_sql(
+    entity: &SqlGraphEntity,
+    context: &PgrxSql,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let SqlGraphEntity::Function(function) = entity else {
+        return Err(format!("expected a function, got {entity:?}").into());
+    };
+    let schema = match function.schema {
+        Some(schema) => format!("{schema}."),
+        None => context.schema_prefix_for(&context.externs[function]),
+    };
+    Ok(format!(
+        "{create}\n\
+        REVOKE ALL ON FUNCTION {schema}\"{name}\"() FROM PUBLIC;\n",
+        create = function.to_sql(context)?,
+        name = function.name,
+    ))
+}
+
+/// Declares a static [`PgStats`], along with a SQL view of its statistics and a SQL function that
+/// resets them.  See the [module documentation](crate::stats).
+///
+/// The view is a set-returning function with a row for each key, of its `key`, `count`, `total`
+/// and `histogram`.  The key's type must be usable as a SQL type.  As with
+/// `pg_stat_statements_reset()`, only superusers may reset the statistics unless they grant
+/// `EXECUTE` on the reset function to others.
+#[macro_export]
+macro_rules! pg_stats {
+    (
+        $(#[$meta:meta])*
+        $vis:vis static $name:ident: PgStats<$key:ty, $n:tt $(, $b:tt)?> = $init:expr;
+        view = $view:ident;
+        reset = $reset:ident;
+    ) => {
+        $(#[$meta])*
+        $vis static $name: $crate::stats::PgStats<$key, $n $(, $b)?> = $init;
+
+        #[$crate::pg_extern]
+        fn $view() -> $crate::iter::TableIterator<
+            'static,
+            (
+                $crate::name!(key, $key),
+                $crate::name!(count, i64),
+                $crate::name!(total, f64),
+                $crate::name!(histogram, Vec<i64>),
+            ),
+        > {
+            $crate::iter::TableIterator::new($name.entries().into_iter().map(|(key, entry)| {
+                (key, entry.count, entry.total, entry.histogram.to_vec())
+            }))
+        }
+
+        #[$crate::pg_extern(sql = $crate::stats::reset_function_sql)]
+        fn $reset() {
+            $name.reset()
+        }
+    };
+}
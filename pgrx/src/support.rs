@@ -0,0 +1,370 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Support for writing [planner support functions](https://www.postgresql.org/docs/current/xfunc-optimization.html)
+//!
+//! A support function is attached to another function with `#[pg_extern(support = my_support)]`.
+//! It receives a [`SupportRequest`] and answers the requests it understands, returning a null
+//! [`Internal`] for the others.
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::Internal;
+//!
+//! #[pg_extern]
+//! fn numbers_support(request: SupportRequest) -> Internal {
+//!     match request {
+//!         // `numbers(n)` returns exactly `n` rows
+//!         SupportRequest::Rows(rows) => match rows.const_arg::<i32>(0) {
+//!             Some(n) => rows.set_rows(n as f64),
+//!             None => Internal::default(),
+//!         },
+//!         _ => Internal::default(),
+//!     }
+//! }
+//!
+//! #[pg_extern(support = numbers_support)]
+//! fn numbers(n: i32) -> SetOfIterator<'static, i32> {
+//!     SetOfIterator::new(1..=n)
+//! }
+//! ```
+use crate::{is_a, pg_sys, FromDatum, Internal, IntoDatum, PgList};
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+
+/// A request Postgres' planner makes of a support function
+pub enum SupportRequest {
+    /// Replace a call to the function with a simpler expression
+    Simplify(SimplifyRequest),
+    /// Estimate the selectivity of a boolean function used as a qualifier
+    Selectivity(SelectivityRequest),
+    /// Estimate the cost of executing the function
+    Cost(CostRequest),
+    /// Estimate the number of rows a set-returning function returns
+    Rows(RowsRequest),
+    /// Derive index conditions from a qualifier using the function
+    IndexCondition(IndexConditionRequest),
+    /// A request this version of pgrx does not know how to represent
+    Other(*mut pg_sys::Node),
+}
+
+impl SupportRequest {
+    /// Create a [`SupportRequest`] from the node a support function is passed.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a valid pointer to one of Postgres' `SupportRequest*` nodes
+    pub unsafe fn from_pg(node: *mut pg_sys::Node) -> Self {
+        if is_a(node, pg_sys::NodeTag_T_SupportRequestSimplify) {
+            SupportRequest::Simplify(SimplifyRequest(node.cast()))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestSelectivity) {
+            SupportRequest::Selectivity(SelectivityRequest(node.cast()))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestCost) {
+            SupportRequest::Cost(CostRequest(node.cast()))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestRows) {
+            SupportRequest::Rows(RowsRequest(node.cast()))
+        } else if is_a(node, pg_sys::NodeTag_T_SupportRequestIndexCondition) {
+            SupportRequest::IndexCondition(IndexConditionRequest(node.cast()))
+        } else {
+            SupportRequest::Other(node)
+        }
+    }
+}
+
+/// A `SupportRequestSimplify`
+pub struct SimplifyRequest(*mut pg_sys::SupportRequestSimplify);
+
+impl SimplifyRequest {
+    /// The underlying `SupportRequestSimplify` node
+    pub fn as_ptr(&self) -> *mut pg_sys::SupportRequestSimplify {
+        self.0
+    }
+
+    /// The function being called
+    pub fn func_oid(&self) -> pg_sys::Oid {
+        unsafe { (*(*self.0).fcall).funcid }
+    }
+
+    /// The arguments of the call being simplified
+    pub fn args(&self) -> PgList<pg_sys::Node> {
+        unsafe { PgList::from_pg((*(*self.0).fcall).args) }
+    }
+
+    /// The value of argument `n`, if it is a non-NULL constant of a type `T` can represent
+    pub fn const_arg<T: FromDatum + IntoDatum>(&self, n: usize) -> Option<T> {
+        const_arg(&self.args(), n)
+    }
+
+    /// Replace the function call with a constant of the function's result type
+    ///
+    /// Returns `None`, leaving the call alone, if `T` isn't compatible with that result type.
+    pub fn simplify_to_const<T: IntoDatum>(self, value: Option<T>) -> Option<Internal> {
+        unsafe {
+            let fcall = (*self.0).fcall;
+            if !T::is_compatible_with((*fcall).funcresulttype) {
+                return None;
+            }
+            let mut typlen = 0;
+            let mut typbyval = false;
+            pg_sys::get_typlenbyval((*fcall).funcresulttype, &mut typlen, &mut typbyval);
+            let datum = value.and_then(|value| value.into_datum());
+            let constant = pg_sys::makeConst(
+                (*fcall).funcresulttype,
+                -1,
+                (*fcall).funccollid,
+                typlen as _,
+                datum.unwrap_or(pg_sys::Datum::from(0)),
+                datum.is_none(),
+                typbyval,
+            );
+            Some(self.replace_with(constant.cast()))
+        }
+    }
+
+    /// Replace the function call with the expression `node`
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a valid expression node which evaluates to the function's result type
+    pub unsafe fn replace_with(self, node: *mut pg_sys::Node) -> Internal {
+        Internal::from(Some(pg_sys::Datum::from(node)))
+    }
+}
+
+/// A `SupportRequestSelectivity`
+pub struct SelectivityRequest(*mut pg_sys::SupportRequestSelectivity);
+
+impl SelectivityRequest {
+    /// The underlying `SupportRequestSelectivity` node
+    pub fn as_ptr(&self) -> *mut pg_sys::SupportRequestSelectivity {
+        self.0
+    }
+
+    /// The function being called
+    pub fn func_oid(&self) -> pg_sys::Oid {
+        unsafe { (*self.0).funcid }
+    }
+
+    /// Is the selectivity being estimated for a join clause?
+    pub fn is_join(&self) -> bool {
+        unsafe { (*self.0).is_join }
+    }
+
+    /// The arguments of the call being estimated
+    pub fn args(&self) -> PgList<pg_sys::Node> {
+        unsafe { PgList::from_pg((*self.0).args) }
+    }
+
+    /// The value of argument `n`, if it is a non-NULL constant of a type `T` can represent
+    pub fn const_arg<T: FromDatum + IntoDatum>(&self, n: usize) -> Option<T> {
+        const_arg(&self.args(), n)
+    }
+
+    /// Answer the request with the fraction of rows, between `0.0` and `1.0`, the qualifier passes
+    pub fn set_selectivity(self, selectivity: f64) -> Internal {
+        unsafe {
+            (*self.0).selectivity = selectivity.clamp(0.0, 1.0);
+        }
+        request_answered(self.0)
+    }
+}
+
+/// A `SupportRequestCost`
+pub struct CostRequest(*mut pg_sys::SupportRequestCost);
+
+impl CostRequest {
+    /// The underlying `SupportRequestCost` node
+    pub fn as_ptr(&self) -> *mut pg_sys::SupportRequestCost {
+        self.0
+    }
+
+    /// The function being called
+    pub fn func_oid(&self) -> pg_sys::Oid {
+        unsafe { (*self.0).funcid }
+    }
+
+    /// The arguments of the call being estimated.  This is empty if the planner is asking about
+    /// the function in general, rather than a specific call
+    pub fn args(&self) -> PgList<pg_sys::Node> {
+        unsafe { PgList::from_pg(node_args((*self.0).node)) }
+    }
+
+    /// The value of argument `n`, if it is a non-NULL constant of a type `T` can represent
+    pub fn const_arg<T: FromDatum + IntoDatum>(&self, n: usize) -> Option<T> {
+        const_arg(&self.args(), n)
+    }
+
+    /// Answer the request with the one-time startup cost and the cost per call or per row
+    /// returned, in the planner's usual cost units
+    pub fn set_cost(self, startup: f64, per_tuple: f64) -> Internal {
+        unsafe {
+            (*self.0).startup = startup;
+            (*self.0).per_tuple = per_tuple;
+        }
+        request_answered(self.0)
+    }
+}
+
+/// A `SupportRequestRows`
+pub struct RowsRequest(*mut pg_sys::SupportRequestRows);
+
+impl RowsRequest {
+    /// The underlying `SupportRequestRows` node
+    pub fn as_ptr(&self) -> *mut pg_sys::SupportRequestRows {
+        self.0
+    }
+
+    /// The function being called
+    pub fn func_oid(&self) -> pg_sys::Oid {
+        unsafe { (*self.0).funcid }
+    }
+
+    /// The arguments of the call being estimated
+    pub fn args(&self) -> PgList<pg_sys::Node> {
+        unsafe { PgList::from_pg(node_args((*self.0).node)) }
+    }
+
+    /// The value of argument `n`, if it is a non-NULL constant of a type `T` can represent
+    pub fn const_arg<T: FromDatum + IntoDatum>(&self, n: usize) -> Option<T> {
+        const_arg(&self.args(), n)
+    }
+
+    /// Answer the request with the number of rows the function returns
+    pub fn set_rows(self, rows: f64) -> Internal {
+        unsafe {
+            (*self.0).rows = rows;
+        }
+        request_answered(self.0)
+    }
+}
+
+/// A `SupportRequestIndexCondition`
+pub struct IndexConditionRequest(*mut pg_sys::SupportRequestIndexCondition);
+
+impl IndexConditionRequest {
+    /// The underlying `SupportRequestIndexCondition` node
+    pub fn as_ptr(&self) -> *mut pg_sys::SupportRequestIndexCondition {
+        self.0
+    }
+
+    /// The function being called
+    pub fn func_oid(&self) -> pg_sys::Oid {
+        unsafe { (*self.0).funcid }
+    }
+
+    /// The arguments of the qualifier
+    pub fn args(&self) -> PgList<pg_sys::Node> {
+        unsafe { PgList::from_pg(node_args((*self.0).node)) }
+    }
+
+    /// The value of argument `n`, if it is a non-NULL constant of a type `T` can represent
+    pub fn const_arg<T: FromDatum + IntoDatum>(&self, n: usize) -> Option<T> {
+        const_arg(&self.args(), n)
+    }
+
+    /// Which of the arguments matched the index column
+    pub fn index_arg(&self) -> usize {
+        unsafe { (*self.0).indexarg as usize }
+    }
+
+    /// The index being considered
+    pub fn index_oid(&self) -> pg_sys::Oid {
+        unsafe { (*(*self.0).index).indexoid }
+    }
+
+    /// Which column of the index matched, starting at zero
+    pub fn index_column(&self) -> usize {
+        unsafe { (*self.0).indexcol as usize }
+    }
+
+    /// The operator family of the index column
+    pub fn opfamily(&self) -> pg_sys::Oid {
+        unsafe { (*self.0).opfamily }
+    }
+
+    /// The collation of the index column
+    pub fn index_collation(&self) -> pg_sys::Oid {
+        unsafe { (*self.0).indexcollation }
+    }
+
+    /// Answer the request with index conditions derived from the qualifier.  If `lossy` is true
+    /// the original qualifier is rechecked against the rows the index returns.
+    ///
+    /// # Safety
+    ///
+    /// Each condition must be a valid, planner-ready `OpExpr` (or other expression) usable with
+    /// the index column's operator family
+    pub unsafe fn set_conditions(
+        self,
+        conditions: Vec<*mut pg_sys::Expr>,
+        lossy: bool,
+    ) -> Internal {
+        if conditions.is_empty() {
+            return Internal::default();
+        }
+        let mut list = PgList::<pg_sys::Expr>::new();
+        for condition in conditions {
+            list.push(condition);
+        }
+        (*self.0).lossy = lossy;
+        Internal::from(Some(pg_sys::Datum::from(list.into_pg())))
+    }
+}
+
+fn request_answered<T>(request: *mut T) -> Internal {
+    Internal::from(Some(pg_sys::Datum::from(request)))
+}
+
+unsafe fn node_args(node: *mut pg_sys::Node) -> *mut pg_sys::List {
+    if is_a(node, pg_sys::NodeTag_T_FuncExpr) {
+        (*node.cast::<pg_sys::FuncExpr>()).args
+    } else if is_a(node, pg_sys::NodeTag_T_OpExpr) {
+        (*node.cast::<pg_sys::OpExpr>()).args
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
+fn const_arg<T: FromDatum + IntoDatum>(args: &PgList<pg_sys::Node>, n: usize) -> Option<T> {
+    let arg = args.get_ptr(n)?;
+    unsafe {
+        if !is_a(arg, pg_sys::NodeTag_T_Const) {
+            return None;
+        }
+        // SAFETY:  try_from_datum() checks the constant's type against `T` before reading it
+        let constant = arg.cast::<pg_sys::Const>();
+        T::try_from_datum((*constant).constvalue, (*constant).constisnull, (*constant).consttype)
+            .ok()
+            .flatten()
+    }
+}
+
+impl FromDatum for SupportRequest {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else {
+            Some(SupportRequest::from_pg(datum.cast_mut_ptr()))
+        }
+    }
+}
+
+unsafe impl SqlTranslatable for SupportRequest {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("internal"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("internal")))
+    }
+}
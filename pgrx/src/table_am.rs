@@ -0,0 +1,907 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Safe support for writing Postgres Table Access Methods
+//!
+//! Implement [`TableAccessMethod`] for a type and decorate the `impl` block with `#[pg_table_am]`.
+//! pgrx will generate the `table_am_handler` function along with the
+//! `CREATE ACCESS METHOD ... TYPE TABLE` statement for you.  Tables are then created with
+//! `CREATE TABLE ... USING`.
+//!
+//! A new instance of the implementing type is created for every sequential scan (see
+//! [`TableAccessMethod::begin_scan`]).  Everything else is an associated function which is handed
+//! the relation it operates on.  Rows move in and out of the access method through [`TupleSlot`]s.
+//!
+//! Only the parts of the table access method API needed to scan and modify a table are exposed.
+//! Everything else (index builds, `CLUSTER`, sample and bitmap scans, parallel scans, row locking,
+//! and so on) raises an error when Postgres asks for it.
+//!
+//! Table access methods are only available on Postgres 12 and later.
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::datum::TryFromDatumError;
+use crate::heap_tuple::PgHeapTupleError;
+use crate::prelude::*;
+use crate::{pg_sys, PgMemoryContexts, PgRelation, PgTupleDesc};
+use std::marker::PhantomData;
+use std::num::NonZeroUsize;
+
+/// The planner's size estimate for a table, as returned by [`TableAccessMethod::estimate_size`]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TableSizeEstimate {
+    pub pages: pg_sys::BlockNumber,
+    pub tuples: f64,
+    pub allvisfrac: f64,
+}
+
+/// A safe wrapper around a Postgres `TupleTableSlot`, the executor's container for a single row
+pub struct TupleSlot<'a> {
+    slot: *mut pg_sys::TupleTableSlot,
+    _marker: PhantomData<&'a mut pg_sys::TupleTableSlot>,
+}
+
+impl<'a> TupleSlot<'a> {
+    /// Wrap a `TupleTableSlot` provided by Postgres
+    ///
+    /// ## Safety
+    ///
+    /// `slot` must be a valid, non-null `TupleTableSlot` which outlives the returned `TupleSlot`
+    pub unsafe fn from_pg(slot: *mut pg_sys::TupleTableSlot) -> Self {
+        assert!(!slot.is_null(), "TupleTableSlot is null");
+        TupleSlot { slot, _marker: PhantomData }
+    }
+
+    /// Returns the underlying `TupleTableSlot` pointer
+    pub fn as_ptr(&self) -> *mut pg_sys::TupleTableSlot {
+        self.slot
+    }
+
+    /// The tuple descriptor describing this slot's attributes
+    pub fn tuple_desc(&self) -> PgTupleDesc<'_> {
+        unsafe { PgTupleDesc::from_pg_unchecked((*self.slot).tts_tupleDescriptor) }
+    }
+
+    /// Does the slot not currently hold a row?
+    pub fn is_empty(&self) -> bool {
+        unsafe { (*self.slot).tts_flags as u32 & pg_sys::TTS_FLAG_EMPTY != 0 }
+    }
+
+    /// Remove the row currently held by the slot, if any
+    pub fn clear(&mut self) {
+        unsafe {
+            pg_sys::ExecClearTuple(self.slot);
+        }
+    }
+
+    /// The location of the row held by the slot
+    pub fn tid(&self) -> pg_sys::ItemPointerData {
+        unsafe { (*self.slot).tts_tid }
+    }
+
+    /// Set the location of the row held by the slot.  Access methods should do this after
+    /// storing a row in the slot during scans and inserts.
+    pub fn set_tid(&mut self, tid: pg_sys::ItemPointerData) {
+        unsafe {
+            (*self.slot).tts_tid = tid;
+        }
+    }
+
+    /// Replace the slot's contents with a new row made from an iterator of Datums.
+    ///
+    /// By-reference values are copied into the slot's own memory, so they needn't outlive this call.
+    ///
+    /// ## Errors
+    /// - [PgHeapTupleError::IncorrectAttributeCount] if the number of items in the iterator
+    /// does not match the number of attributes in the slot's tuple descriptor.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe as we cannot guarantee the provided [`pg_sys::Datum`]s are valid
+    /// as the slot's tuple descriptor might expect
+    pub unsafe fn store_datums<I: IntoIterator<Item = Option<pg_sys::Datum>>>(
+        &mut self,
+        datums: I,
+    ) -> Result<(), PgHeapTupleError> {
+        let datums = datums.into_iter().collect::<Vec<_>>();
+        let natts = (*(*self.slot).tts_tupleDescriptor).natts as usize;
+        if datums.len() != natts {
+            return Err(PgHeapTupleError::IncorrectAttributeCount(datums.len(), natts));
+        }
+
+        pg_sys::ExecClearTuple(self.slot);
+        for (i, datum) in datums.into_iter().enumerate() {
+            *(*self.slot).tts_isnull.add(i) = datum.is_none();
+            *(*self.slot).tts_values.add(i) = datum.unwrap_or(0.into());
+        }
+        pg_sys::ExecStoreVirtualTuple(self.slot);
+        pg_sys::ExecMaterializeSlot(self.slot);
+        Ok(())
+    }
+
+    /// Retrieve the value of the specified attribute, by name.
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeName`] if the attribute does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type of the `value` is not
+    /// compatible with the attribute's Postgres type
+    pub fn get_by_name<T: FromDatum + IntoDatum + 'static>(
+        &mut self,
+        attname: &str,
+    ) -> Result<Option<T>, TryFromDatumError> {
+        let attnum =
+            self.tuple_desc().iter().find(|att| att.name() == attname).map(|att| att.attnum);
+        match attnum {
+            Some(attnum) => self.get_by_index(NonZeroUsize::new(attnum as usize).unwrap()),
+            None => Err(TryFromDatumError::NoSuchAttributeName(attname.to_owned())),
+        }
+    }
+
+    /// Retrieve the value of the specified attribute, by index.
+    ///
+    /// Attribute numbers start at 1, not 0.
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeNumber`] if the attribute does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type of the `value` is not
+    /// compatible with the attribute's Postgres type
+    pub fn get_by_index<T: FromDatum + IntoDatum + 'static>(
+        &mut self,
+        attno: NonZeroUsize,
+    ) -> Result<Option<T>, TryFromDatumError> {
+        let type_oid = match self.tuple_desc().get(attno.get() - 1) {
+            // it's an attribute number outside the bounds of the tuple descriptor
+            None => return Err(TryFromDatumError::NoSuchAttributeNumber(attno)),
+            Some(att) => att.type_oid().value(),
+        };
+
+        unsafe {
+            let mut is_null = false;
+            let datum = pg_sys::slot_getattr(self.slot, attno.get() as i32, &mut is_null);
+            match T::type_oid() {
+                record @ pg_sys::RECORDOID => T::try_from_datum(datum, is_null, record),
+                _ => T::try_from_datum(datum, is_null, type_oid),
+            }
+        }
+    }
+}
+
+/// A safe wrapper around the Postgres `TableScanDesc` a sequential scan was started with
+pub struct TableScan<'a> {
+    desc: &'a pg_sys::TableScanDescData,
+}
+
+impl<'a> TableScan<'a> {
+    /// The relation being scanned
+    pub fn relation(&self) -> PgRelation {
+        unsafe { PgRelation::from_pg(self.desc.rs_rd) }
+    }
+
+    /// The snapshot the scan should see rows through
+    pub fn snapshot(&self) -> pg_sys::Snapshot {
+        self.desc.rs_snapshot
+    }
+
+    /// The keys the scan was started (or restarted) with.  Rows not matching them may be skipped.
+    pub fn keys(&self) -> &[pg_sys::ScanKeyData] {
+        if self.desc.rs_key.is_null() || self.desc.rs_nkeys <= 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.desc.rs_key, self.desc.rs_nkeys as usize) }
+        }
+    }
+
+    /// The scan's `ScanOptions` flags, such as `SO_TYPE_SEQSCAN` or `SO_TYPE_ANALYZE`
+    pub fn flags(&self) -> u32 {
+        self.desc.rs_flags
+    }
+}
+
+/// A Postgres Table Access Method.
+///
+/// Use `#[pg_table_am]` on the `impl` block to generate the handler function and SQL.
+pub trait TableAccessMethod: Sized {
+    /// The kind of slot the executor should use for this access method's rows.
+    ///
+    /// The default is a virtual slot, which [`TupleSlot::store_datums`] fills directly.
+    fn slot_callbacks(_rel: &PgRelation) -> *const pg_sys::TupleTableSlotOps {
+        unsafe { &pg_sys::TTSOpsVirtual }
+    }
+
+    /// Start a new sequential scan
+    fn begin_scan(scan: &TableScan) -> Self;
+
+    /// Restart the scan from the beginning, possibly with new keys
+    fn rescan(&mut self, _scan: &TableScan) {}
+
+    /// Store the next row in `slot`, returning `false` when the scan is finished
+    fn get_next_slot(&mut self, direction: pg_sys::ScanDirection, slot: &mut TupleSlot) -> bool;
+
+    /// Called when the scan is finished.  Any resources not cleaned up here are released when
+    /// `Self` is dropped.
+    fn end_scan(&mut self) {}
+
+    /// Insert the row held by `slot`
+    fn insert(_rel: &PgRelation, _slot: &mut TupleSlot) {
+        error!("this table access method does not support INSERT")
+    }
+
+    /// Insert many rows at once, such as during `COPY`.
+    ///
+    /// The default implementation calls [`TableAccessMethod::insert`] for each slot.
+    fn multi_insert(rel: &PgRelation, slots: &mut [TupleSlot]) {
+        for slot in slots {
+            Self::insert(rel, slot);
+        }
+    }
+
+    /// Store the row at `tid` in `slot`, returning `false` if there's no such row
+    fn fetch_row_version(
+        _rel: &PgRelation,
+        _tid: pg_sys::ItemPointerData,
+        _slot: &mut TupleSlot,
+    ) -> bool {
+        error!("this table access method does not support fetching rows by ctid")
+    }
+
+    /// Delete the row at `tid`
+    fn delete(_rel: &PgRelation, _tid: pg_sys::ItemPointerData) {
+        error!("this table access method does not support DELETE")
+    }
+
+    /// Replace the row at `tid` with the row held by `slot`
+    fn update(_rel: &PgRelation, _tid: pg_sys::ItemPointerData, _slot: &mut TupleSlot) {
+        error!("this table access method does not support UPDATE")
+    }
+
+    /// Create empty storage for `rel`.  Called by `CREATE TABLE`, and again whenever a
+    /// transactional `TRUNCATE` (or similar) gives the table a new relfilenode.
+    fn create_storage(_rel: &PgRelation, _persistence: std::os::raw::c_char) {}
+
+    /// Remove every row from `rel`, for a `TRUNCATE` in the same transaction that created it
+    fn truncate(_rel: &PgRelation) {
+        error!("this table access method does not support TRUNCATE")
+    }
+
+    /// The size of the given fork of `rel`, in bytes
+    fn relation_size(_rel: &PgRelation, _fork: pg_sys::ForkNumber) -> u64 {
+        0
+    }
+
+    /// Does `rel` need a TOAST table?
+    fn needs_toast_table(_rel: &PgRelation) -> bool {
+        false
+    }
+
+    /// Estimate the size of `rel` for the planner
+    fn estimate_size(_rel: &PgRelation) -> TableSizeEstimate {
+        TableSizeEstimate::default()
+    }
+
+    /// Called by `VACUUM`
+    fn vacuum(_rel: &PgRelation) {}
+}
+
+/// Build the `TableAmRoutine` for the [`TableAccessMethod`] `T`.
+///
+/// This is what the handler function generated by `#[pg_table_am]` returns.  Postgres holds on to
+/// a pointer to the routine for as long as the backend lives, so the handler builds it once and
+/// keeps it in a `static`.
+pub fn table_am_routine<T: TableAccessMethod>() -> pg_sys::TableAmRoutine {
+    let mut routine =
+        pg_sys::TableAmRoutine { type_: pg_sys::NodeTag_T_TableAmRoutine, ..Default::default() };
+
+    routine.slot_callbacks = Some(slot_callbacks::<T>);
+    routine.scan_begin = Some(scan_begin::<T>);
+    routine.scan_end = Some(scan_end::<T>);
+    routine.scan_rescan = Some(scan_rescan::<T>);
+    routine.scan_getnextslot = Some(scan_getnextslot::<T>);
+
+    routine.parallelscan_estimate = Some(parallelscan_estimate);
+    routine.parallelscan_initialize = Some(parallelscan_initialize);
+    routine.parallelscan_reinitialize = Some(parallelscan_reinitialize);
+
+    routine.index_fetch_begin = Some(index_fetch_begin);
+    routine.index_fetch_reset = Some(index_fetch_reset);
+    routine.index_fetch_end = Some(index_fetch_end);
+    routine.index_fetch_tuple = Some(index_fetch_tuple);
+
+    routine.tuple_fetch_row_version = Some(tuple_fetch_row_version::<T>);
+    routine.tuple_tid_valid = Some(tuple_tid_valid);
+    routine.tuple_get_latest_tid = Some(tuple_get_latest_tid);
+    routine.tuple_satisfies_snapshot = Some(tuple_satisfies_snapshot);
+    #[cfg(any(feature = "pg12", feature = "pg13"))]
+    {
+        routine.compute_xid_horizon_for_tuples = Some(compute_xid_horizon_for_tuples);
+    }
+    #[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16"))]
+    {
+        routine.index_delete_tuples = Some(index_delete_tuples);
+    }
+
+    routine.tuple_insert = Some(tuple_insert::<T>);
+    routine.tuple_insert_speculative = Some(tuple_insert_speculative);
+    routine.tuple_complete_speculative = Some(tuple_complete_speculative);
+    routine.multi_insert = Some(multi_insert::<T>);
+    routine.tuple_delete = Some(tuple_delete::<T>);
+    routine.tuple_update = Some(tuple_update::<T>);
+    routine.tuple_lock = Some(tuple_lock);
+
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    {
+        routine.relation_set_new_filenode = Some(relation_set_new_storage::<T>);
+    }
+    #[cfg(feature = "pg16")]
+    {
+        routine.relation_set_new_filelocator = Some(relation_set_new_storage::<T>);
+    }
+    routine.relation_nontransactional_truncate = Some(relation_nontransactional_truncate::<T>);
+    routine.relation_copy_data = Some(relation_copy_data);
+    routine.relation_copy_for_cluster = Some(relation_copy_for_cluster);
+    routine.relation_vacuum = Some(relation_vacuum::<T>);
+    routine.scan_analyze_next_block = Some(scan_analyze_next_block);
+    routine.scan_analyze_next_tuple = Some(scan_analyze_next_tuple);
+    routine.index_build_range_scan = Some(index_build_range_scan);
+    routine.index_validate_scan = Some(index_validate_scan);
+
+    routine.relation_size = Some(relation_size::<T>);
+    routine.relation_needs_toast_table = Some(relation_needs_toast_table::<T>);
+    routine.relation_estimate_size = Some(relation_estimate_size::<T>);
+
+    routine.scan_bitmap_next_block = Some(scan_bitmap_next_block);
+    routine.scan_bitmap_next_tuple = Some(scan_bitmap_next_tuple);
+    routine.scan_sample_next_block = Some(scan_sample_next_block);
+    routine.scan_sample_next_tuple = Some(scan_sample_next_tuple);
+
+    routine
+}
+
+/// Our scan descriptor.  Postgres only ever sees the `base` field, which must come first.
+///
+/// The state lives in the scan's memory context, which drops it if the scan is aborted by an
+/// `ERROR`.  [`scan_end`] drops it sooner and leaves `None` behind.
+#[repr(C)]
+struct ScanDesc<T> {
+    base: pg_sys::TableScanDescData,
+    state: *mut Option<T>,
+}
+
+impl<T> ScanDesc<T> {
+    #[inline]
+    unsafe fn state<'a>(&self) -> &'a mut T {
+        (*self.state).as_mut().expect("table scan has ended")
+    }
+}
+
+#[inline]
+unsafe fn scan_desc<'a, T>(scan: pg_sys::TableScanDesc) -> &'a mut ScanDesc<T> {
+    scan.cast::<ScanDesc<T>>().as_mut().expect("table scan was not started")
+}
+
+fn unsupported(what: &str) -> ! {
+    error!("this table access method does not support {}", what)
+}
+
+#[pg_guard]
+unsafe extern "C" fn slot_callbacks<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+) -> *const pg_sys::TupleTableSlotOps {
+    T::slot_callbacks(&PgRelation::from_pg(rel))
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_begin<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    snapshot: pg_sys::Snapshot,
+    nkeys: i32,
+    key: *mut pg_sys::ScanKeyData,
+    pscan: pg_sys::ParallelTableScanDesc,
+    flags: u32,
+) -> pg_sys::TableScanDesc {
+    // keep our own copy of the keys, like every built-in access method does
+    let rs_key = if nkeys > 0 && !key.is_null() {
+        let copy = pg_sys::palloc(std::mem::size_of::<pg_sys::ScanKeyData>() * nkeys as usize)
+            .cast::<pg_sys::ScanKeyData>();
+        std::ptr::copy(key, copy, nkeys as usize);
+        copy
+    } else {
+        std::ptr::null_mut()
+    };
+
+    let base = pg_sys::TableScanDescData {
+        rs_rd: rel,
+        rs_snapshot: snapshot,
+        rs_nkeys: nkeys,
+        rs_key,
+        rs_flags: flags,
+        rs_parallel: pscan,
+        ..Default::default()
+    };
+    let state = T::begin_scan(&TableScan { desc: &base });
+    let state = PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(Some(state));
+    let desc = PgMemoryContexts::CurrentMemoryContext.palloc_struct::<ScanDesc<T>>();
+    desc.write(ScanDesc { base, state });
+    desc.cast()
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_end<T: TableAccessMethod>(scan: pg_sys::TableScanDesc) {
+    let desc = scan_desc::<T>(scan);
+    if let Some(mut state) = (*desc.state).take() {
+        state.end_scan();
+    }
+    if !desc.base.rs_key.is_null() {
+        pg_sys::pfree(desc.base.rs_key.cast());
+    }
+    pg_sys::pfree(scan.cast());
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_rescan<T: TableAccessMethod>(
+    scan: pg_sys::TableScanDesc,
+    key: *mut pg_sys::ScanKeyData,
+    _set_params: bool,
+    _allow_strat: bool,
+    _allow_sync: bool,
+    _allow_pagemode: bool,
+) {
+    let desc = scan_desc::<T>(scan);
+    if !key.is_null() && !desc.base.rs_key.is_null() {
+        std::ptr::copy(key, desc.base.rs_key, desc.base.rs_nkeys as usize);
+    }
+    desc.state().rescan(&TableScan { desc: &desc.base });
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_getnextslot<T: TableAccessMethod>(
+    scan: pg_sys::TableScanDesc,
+    direction: pg_sys::ScanDirection,
+    slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    let desc = scan_desc::<T>(scan);
+    if desc.state().get_next_slot(direction, &mut TupleSlot::from_pg(slot)) {
+        (*slot).tts_tableOid = (*desc.base.rs_rd).rd_id;
+        true
+    } else {
+        pg_sys::ExecClearTuple(slot);
+        false
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn parallelscan_estimate(_rel: pg_sys::Relation) -> pg_sys::Size {
+    unsupported("parallel scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn parallelscan_initialize(
+    _rel: pg_sys::Relation,
+    _pscan: pg_sys::ParallelTableScanDesc,
+) -> pg_sys::Size {
+    unsupported("parallel scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn parallelscan_reinitialize(
+    _rel: pg_sys::Relation,
+    _pscan: pg_sys::ParallelTableScanDesc,
+) {
+    unsupported("parallel scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn index_fetch_begin(_rel: pg_sys::Relation) -> *mut pg_sys::IndexFetchTableData {
+    unsupported("index scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn index_fetch_reset(_data: *mut pg_sys::IndexFetchTableData) {
+    unsupported("index scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn index_fetch_end(_data: *mut pg_sys::IndexFetchTableData) {
+    unsupported("index scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn index_fetch_tuple(
+    _scan: *mut pg_sys::IndexFetchTableData,
+    _tid: pg_sys::ItemPointer,
+    _snapshot: pg_sys::Snapshot,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _call_again: *mut bool,
+    _all_dead: *mut bool,
+) -> bool {
+    unsupported("index scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_fetch_row_version<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    tid: pg_sys::ItemPointer,
+    _snapshot: pg_sys::Snapshot,
+    slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    if T::fetch_row_version(&PgRelation::from_pg(rel), *tid, &mut TupleSlot::from_pg(slot)) {
+        (*slot).tts_tableOid = (*rel).rd_id;
+        true
+    } else {
+        false
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_tid_valid(
+    _scan: pg_sys::TableScanDesc,
+    _tid: pg_sys::ItemPointer,
+) -> bool {
+    true
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_get_latest_tid(_scan: pg_sys::TableScanDesc, _tid: pg_sys::ItemPointer) {
+    // rows are updated in place, so `tid` is already the latest version
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_satisfies_snapshot(
+    _rel: pg_sys::Relation,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _snapshot: pg_sys::Snapshot,
+) -> bool {
+    true
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn compute_xid_horizon_for_tuples(
+    _rel: pg_sys::Relation,
+    _items: *mut pg_sys::ItemPointerData,
+    _nitems: i32,
+) -> pg_sys::TransactionId {
+    unsupported("indexes")
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15", feature = "pg16"))]
+#[pg_guard]
+unsafe extern "C" fn index_delete_tuples(
+    _rel: pg_sys::Relation,
+    _delstate: *mut pg_sys::TM_IndexDeleteOp,
+) -> pg_sys::TransactionId {
+    unsupported("indexes")
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_insert<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _options: i32,
+    _bistate: *mut pg_sys::BulkInsertStateData,
+) {
+    (*slot).tts_tableOid = (*rel).rd_id;
+    T::insert(&PgRelation::from_pg(rel), &mut TupleSlot::from_pg(slot));
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_insert_speculative(
+    _rel: pg_sys::Relation,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _options: i32,
+    _bistate: *mut pg_sys::BulkInsertStateData,
+    _spec_token: u32,
+) {
+    unsupported("INSERT ... ON CONFLICT")
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_complete_speculative(
+    _rel: pg_sys::Relation,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _spec_token: u32,
+    _succeeded: bool,
+) {
+    unsupported("INSERT ... ON CONFLICT")
+}
+
+#[pg_guard]
+unsafe extern "C" fn multi_insert<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    slots: *mut *mut pg_sys::TupleTableSlot,
+    nslots: i32,
+    _cid: pg_sys::CommandId,
+    _options: i32,
+    _bistate: *mut pg_sys::BulkInsertStateData,
+) {
+    let mut slots = std::slice::from_raw_parts(slots, nslots as usize)
+        .iter()
+        .map(|slot| {
+            (**slot).tts_tableOid = (*rel).rd_id;
+            TupleSlot::from_pg(*slot)
+        })
+        .collect::<Vec<_>>();
+    T::multi_insert(&PgRelation::from_pg(rel), &mut slots);
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_delete<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    tid: pg_sys::ItemPointer,
+    _cid: pg_sys::CommandId,
+    _snapshot: pg_sys::Snapshot,
+    _crosscheck: pg_sys::Snapshot,
+    _wait: bool,
+    _tmfd: *mut pg_sys::TM_FailureData,
+    _changing_part: bool,
+) -> pg_sys::TM_Result {
+    T::delete(&PgRelation::from_pg(rel), *tid);
+    pg_sys::TM_Result_TM_Ok
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn tuple_update<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    otid: pg_sys::ItemPointer,
+    slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _snapshot: pg_sys::Snapshot,
+    _crosscheck: pg_sys::Snapshot,
+    _wait: bool,
+    _tmfd: *mut pg_sys::TM_FailureData,
+    lockmode: *mut pg_sys::LockTupleMode,
+    update_indexes: *mut bool,
+) -> pg_sys::TM_Result {
+    tuple_update_internal::<T>(rel, otid, slot, lockmode);
+    *update_indexes = true;
+    pg_sys::TM_Result_TM_Ok
+}
+
+#[cfg(feature = "pg16")]
+#[pg_guard]
+unsafe extern "C" fn tuple_update<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    otid: pg_sys::ItemPointer,
+    slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _snapshot: pg_sys::Snapshot,
+    _crosscheck: pg_sys::Snapshot,
+    _wait: bool,
+    _tmfd: *mut pg_sys::TM_FailureData,
+    lockmode: *mut pg_sys::LockTupleMode,
+    update_indexes: *mut pg_sys::TU_UpdateIndexes,
+) -> pg_sys::TM_Result {
+    tuple_update_internal::<T>(rel, otid, slot, lockmode);
+    *update_indexes = pg_sys::TU_UpdateIndexes_TU_All;
+    pg_sys::TM_Result_TM_Ok
+}
+
+unsafe fn tuple_update_internal<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    otid: pg_sys::ItemPointer,
+    slot: *mut pg_sys::TupleTableSlot,
+    lockmode: *mut pg_sys::LockTupleMode,
+) {
+    (*slot).tts_tableOid = (*rel).rd_id;
+    T::update(&PgRelation::from_pg(rel), *otid, &mut TupleSlot::from_pg(slot));
+    *lockmode = pg_sys::LockTupleMode_LockTupleExclusive;
+}
+
+#[pg_guard]
+unsafe extern "C" fn tuple_lock(
+    _rel: pg_sys::Relation,
+    _tid: pg_sys::ItemPointer,
+    _snapshot: pg_sys::Snapshot,
+    _slot: *mut pg_sys::TupleTableSlot,
+    _cid: pg_sys::CommandId,
+    _mode: pg_sys::LockTupleMode,
+    _wait_policy: pg_sys::LockWaitPolicy,
+    _flags: u8,
+    _tmfd: *mut pg_sys::TM_FailureData,
+) -> pg_sys::TM_Result {
+    unsupported("row locking")
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn relation_set_new_storage<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    _newrnode: *const pg_sys::RelFileNode,
+    persistence: std::os::raw::c_char,
+    freeze_xid: *mut pg_sys::TransactionId,
+    minmulti: *mut pg_sys::MultiXactId,
+) {
+    relation_set_new_storage_internal::<T>(rel, persistence, freeze_xid, minmulti)
+}
+
+#[cfg(feature = "pg16")]
+#[pg_guard]
+unsafe extern "C" fn relation_set_new_storage<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    _newrlocator: *const pg_sys::RelFileLocator,
+    persistence: std::os::raw::c_char,
+    freeze_xid: *mut pg_sys::TransactionId,
+    minmulti: *mut pg_sys::MultiXactId,
+) {
+    relation_set_new_storage_internal::<T>(rel, persistence, freeze_xid, minmulti)
+}
+
+unsafe fn relation_set_new_storage_internal<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    persistence: std::os::raw::c_char,
+    freeze_xid: *mut pg_sys::TransactionId,
+    minmulti: *mut pg_sys::MultiXactId,
+) {
+    // we don't store transaction ids, so there's never anything for VACUUM to freeze
+    *freeze_xid = pg_sys::InvalidTransactionId;
+    *minmulti = pg_sys::InvalidMultiXactId;
+    T::create_storage(&PgRelation::from_pg(rel), persistence);
+}
+
+#[pg_guard]
+unsafe extern "C" fn relation_nontransactional_truncate<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+) {
+    T::truncate(&PgRelation::from_pg(rel));
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn relation_copy_data(
+    _rel: pg_sys::Relation,
+    _newrnode: *const pg_sys::RelFileNode,
+) {
+    unsupported("ALTER TABLE ... SET TABLESPACE")
+}
+
+#[cfg(feature = "pg16")]
+#[pg_guard]
+unsafe extern "C" fn relation_copy_data(
+    _rel: pg_sys::Relation,
+    _newrlocator: *const pg_sys::RelFileLocator,
+) {
+    unsupported("ALTER TABLE ... SET TABLESPACE")
+}
+
+#[pg_guard]
+unsafe extern "C" fn relation_copy_for_cluster(
+    _new_table: pg_sys::Relation,
+    _old_table: pg_sys::Relation,
+    _old_index: pg_sys::Relation,
+    _use_sort: bool,
+    _oldest_xmin: pg_sys::TransactionId,
+    _xid_cutoff: *mut pg_sys::TransactionId,
+    _multi_cutoff: *mut pg_sys::MultiXactId,
+    _num_tuples: *mut f64,
+    _tups_vacuumed: *mut f64,
+    _tups_recently_dead: *mut f64,
+) {
+    unsupported("CLUSTER or VACUUM FULL")
+}
+
+#[pg_guard]
+unsafe extern "C" fn relation_vacuum<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    _params: *mut pg_sys::VacuumParams,
+    _bstrategy: pg_sys::BufferAccessStrategy,
+) {
+    T::vacuum(&PgRelation::from_pg(rel));
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_analyze_next_block(
+    _scan: pg_sys::TableScanDesc,
+    _blockno: pg_sys::BlockNumber,
+    _bstrategy: pg_sys::BufferAccessStrategy,
+) -> bool {
+    // there are no blocks for ANALYZE to sample
+    false
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_analyze_next_tuple(
+    _scan: pg_sys::TableScanDesc,
+    _oldest_xmin: pg_sys::TransactionId,
+    _liverows: *mut f64,
+    _deadrows: *mut f64,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    false
+}
+
+#[pg_guard]
+unsafe extern "C" fn index_build_range_scan(
+    _table_rel: pg_sys::Relation,
+    _index_rel: pg_sys::Relation,
+    _index_info: *mut pg_sys::IndexInfo,
+    _allow_sync: bool,
+    _anyvisible: bool,
+    _progress: bool,
+    _start_blockno: pg_sys::BlockNumber,
+    _numblocks: pg_sys::BlockNumber,
+    _callback: pg_sys::IndexBuildCallback,
+    _callback_state: *mut std::os::raw::c_void,
+    _scan: pg_sys::TableScanDesc,
+) -> f64 {
+    unsupported("indexes")
+}
+
+#[pg_guard]
+unsafe extern "C" fn index_validate_scan(
+    _table_rel: pg_sys::Relation,
+    _index_rel: pg_sys::Relation,
+    _index_info: *mut pg_sys::IndexInfo,
+    _snapshot: pg_sys::Snapshot,
+    _state: *mut pg_sys::ValidateIndexState,
+) {
+    unsupported("indexes")
+}
+
+#[pg_guard]
+unsafe extern "C" fn relation_size<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    fork_number: pg_sys::ForkNumber,
+) -> u64 {
+    T::relation_size(&PgRelation::from_pg(rel), fork_number)
+}
+
+#[pg_guard]
+unsafe extern "C" fn relation_needs_toast_table<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+) -> bool {
+    T::needs_toast_table(&PgRelation::from_pg(rel))
+}
+
+#[pg_guard]
+unsafe extern "C" fn relation_estimate_size<T: TableAccessMethod>(
+    rel: pg_sys::Relation,
+    _attr_widths: *mut i32,
+    pages: *mut pg_sys::BlockNumber,
+    tuples: *mut f64,
+    allvisfrac: *mut f64,
+) {
+    let estimate = T::estimate_size(&PgRelation::from_pg(rel));
+    *pages = estimate.pages;
+    *tuples = estimate.tuples;
+    *allvisfrac = estimate.allvisfrac;
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_bitmap_next_block(
+    _scan: pg_sys::TableScanDesc,
+    _tbmres: *mut pg_sys::TBMIterateResult,
+) -> bool {
+    unsupported("bitmap scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_bitmap_next_tuple(
+    _scan: pg_sys::TableScanDesc,
+    _tbmres: *mut pg_sys::TBMIterateResult,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    unsupported("bitmap scans")
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_sample_next_block(
+    _scan: pg_sys::TableScanDesc,
+    _scanstate: *mut pg_sys::SampleScanState,
+) -> bool {
+    unsupported("TABLESAMPLE")
+}
+
+#[pg_guard]
+unsafe extern "C" fn scan_sample_next_tuple(
+    _scan: pg_sys::TableScanDesc,
+    _scanstate: *mut pg_sys::SampleScanState,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    unsupported("TABLESAMPLE")
+}
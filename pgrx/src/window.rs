@@ -0,0 +1,267 @@
+//LICENSE Portions Copyright 2019-2021 ZomboDB, LLC.
+//LICENSE
+//LICENSE Portions Copyright 2021-2023 Technology Concepts & Design, Inc.
+//LICENSE
+//LICENSE Portions Copyright 2023-2023 PgCentral Foundation, Inc. <contact@pgcentral.org>
+//LICENSE
+//LICENSE All rights reserved.
+//LICENSE
+//LICENSE Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+//! Support for writing Postgres [window functions](https://www.postgresql.org/docs/current/xfunc-c.html#XFUNC-C-WINDOW)
+//!
+//! A `#[pg_extern(window)]` function is declared to Postgres as `CREATE FUNCTION ... WINDOW`.  It
+//! may accept a [`WindowContext`] argument, which is not part of the SQL signature, and through
+//! which it can look at any row of the current partition and keep Rust state for the lifetime
+//! of that partition.  Its other arguments are evaluated against the current row.
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::window::{WindowContext, WindowSeek};
+//!
+//! /// Returns how much `v` changed since the previous row in the partition
+//! #[pg_extern(window)]
+//! fn delta(mut window: WindowContext, v: i32) -> Option<i32> {
+//!     let previous = window.arg_in_partition::<i32>(0, -1, WindowSeek::Current, false).unwrap()??;
+//!     Some(v - previous)
+//! }
+//! ```
+use crate::{pg_sys, FromDatum, IntoDatum, PgMemoryContexts, TryFromDatumError};
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::num::NonZeroUsize;
+
+/// The position from which a relative row offset is measured
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WindowSeek {
+    /// Relative to the current row
+    Current,
+    /// Relative to the first row of the partition (or frame)
+    Head,
+    /// Relative to the last row of the partition (or frame)
+    Tail,
+}
+
+impl WindowSeek {
+    fn as_c_int(self) -> std::os::raw::c_int {
+        (match self {
+            WindowSeek::Current => pg_sys::WINDOW_SEEK_CURRENT,
+            WindowSeek::Head => pg_sys::WINDOW_SEEK_HEAD,
+            WindowSeek::Tail => pg_sys::WINDOW_SEEK_TAIL,
+        }) as _
+    }
+}
+
+/// Safe access to the [`pg_sys::WindowObject`] a window function is called with
+pub struct WindowContext<'fcx> {
+    fcinfo: pg_sys::FunctionCallInfo,
+    winobj: pg_sys::WindowObject,
+    __marker: PhantomData<&'fcx mut pg_sys::WindowObjectData>,
+}
+
+/// Per-partition state, stored in the memory returned by `WinGetPartitionLocalMemory()`
+#[repr(C)]
+struct PartitionState {
+    state: *mut std::os::raw::c_void,
+    type_id: MaybeUninit<TypeId>,
+}
+
+impl<'fcx> WindowContext<'fcx> {
+    /// Create a [`WindowContext`] from the [`pg_sys::FunctionCallInfo`] of a function Postgres
+    /// is executing as a window function.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure `fcinfo` is a valid pointer that outlives `'fcx`.
+    ///
+    /// # Panics
+    ///
+    /// If the function wasn't called as a window function
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> WindowContext<'fcx> {
+        let context = fcinfo.as_ref().unwrap().context;
+        if !crate::is_a(context, pg_sys::NodeTag_T_WindowObjectData) {
+            panic!("function was not called as a window function")
+        }
+        WindowContext { fcinfo, winobj: context.cast(), __marker: PhantomData }
+    }
+
+    /// The underlying [`pg_sys::WindowObject`]
+    pub fn as_ptr(&self) -> pg_sys::WindowObject {
+        self.winobj
+    }
+
+    /// The position of the current row within its partition, starting at zero
+    pub fn current_position(&self) -> i64 {
+        unsafe { pg_sys::WinGetCurrentPosition(self.winobj) }
+    }
+
+    /// The total number of rows in the current partition
+    ///
+    /// Note that this requires Postgres to read the entire partition.
+    pub fn partition_row_count(&self) -> i64 {
+        unsafe { pg_sys::WinGetPartitionRowCount(self.winobj) }
+    }
+
+    /// Tell Postgres that rows before `position` will no longer be requested, allowing it to
+    /// discard them
+    pub fn set_mark_position(&self, position: i64) {
+        unsafe { pg_sys::WinSetMarkPosition(self.winobj, position) }
+    }
+
+    /// Are the rows at the two positions peers according to the window's `ORDER BY` clause?
+    pub fn rows_are_peers(&self, position1: i64, position2: i64) -> bool {
+        unsafe { pg_sys::WinRowsArePeers(self.winobj, position1, position2) }
+    }
+
+    /// Evaluate the window function's argument `argno` (zero-based, excluding this
+    /// [`WindowContext`]) against the current row
+    ///
+    /// Returns [`TryFromDatumError::NoSuchAttributeNumber`] if there is no such argument, and
+    /// [`TryFromDatumError::IncompatibleTypes`] if `T` can't represent its SQL type.
+    pub fn arg_current<T: FromDatum + IntoDatum>(
+        &self,
+        argno: usize,
+    ) -> Result<Option<T>, TryFromDatumError> {
+        let type_oid = self.arg_type(argno)?;
+        let mut isnull = false;
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgCurrent(self.winobj, argno as _, &mut isnull);
+            T::try_from_datum(datum, isnull, type_oid)
+        }
+    }
+
+    /// Evaluate the window function's argument `argno` against the row `relpos` rows away from
+    /// `seek` within the current partition.
+    ///
+    /// Returns `None` if that row lies outside the partition, otherwise `Some` with the
+    /// (possibly NULL) argument value.  If `set_mark` is true, rows before the one fetched are
+    /// marked as no longer needed.  Errors are the same as for [`WindowContext::arg_current`].
+    pub fn arg_in_partition<T: FromDatum + IntoDatum>(
+        &mut self,
+        argno: usize,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Result<Option<Option<T>>, TryFromDatumError> {
+        let type_oid = self.arg_type(argno)?;
+        let mut isnull = false;
+        let mut isout = false;
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgInPartition(
+                self.winobj,
+                argno as _,
+                relpos,
+                seek.as_c_int(),
+                set_mark,
+                &mut isnull,
+                &mut isout,
+            );
+            (!isout).then(|| T::try_from_datum(datum, isnull, type_oid)).transpose()
+        }
+    }
+
+    /// Like [`WindowContext::arg_in_partition`], but only considers rows within the current
+    /// row's window frame
+    pub fn arg_in_frame<T: FromDatum + IntoDatum>(
+        &mut self,
+        argno: usize,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Result<Option<Option<T>>, TryFromDatumError> {
+        let type_oid = self.arg_type(argno)?;
+        let mut isnull = false;
+        let mut isout = false;
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgInFrame(
+                self.winobj,
+                argno as _,
+                relpos,
+                seek.as_c_int(),
+                set_mark,
+                &mut isnull,
+                &mut isout,
+            );
+            (!isout).then(|| T::try_from_datum(datum, isnull, type_oid)).transpose()
+        }
+    }
+
+    /// Rust state that lives as long as the current partition.
+    ///
+    /// The state is created with [`Default::default()`] the first time it's requested for a
+    /// partition, and dropped once Postgres moves on to the next partition.
+    ///
+    /// # Panics
+    ///
+    /// If the state was previously requested as a different type for this partition
+    pub fn partition_state<S: Default + 'static>(&mut self) -> &mut S {
+        unsafe {
+            let memory = pg_sys::WinGetPartitionLocalMemory(
+                self.winobj,
+                std::mem::size_of::<PartitionState>(),
+            );
+            // SAFETY:  Postgres zeroes this memory when it's first allocated for the partition,
+            // which is a valid `PartitionState` with a null `state`
+            let partition = memory.cast::<PartitionState>().as_mut().unwrap();
+            if partition.state.is_null() {
+                // the state is dropped when Postgres resets the memory context holding the
+                // partition local memory, which it does at the end of each partition
+                let context = pg_sys::GetMemoryContextChunk(memory);
+                let state = PgMemoryContexts::For(context).leak_and_drop_on_delete(S::default());
+                partition.type_id.write(TypeId::of::<S>());
+                partition.state = state.cast();
+            } else if partition.type_id.assume_init() != TypeId::of::<S>() {
+                panic!("partition state was already created as a different type")
+            }
+            partition.state.cast::<S>().as_mut().unwrap()
+        }
+    }
+
+    /// The SQL type of argument `argno`, which must be checked before Postgres is asked for it
+    fn arg_type(&self, argno: usize) -> Result<pg_sys::Oid, TryFromDatumError> {
+        // SAFETY:  self.fcinfo is the valid pointer we were called with.  Postgres returns
+        // InvalidOid for an argument number that is out of range
+        match unsafe { crate::pg_getarg_type(self.fcinfo, argno) } {
+            pg_sys::InvalidOid => {
+                Err(TryFromDatumError::NoSuchAttributeNumber(NonZeroUsize::new(argno + 1).unwrap()))
+            }
+            type_oid => Ok(type_oid),
+        }
+    }
+}
+
+/// Fetch argument `argno` of a window function for the current row.
+///
+/// This is used by `#[pg_extern(window)]` as window functions aren't passed their arguments in
+/// the usual way.
+///
+/// # Safety
+///
+/// The caller must ensure `fcinfo` is a valid pointer, and that `T` is compatible with the
+/// argument's SQL type.
+#[doc(hidden)]
+pub unsafe fn pg_getarg_current<T: FromDatum>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    argno: usize,
+) -> Option<T> {
+    let window = WindowContext::from_fcinfo(fcinfo);
+    let mut isnull = false;
+    let datum = pg_sys::WinGetFuncArgCurrent(window.winobj, argno as _, &mut isnull);
+    if T::GET_TYPOID {
+        T::from_polymorphic_datum(datum, isnull, crate::pg_getarg_type(fcinfo, argno))
+    } else {
+        T::from_datum(datum, isnull)
+    }
+}
+
+unsafe impl<'fcx> SqlTranslatable for WindowContext<'fcx> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::Skip)
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::Skip))
+    }
+}